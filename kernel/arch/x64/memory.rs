@@ -109,6 +109,24 @@ pub fn map(address: Page, pages: usize, flags: Addr) {
 	}
 }
 
+/// Changes the flags of mapped pages, keeping the physical pages they map to
+pub fn protect(address: Page, pages: usize, flags: Addr) {
+	let ops = &mut *LOCK.lock();
+	for i in 0..pages {
+		let page = Page::new(address.ptr() + i * PAGE_SIZE);
+
+		let page_entry = get_page_entry(ops, page);
+
+		unsafe {
+			if entry_present(*page_entry) {
+				*page_entry = page_table_entry(physical_page_from_table_entry(*page_entry), flags);
+
+				invalidate_page(page);
+			}
+		}
+	}
+}
+
 pub fn unmap(address: Page, pages: usize) {
 	let ops = &mut *LOCK.lock();
 	for i in 0..pages {
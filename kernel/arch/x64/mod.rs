@@ -2,6 +2,9 @@ use util::FixVec;
 use std;
 use std::fmt;
 use elfloader::{self, Image, elf};
+use verifier_core::image::{self, Selection};
+use verifier_core::stack;
+use verifier_core::policy::Policy;
 
 #[cfg(multiboot)]
 pub mod multiboot;
@@ -228,20 +231,46 @@ pub unsafe fn initialize(st: &::memory::initial::State) {
 
 	let bin = get_user_elf();
 
+	let report = image::verify_image(&bin, &[], &Policy::default(), &Selection::default(), image::verify_each).unwrap_or_else(|error| panic!("unable to verify the user program: {}", error));
+
+	for function in report.functions.iter().filter(|f| !f.passed()) {
+		println!("rejected {} at {:#x}: {}", function.name, function.address, function.failure.as_ref().unwrap());
+	}
+
+	let entry = bin.header.unwrap().entry;
+
+	let index = report.functions.iter().position(|f| f.address == entry && f.passed()).unwrap_or_else(|| {
+		panic!("the user program entry point {:#x} is not a verified function", entry)
+	});
+
+	// The program runs on the CPU stack, so its deepest call chain has to fit there
+	let stack_size = u64::coerce(cpu::current().arch.stack.end - cpu::current().arch.stack.start);
+
+	match stack::worst_case(&report.functions)[index] {
+		Ok(depth) if depth <= stack_size => println!("user program stack depth {:#x} of {:#x}", depth, stack_size),
+		Ok(depth) => panic!("the user program needs {:#x} bytes of stack, but there are only {:#x}", depth, stack_size),
+		Err(error) => panic!("the user program stack depth is unbounded: {}", error),
+	}
+
 	bin.load(|header, data| {
-		println!("loading program header {} EXEC:{}", header, header.flags.0 & elf::PF_X.0 != 0);
+		let exec = header.flags.0 & elf::PF_X.0 != 0;
+		println!("loading program header {} EXEC:{}", header, exec);
+		image::check_segment(&bin, &report, header, data)?;
 		let pos = usize::coerce(header.vaddr);
 		let size = usize::coerce(header.memsz);
 		let pos_aligned = align_down(pos, PAGE_SIZE);
 		let size_aligned = align_up(pos + size, PAGE_SIZE) - pos_aligned;
 		process.space.lock().alloc_at(pos_aligned, size);
-		memory::map(Page::new(process.arch.base + pos_aligned), size_aligned / PAGE_SIZE, memory::WRITE_BIT | memory::PRESENT_BIT);
+		let page = Page::new(process.arch.base + pos_aligned);
+		memory::map(page, size_aligned / PAGE_SIZE, memory::WRITE_BIT | memory::PRESENT_BIT);
 		std::ptr::copy_nonoverlapping(data.as_ptr(), (process.arch.base + pos) as *mut u8, data.len());
 		std::ptr::write_bytes((process.arch.base + pos + data.len()) as *mut u8, 0, size - data.len());
+		// Verified code must stay unchanged and only verified code may run
+		memory::protect(page, size_aligned / PAGE_SIZE, if exec { memory::PRESENT_BIT } else { memory::RW_DATA_FLAGS });
 		Ok(())
-	});
+	}).unwrap_or_else(|error| panic!("unable to load the user program: {}", error));
 
-	let entry = process.arch.base + usize::coerce(bin.header.unwrap().entry);
+	let entry = process.arch.base + usize::coerce(entry);
 
 	println!("program entry point: {:x} stack {:x}", entry, cpu::current().arch.stack.end);
 
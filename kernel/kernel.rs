@@ -18,6 +18,7 @@ extern crate allocator;
 
 extern crate rlibc;
 extern crate elfloader;
+extern crate verifier_core;
 extern crate alloc;
 extern crate collections;
 
@@ -0,0 +1,162 @@
+use collections::vec::Vec;
+use byteorder::{ByteOrder, LittleEndian};
+use x86_decoder::{Context, Cursor, DecoderError, FunctionState, Inst, Operation, Parts, Reg, Rm};
+use matcher::{NoMatch, Rex, rex, expect, modrm};
+
+/// The name of the section holding the indirect call table
+pub const CALLS_SECTION: &'static str = ".avery.calls";
+
+/// The size of an entry in the indirect call table. Indirect call targets are aligned to this.
+pub const SLOT_SIZE: u64 = 8;
+
+/// The indirect call table emitted by the isolation pass.
+///
+/// Each slot is either `jmp rel32` to a valid entry point padded with `int3`, or is filled with `int3`.
+#[derive(Copy, Clone, Debug)]
+pub struct CallTable {
+	pub start: u64,
+	pub size: u64,
+}
+
+/// Checks that every slot of the call table in `data` at `start` jumps to an address in the sorted `entries`, or traps.
+pub fn check_table(start: u64, data: &[u8], entries: &[u64]) -> Result<CallTable, &'static str> {
+	if data.is_empty() || data.len() as u64 % SLOT_SIZE != 0 {
+		return Err("indirect call table size is not a multiple of the slot size");
+	}
+
+	for (i, slot) in data.chunks(SLOT_SIZE as usize).enumerate() {
+		if slot.iter().all(|&b| b == 0xCC) {
+			continue;
+		}
+
+		if slot[0] != 0xE9 || slot[5..].iter().any(|&b| b != 0xCC) {
+			return Err("indirect call table slot is not a jump");
+		}
+
+		let target = (start + i as u64 * SLOT_SIZE + 5).wrapping_add(LittleEndian::read_i32(&slot[1..5]) as i64 as u64);
+
+		if entries.binary_search(&target).is_err() {
+			return Err("indirect call table entry is not a valid entry point");
+		}
+	}
+
+	Ok(CallTable {
+		start: start,
+		size: data.len() as u64,
+	})
+}
+
+/// A matched indirect call. Offsets are from the start of the function.
+struct Call {
+	mask: u64,
+	table: i64,
+	/// Registers written by the sequence
+	clobbers: Vec<Reg>,
+}
+
+/// Matches the sequence the isolation pass emits for indirect calls:
+///
+/// ```text
+/// and target32, mask
+/// lea table, [rip + .avery.calls]
+/// add target, table
+/// call target
+/// ```
+///
+/// The 32-bit `and` clears the upper bits of the target, so it ends up at an aligned offset into the call table.
+fn parse(c: &mut Cursor) -> Result<Call, NoMatch> {
+	// and target32, imm8 | imm32
+	let r = rex(c)?;
+	let op = c.next()?;
+	let (mode, ext, rm) = modrm(c, Rex { r: 0, ..r })?;
+	expect(!r.w && mode == 3 && ext.0 == 4)?;
+	let target = Reg(rm | r.b << 3);
+	let mask = match op {
+		0x83 => c.next()? as i8 as i32,
+		0x81 => c.next_u32()? as i32,
+		_ => return Err(NoMatch),
+	};
+	expect(mask >= 0)?;
+
+	// lea table, [rip + disp32]
+	let r = rex(c)?;
+	expect(r.w && c.next()? == 0x8D)?;
+	let (mode, table, rm) = modrm(c, r)?;
+	expect(mode == 0 && rm == 5 && table != target)?;
+	let disp = c.next_u32()? as i32 as i64;
+	let table_offset = c.offset as i64 + disp;
+
+	// add target, table
+	let r = rex(c)?;
+	expect(r.w)?;
+	let op = c.next()?;
+	let (mode, reg, rm) = modrm(c, r)?;
+	let rm = Reg(rm | r.b << 3);
+	expect(mode == 3 && match op {
+		0x01 => rm == target && reg == table,
+		0x03 => reg == target && rm == table,
+		_ => false,
+	})?;
+
+	// call target
+	let r = rex(c)?;
+	expect(c.next()? == 0xFF)?;
+	let (mode, ext, rm) = modrm(c, Rex { r: 0, ..r })?;
+	expect(mode == 3 && ext.0 == 2 && Reg(rm | r.b << 3) == target)?;
+
+	// The stack pointer can't be used as a temporary
+	expect(target != Reg(4) && table != Reg(4))?;
+
+	Ok(Call {
+		mask: mask as u64,
+		table: table_offset,
+		clobbers: vec![target, table],
+	})
+}
+
+/// Recognizes an indirect call which is masked into the call table of `ctx` at the cursor.
+/// The whole sequence is returned as a single call instruction.
+/// Returns `None` and leaves the cursor unchanged if the code doesn't match.
+pub fn recognize(c: &mut Cursor, parts: &Parts, state: &mut FunctionState, ctx: &Context) -> Result<Option<(Inst, usize, usize)>, DecoderError> {
+	let start = c.offset;
+
+	let call = match parse(c) {
+		Ok(call) => call,
+		Err(NoMatch) => {
+			c.offset = start;
+			return Ok(None);
+		}
+	};
+
+	let table = match ctx.calls {
+		Some(table) => table,
+		None => return Err(DecoderError::InvalidIndirectCall),
+	};
+
+	// The masked offset must be aligned to a slot and the whole slot must be in the table
+	let address = parts.address(start).wrapping_add((call.table - start as i64) as u64);
+
+	if address != table.start || call.mask % SLOT_SIZE != 0 || call.mask + SLOT_SIZE > table.size {
+		return Err(DecoderError::InvalidIndirectCall);
+	}
+
+	let ops_index = state.ops.len();
+
+	for &reg in &call.clobbers {
+		state.op(Operation::ClobReg(reg))?;
+	}
+
+	let inst = Inst {
+		jmp: None,
+		rm: Rm::None,
+		term: false,
+		call: true,
+		callee: None,
+		data: None,
+		table: Vec::new(),
+		sequence: true,
+		kind: None,
+	};
+
+	Ok(Some((inst, c.offset - start, ops_index)))
+}
@@ -0,0 +1,118 @@
+use collections::vec::Vec;
+use x86_decoder::{Context, Cursor, DecoderError, FunctionState, Inst, Reg, Rm};
+use matcher::{NoMatch, Rex, rex, expect, modrm};
+
+/// Matches `add reg, imm` if `ext` is 0 or `sub reg, imm` if `ext` is 5, with a positive immediate
+fn adjust(c: &mut Cursor, reg: Reg, ext: u8) -> Result<u32, NoMatch> {
+	let r = rex(c)?;
+	let op = c.next()?;
+	let (mode, e, rm) = modrm(c, Rex { r: 0, ..r })?;
+	expect(r.w && mode == 3 && e.0 == ext && Reg(rm | r.b << 3) == reg)?;
+	let imm = match op {
+		0x83 => c.next()? as i8 as i32,
+		0x81 => c.next_u32()? as i32,
+		_ => return Err(NoMatch),
+	};
+	expect(imm > 0)?;
+	Ok(imm as u32)
+}
+
+/// Matches the prologue and returns the frame size:
+///
+/// ```text
+/// sub reg, frame
+/// or segment:[reg], 0
+/// ```
+fn prologue(c: &mut Cursor, reg: Reg, segment: u8) -> Result<u32, NoMatch> {
+	let frame = adjust(c, reg, 5)?;
+
+	// or dword | qword segment:[reg], imm8
+	expect(c.next()? == segment)?;
+	let r = rex(c)?;
+	expect(c.next()? == 0x83)?;
+	let (mode, ext, rm) = modrm(c, Rex { r: 0, ..r })?;
+	expect(ext.0 == 1 && r.x == 0 && Reg(rm | r.b << 3) == reg)?;
+	match (mode, rm) {
+		// [r12] needs a SIB byte without an index
+		(0, 4) => expect(c.next()? == 0x24)?,
+		// [rbp] and [r13] need a zero displacement
+		(1, 5) => expect(c.next()? == 0)?,
+		(0, rm) if rm != 5 => (),
+		_ => return Err(NoMatch),
+	}
+	expect(c.next()? == 0)?;
+
+	Ok(frame)
+}
+
+/// Matches the epilogue and returns the frame size:
+///
+/// ```text
+/// add reg, frame
+/// ret
+/// ```
+fn epilogue(c: &mut Cursor, reg: Reg) -> Result<u32, NoMatch> {
+	let frame = adjust(c, reg, 0)?;
+	expect(c.next()? == 0xC3)?;
+	Ok(frame)
+}
+
+/// Recognizes the data stack prologue and epilogue of the policy in `ctx` at the cursor.
+/// The prologue must be at the start of the function and its frame can't be larger than the guard page.
+/// The epilogue must release the whole frame. Data stack accesses are checked against the frame by `x86_decoder::inst_with_policy`.
+/// Returns `None` and leaves the cursor unchanged if the code doesn't match.
+pub fn recognize(c: &mut Cursor, state: &mut FunctionState, ctx: &Context) -> Result<Option<(Inst, usize, usize)>, DecoderError> {
+	let data_stack = match state.data_stack {
+		Some(data_stack) => data_stack,
+		None => return Ok(None),
+	};
+
+	let start = c.offset;
+
+	let inst = Inst {
+		jmp: None,
+		rm: Rm::None,
+		term: false,
+		call: false,
+		callee: None,
+		data: None,
+		table: Vec::new(),
+		sequence: true,
+		kind: None,
+	};
+
+	if let Ok(frame) = prologue(c, data_stack.reg, ctx.policy.segment) {
+		if start != 0 || state.data_frame.is_some() {
+			return Err(DecoderError::MisplacedDataStackPrologue);
+		}
+
+		if frame > data_stack.guard {
+			return Err(DecoderError::DataFrameTooLarge);
+		}
+
+		state.data_frame = Some(frame);
+
+		return Ok(Some((inst, c.offset - start, state.ops.len())));
+	}
+
+	c.offset = start;
+
+	if let Ok(frame) = epilogue(c, data_stack.reg) {
+		if state.data_frame != Some(frame) {
+			return Err(DecoderError::DataStackIsNotRestored);
+		}
+
+		if state.stack_offset != 0 {
+			return Err(DecoderError::StackIsNotRestored);
+		}
+
+		return Ok(Some((Inst {
+			term: true,
+			..inst
+		}, c.offset - start, state.ops.len())));
+	}
+
+	c.offset = start;
+
+	Ok(None)
+}
@@ -0,0 +1,288 @@
+use collections::vec::Vec;
+use x86_decoder::{Cursor, DecoderError, Failure, Masked, Operation, Parts, Reg, Rm, Step};
+
+/// What is known to hold at a program point.
+///
+/// Each general purpose register and each aligned 8-byte stack slot is treated as a virtual register,
+/// which is either proven to hold a value masked by the mask register or not. Facts meet by intersection
+/// at jump targets, and a jump target which has not been reached yet is the top of the lattice.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Facts {
+	regs: u16,
+	/// Masked stack slots. Slot `n` covers the 8 bytes at offset `n * 8` from the stack pointer at function entry
+	slots: Vec<i64>,
+}
+
+fn bit(r: Reg) -> u16 {
+	1 << r.0
+}
+
+/// The stack slots touched by an access
+#[derive(Copy, Clone, Debug)]
+struct Access {
+	first: i64,
+	last: i64,
+	/// The access covers whole slots
+	exact: bool,
+}
+
+impl Access {
+	fn new(step: &Step, off: i32, size: i64) -> Access {
+		// Offset from the stack pointer at function entry
+		let key = off as i64 - step.stack_offset as i64;
+		Access {
+			first: key >> 3,
+			last: (key + size - 1) >> 3,
+			exact: key & 7 == 0 && size & 7 == 0,
+		}
+	}
+
+	/// The slot accessed by an aligned 8-byte access
+	fn slot(self) -> Option<i64> {
+		if self.exact && self.first == self.last {
+			Some(self.first)
+		} else {
+			None
+		}
+	}
+}
+
+impl Facts {
+	/// Only the trusted registers are masked at function entry
+	fn entry(trusted: &[Reg]) -> Facts {
+		Facts {
+			regs: trusted.iter().fold(0, |regs, &r| regs | bit(r)),
+			slots: Vec::new(),
+		}
+	}
+
+	fn reg(&self, r: Reg) -> bool {
+		self.regs & bit(r) != 0
+	}
+
+	fn set_reg(&mut self, r: Reg, masked: bool) {
+		if masked {
+			self.regs |= bit(r);
+		} else {
+			self.regs &= !bit(r);
+		}
+	}
+
+	fn slot(&self, slot: i64) -> bool {
+		self.slots.binary_search(&slot).is_ok()
+	}
+
+	fn set_slot(&mut self, slot: i64, masked: bool) {
+		match (self.slots.binary_search(&slot), masked) {
+			(Err(i), true) => self.slots.insert(i, slot),
+			(Ok(i), false) => {
+				self.slots.remove(i);
+			}
+			_ => (),
+		}
+	}
+
+	/// Checks that an access which doesn't line up with the slots doesn't touch a masked value
+	fn check(&self, access: Access) -> Result<(), DecoderError> {
+		if !access.exact && self.slots.iter().any(|&s| s >= access.first && s <= access.last) {
+			Err(DecoderError::PartialSlotAccess)
+		} else {
+			Ok(())
+		}
+	}
+
+	fn clob(&mut self, access: Access) {
+		self.slots.retain(|&s| s < access.first || s > access.last);
+	}
+
+	/// Keeps only the facts which also hold in `other`. Returns true if anything changed.
+	fn meet(&mut self, other: &Facts) -> bool {
+		let old = self.clone();
+		self.regs &= other.regs;
+		self.slots.retain(|&s| other.slot(s));
+		*self != old
+	}
+}
+
+/// Applies the operations of `step` to `facts`.
+/// The facts are updated even if the step is rejected, so the fixpoint iteration can ignore errors.
+fn apply(facts: &mut Facts, step: &Step, ops: &[Operation], trusted: &[Reg]) -> Result<(), DecoderError> {
+	let mut result = Ok(());
+
+	for op in &ops[step.ops.0..step.ops.1] {
+		match *op {
+			Operation::ClobReg(r) => facts.set_reg(r, false),
+			Operation::ClobStack(off, size) => {
+				// SSE stores may cover multiple slots
+				let access = Access::new(step, off, size as i64);
+				result = result.and(facts.check(access));
+				facts.clob(access);
+			}
+			Operation::ReadStack(off, size) => {
+				let access = Access::new(step, off, size as i64);
+				result = result.and(facts.check(access));
+			}
+			Operation::MoveRegs(dst, src) => {
+				let masked = facts.reg(src);
+				facts.set_reg(dst, masked);
+			}
+			Operation::MoveToStack(off, src) => {
+				let access = Access::new(step, off, 8);
+				result = result.and(facts.check(access));
+				facts.clob(access);
+				if let Some(slot) = access.slot() {
+					let masked = facts.reg(src);
+					facts.set_slot(slot, masked);
+				}
+			}
+			Operation::MoveFromStack(dst, off) => {
+				let access = Access::new(step, off, 8);
+				result = result.and(facts.check(access));
+				let masked = access.slot().map(|slot| facts.slot(slot)).unwrap_or(false);
+				facts.set_reg(dst, masked);
+			}
+			// The result of an AND is masked if either operand is
+			Operation::AndRegFromReg(dst, src) => {
+				let masked = facts.reg(dst) || facts.reg(src);
+				facts.set_reg(dst, masked);
+			}
+			Operation::AndRegFromStack(dst, off) => {
+				let access = Access::new(step, off, 8);
+				result = result.and(facts.check(access));
+				let masked = facts.reg(dst) || access.slot().map(|slot| facts.slot(slot)).unwrap_or(false);
+				facts.set_reg(dst, masked);
+			}
+			Operation::AndStackFromReg(off, src) => {
+				let access = Access::new(step, off, 8);
+				result = result.and(facts.check(access));
+				let masked = access.slot().map(|slot| facts.slot(slot) || facts.reg(src));
+				facts.clob(access);
+				if let (Some(slot), Some(masked)) = (access.slot(), masked) {
+					facts.set_slot(slot, masked);
+				}
+			}
+		}
+	}
+
+	if step.call {
+		// The callee may leave anything in the registers and may write into our frame
+		*facts = Facts::entry(trusted);
+	}
+
+	// Slots below the stack pointer can be overwritten at any time
+	let top = -(step.stack_after as i64);
+	facts.slots.retain(|&s| s * 8 >= top);
+
+	result
+}
+
+/// Runs the block starting at jump target `i`, passing the facts flowing out along each edge to `edge`.
+/// If `check` is set, returns the first step which is rejected given the facts.
+fn run<'s, F: FnMut(usize, &Facts)>(i: usize, mut facts: Facts, blocks: &[(usize, usize)], steps: &'s [Step], ops: &[Operation], trusted: &[Reg], check: bool, mut edge: F) -> Result<(), (&'s Step, DecoderError)> {
+	let (first, last) = blocks[i];
+
+	for step in &steps[first..last] {
+		if let Rm::Base(base, _) = step.rm {
+			if check && !facts.reg(base) {
+				return Err((step, DecoderError::UnmaskedAccess));
+			}
+		}
+
+		let result = apply(&mut facts, step, ops, trusted);
+
+		if check {
+			result.map_err(|e| (step, e))?;
+		}
+
+		for &target in step.jmp.iter().chain(&step.table) {
+			edge(target, &facts);
+		}
+
+		if step.term {
+			return Ok(());
+		}
+	}
+
+	// Fall through into the next jump target
+	if i + 1 < blocks.len() {
+		edge(i + 1, &facts);
+	}
+
+	Ok(())
+}
+
+/// Finds the facts holding at the start of the block at each jump target, or `None` for unreachable blocks
+fn solve(targets: &[(u64, u32)], blocks: &[(usize, usize)], steps: &[Step], ops: &[Operation], trusted: &[Reg]) -> Vec<Option<Facts>> {
+	let mut entries: Vec<Option<Facts>> = vec![None; targets.len()];
+	let mut pending = vec![false; targets.len()];
+
+	entries[0] = Some(Facts::entry(trusted));
+	pending[0] = true;
+
+	// Facts only shrink, so this terminates
+	while let Some(i) = pending.iter().position(|&p| p) {
+		pending[i] = false;
+
+		let facts = entries[i].clone().unwrap();
+
+		run(i, facts, blocks, steps, ops, trusted, false, |target, facts| {
+			if let Some(ref mut entry) = entries[target] {
+				if entry.meet(facts) {
+					pending[target] = true;
+				}
+				return;
+			}
+			entries[target] = Some(facts.clone());
+			pending[target] = true;
+		}).unwrap();
+	}
+
+	entries
+}
+
+/// Proves that the base register of every sandboxed memory access holds a masked value on every path to the access.
+/// Masked values may be spilled to and reloaded from the stack as long as the spill slot is accessed in whole.
+pub fn check(data: &[u8], parts: &Parts, targets: &[(u64, u32)], blocks: &[(usize, usize)], steps: &[Step], ops: &[Operation], trusted: &[Reg]) -> Result<(), Failure> {
+	for (i, entry) in solve(targets, blocks, steps, ops, trusted).into_iter().enumerate() {
+		if let Some(facts) = entry {
+			run(i, facts, blocks, steps, ops, trusted, true, |_, _| ()).map_err(|(step, error)| {
+				let c = Cursor {
+					data: data,
+					offset: step.offset + step.len,
+				};
+				Failure::new(error, &c, step.offset, parts, step.stack_offset)
+			})?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Lists what is masked before each step, or `None` for unreachable steps
+pub fn masked(targets: &[(u64, u32)], blocks: &[(usize, usize)], steps: &[Step], ops: &[Operation], trusted: &[Reg]) -> Vec<Option<Masked>> {
+	let mut result = vec![None; steps.len()];
+
+	for (i, entry) in solve(targets, blocks, steps, ops, trusted).into_iter().enumerate() {
+		let mut facts = match entry {
+			Some(facts) => facts,
+			None => continue,
+		};
+
+		let (first, last) = blocks[i];
+
+		for (j, step) in steps[first..last].iter().enumerate() {
+			result[first + j] = Some(Masked {
+				regs: (0..16).map(Reg).filter(|&r| facts.reg(r)).collect(),
+				slots: facts.slots.iter().map(|&s| s * 8).collect(),
+			});
+
+			apply(&mut facts, step, ops, trusted).ok();
+
+			if step.term {
+				break
+			}
+		}
+	}
+
+	result
+}
@@ -0,0 +1,45 @@
+use x86_decoder::{Cursor, CursorError};
+use x86_dfa::{TRANSITIONS, PREFIX_WORDS, OPCODE_WORDS, VEX_STARTS};
+
+// Transition tags, generated by `Dfa` in gen_decoder
+const STATE: u16 = 1;
+const PREFIX: u16 = 2;
+const OPCODE: u16 = 3;
+
+fn run(c: &mut Cursor, mut state: usize, prefixes: u32) -> Result<u32, CursorError> {
+	loop {
+		let transition = TRANSITIONS[state * 256 + c.next()? as usize];
+		let index = (transition & 0x3FFF) as usize;
+
+		match transition >> 14 {
+			STATE => state = index,
+			PREFIX => {
+				let words = &PREFIX_WORDS[(index * 4)..(index * 4 + 4)];
+
+				// The 66, F2 and F3 prefixes are tested in the same order as the generated `match`
+				for (&bit, &word) in [8, 4, 2].iter().zip(words) {
+					if prefixes & bit != 0 && word != 0 {
+						return Ok(word);
+					}
+				}
+
+				return Ok(words[3]);
+			}
+			OPCODE => return Ok(OPCODE_WORDS[index * 8 + ((c.peek()? >> 3) & 7) as usize]),
+			_ => return Ok(0),
+		}
+	}
+}
+
+/// Table driven equivalent of `x86_opcodes::decode`
+pub fn decode(c: &mut Cursor, prefixes: u32) -> Result<u32, CursorError> {
+	run(c, 0, prefixes)
+}
+
+/// Table driven equivalent of `x86_vex_opcodes::decode`
+pub fn decode_vex(c: &mut Cursor, map: u8, prefixes: u32) -> Result<u32, CursorError> {
+	match map {
+		1...3 => run(c, VEX_STARTS[map as usize - 1] as usize, prefixes),
+		_ => Ok(0),
+	}
+}
@@ -0,0 +1,679 @@
+use core::{cmp, str};
+use collections::borrow::Cow;
+use collections::string::{String, ToString};
+use collections::vec::Vec;
+use collections::btree_map::BTreeMap;
+use byteorder::{ByteOrder, LittleEndian};
+use elfloader::{Image, elf};
+use x86_decoder::{self, Context, Cursor, DecoderError, Failure, Frame, Line, Parts, Region};
+use cfi::{self, CallTable};
+use relocate::{self, Layout};
+use stack::{self, Unbounded};
+use policy::Policy;
+
+/// Selects the functions to work on by symbol name or by section. Empty lists select everything.
+#[derive(Clone, Debug, Default)]
+pub struct Selection {
+	/// Symbol names. A name ending with `*` selects every symbol starting with the rest of it.
+	pub symbols: Vec<String>,
+	pub sections: Vec<String>,
+}
+
+impl Selection {
+	pub fn selects(&self, code: &Code) -> bool {
+		let symbol = self.symbols.is_empty() || self.symbols.iter().any(|s| {
+			if s.ends_with('*') {
+				code.name.starts_with(&s[..(s.len() - 1)])
+			} else {
+				code.name == s
+			}
+		});
+
+		symbol && (self.sections.is_empty() || self.sections.iter().any(|s| code.section == s))
+	}
+
+	/// The functions of `bin` which are selected
+	pub fn functions<'s>(&self, bin: &Image<'s>, layout: Option<&'s Layout>) -> Vec<Code<'s>> {
+		functions(bin, layout).into_iter().filter(|code| self.selects(code)).collect()
+	}
+
+	/// The invalid padding in the sections which are selected, see `padding`. There's none if symbols are selected.
+	pub fn padding(&self, bin: &Image, layout: Option<&Layout>) -> Vec<Function> {
+		if !self.symbols.is_empty() {
+			return Vec::new();
+		}
+
+		padding(bin, layout).into_iter().filter(|gap| {
+			self.sections.is_empty() || self.sections.iter().any(|s| gap.name.starts_with(&format!("{}+", s)))
+		}).collect()
+	}
+}
+
+/// The code of a function symbol
+#[derive(Clone, Debug)]
+pub struct Code<'s> {
+	pub name: &'s str,
+	/// The name of the section holding the function
+	pub section: &'s str,
+	pub address: u64,
+	pub data: &'s [u8],
+	/// Parts of the function which the compiler split off, like `f.cold`. They're verified together with the function.
+	pub cold: Vec<Code<'s>>,
+}
+
+impl<'s> Code<'s> {
+	/// The bytes of the function followed by its cold parts, and where they are loaded
+	pub fn joined(&self) -> (Cow<'s, [u8]>, Parts) {
+		if self.cold.is_empty() {
+			return (Cow::Borrowed(self.data), Parts::new(self.address, self.data.len()));
+		}
+
+		let mut data = self.data.to_vec();
+		let mut starts = vec![(0, self.address)];
+
+		for part in &self.cold {
+			starts.push((data.len(), part.address));
+			data.extend_from_slice(part.data);
+		}
+
+		let parts = Parts {
+			starts: starts,
+			len: data.len(),
+		};
+
+		(Cow::Owned(data), parts)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct Function {
+	pub name: String,
+	pub address: u64,
+	pub size: u64,
+	pub failure: Option<Failure>,
+	/// The stack usage of the function, if it passed
+	pub frame: Option<Frame>,
+}
+
+impl Function {
+	pub fn passed(&self) -> bool {
+		self.failure.is_none()
+	}
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+	pub functions: Vec<Function>,
+	/// Undefined symbols referenced by the code of a relocatable object, which must be resolved at link time
+	pub externals: Vec<String>,
+}
+
+impl Report {
+	pub fn passed(&self) -> bool {
+		self.functions.iter().all(|f| f.passed())
+	}
+
+	pub fn failed(&self) -> usize {
+		self.functions.iter().filter(|f| !f.passed()).count()
+	}
+
+	/// The worst-case native stack depth of each function, see `stack::worst_case`
+	pub fn stack_depths(&self) -> Vec<Result<u64, Unbounded>> {
+		stack::worst_case(&self.functions)
+	}
+}
+
+/// Places the sections of `bin` and relocates its code if it's a relocatable object.
+pub fn layout(bin: &Image) -> Result<Option<Layout>, &'static str> {
+	if bin.header.unwrap().elftype == elf::ET_REL {
+		relocate::layout(bin).map(Some)
+	} else {
+		Ok(None)
+	}
+}
+
+/// The address of a defined symbol
+fn address(layout: Option<&Layout>, sym: &elf::Symbol) -> Option<u64> {
+	sym.section_index.section().map(|s| sym.value + layout.map(|l| l.addrs[s]).unwrap_or(0))
+}
+
+/// The data of an executable section, relocated if `bin` is a relocatable object
+fn code_section<'s>(bin: &Image<'s>, layout: Option<&'s Layout>, s: usize) -> Option<&'s [u8]> {
+	let code = &bin.sections[s];
+
+	if code.shtype != elf::SHT_PROGBITS || (code.flags.0 & elf::SHF_EXECINSTR.0 == 0) {
+		return None;
+	}
+
+	Some(match layout {
+		Some(layout) => layout.data.get(&s).map(|c| &c[..]).unwrap_or(code.data(bin)),
+		None => code.data(bin),
+	})
+}
+
+/// Finds every `STT_FUNC` symbol in an executable section of `bin`, along with the index of its section and its offset there.
+/// A symbol without a size extends to the next function symbol in its section, or to the end of the section.
+/// It's skipped if that's empty or it lies within a symbol with a size, since it's only a label.
+fn symbols<'s>(bin: &Image<'s>, layout: Option<&'s Layout>) -> Vec<(usize, u64, Code<'s>)> {
+	let mut symbols = Vec::new();
+
+	bin.for_each_symbol(|sym, section| {
+		if sym.sym_type() != elf::STT_FUNC {
+			return;
+		}
+
+		let s = match sym.section_index.section() {
+			Some(s) => s,
+			None => return,
+		};
+
+		let data = match code_section(bin, layout, s) {
+			Some(data) => data,
+			None => return,
+		};
+
+		let code = &bin.sections[s];
+
+		let offset = match layout {
+			Some(_) => sym.value,
+			None => sym.value - code.addr,
+		};
+
+		let name = bin.symbol_name(sym, section).unwrap();
+
+		trace!("dumping symbol {} {:x} {}", name, offset, sym);
+
+		symbols.push((s, offset, sym.size, Code {
+			name: name,
+			section: bin.section_name(code).unwrap_or(""),
+			address: address(layout, sym).unwrap(),
+			data: data,
+			cold: Vec::new(),
+		}));
+	});
+
+	symbols.iter().filter_map(|&(s, offset, size, ref code)| {
+		let size = if size != 0 {
+			size
+		} else {
+			let same = symbols.iter().filter(|other| other.0 == s);
+
+			if same.clone().any(|other| other.2 != 0 && offset >= other.1 && offset - other.1 < other.2) {
+				return None;
+			}
+
+			let next = same.map(|other| other.1).filter(|&start| start > offset).min();
+
+			match next.unwrap_or(code.data.len() as u64).saturating_sub(offset) {
+				// A label at the end of the section
+				0 => return None,
+				size => size,
+			}
+		};
+
+		if offset.saturating_add(size) > code.data.len() as u64 {
+			return None;
+		}
+
+		Some((s, offset, Code {
+			data: &code.data[(offset as usize)..((offset + size) as usize)],
+			..code.clone()
+		}))
+	}).collect()
+}
+
+/// The name of the function `name` was split off from, if it's a cold part like `f.cold` or `f.cold.1`
+fn cold_parent(name: &str) -> Option<&str> {
+	let index = match name.rfind(".cold") {
+		Some(index) => index,
+		None => return None,
+	};
+
+	let rest = &name[(index + 5)..];
+
+	if rest.is_empty() || (rest.starts_with('.') && rest.len() > 1 && rest[1..].chars().all(|c| c.is_digit(10))) {
+		Some(&name[..index])
+	} else {
+		None
+	}
+}
+
+/// Finds every `STT_FUNC` symbol in an executable section of `bin`.
+/// Cold parts are attached to their function if there's a single function with its name, and are verified as their own
+/// function otherwise. Symbols without a size are handled like `symbols`.
+/// `layout` must be the result of `layout` for relocatable objects.
+pub fn functions<'s>(bin: &Image<'s>, layout: Option<&'s Layout>) -> Vec<Code<'s>> {
+	let mut functions: Vec<Code> = symbols(bin, layout).into_iter().map(|(_, _, code)| code).collect();
+
+	let cold: Vec<usize> = (0..functions.len()).filter(|&i| {
+		cold_parent(functions[i].name).map(|parent| {
+			functions.iter().filter(|f| f.name == parent).count() == 1
+		}).unwrap_or(false)
+	}).collect();
+
+	for &i in cold.iter().rev() {
+		let part = functions.remove(i);
+		let parent = cold_parent(part.name).unwrap();
+		let function = functions.iter_mut().find(|f| f.name == parent).unwrap();
+		function.cold.push(part);
+	}
+
+	for function in &mut functions {
+		function.cold.sort_by_key(|part| part.address);
+	}
+
+	functions
+}
+
+/// Checks the bytes of the executable sections of `bin` which aren't covered by a function or a cold part.
+/// They may only be `int3` and `nop` padding. Returns a failed `Function` named after the section and offset
+/// of each range which has other bytes. The indirect call table is checked by `call_table` instead.
+pub fn padding(bin: &Image, layout: Option<&Layout>) -> Vec<Function> {
+	let symbols = symbols(bin, layout);
+
+	let mut failures = Vec::new();
+
+	for s in 0..bin.sections.len() {
+		let data = match code_section(bin, layout, s) {
+			Some(data) => data,
+			None => continue,
+		};
+
+		if bin.section_name(&bin.sections[s]) == Ok(cfi::CALLS_SECTION) {
+			continue;
+		}
+
+		let mut covered: Vec<(usize, usize)> = symbols.iter().filter(|&&(i, _, _)| i == s).map(|&(_, offset, ref code)| {
+			(offset as usize, offset as usize + code.data.len())
+		}).collect();
+
+		covered.sort();
+		covered.push((data.len(), data.len()));
+
+		let mut offset = 0;
+
+		for (start, end) in covered {
+			let gap = offset;
+
+			while offset < start {
+				match x86_decoder::padding_len(&data[offset..start]) {
+					Some(len) => offset += len,
+					None => {
+						let section = bin.section_name(&bin.sections[s]).unwrap_or("");
+						let address = layout.map(|l| l.addrs[s]).unwrap_or(bin.sections[s].addr);
+						let c = Cursor {
+							data: &data[gap..start],
+							offset: offset + 1 - gap,
+						};
+						let name = format!("{}+{:#x}", section, gap);
+						let mut failure = Failure::new(DecoderError::InvalidPadding, &c, offset - gap, &Parts::new(address + gap as u64, start - gap), 0);
+						failure.symbol = Some(name.clone());
+
+						failures.push(Function {
+							name: name,
+							address: address + gap as u64,
+							size: (start - gap) as u64,
+							failure: Some(failure),
+							frame: None,
+						});
+						break
+					}
+				}
+			}
+
+			offset = cmp::max(offset, end);
+		}
+	}
+
+	failures
+}
+
+/// Finds the parts of `bin` which may be accessed with RIP-relative addressing, which are its allocated sections.
+/// A section is writable if it isn't executable and, when there are segments, lies in a writable `PT_LOAD` segment.
+pub fn regions(bin: &Image, layout: Option<&Layout>) -> Vec<Region> {
+	if let Some(layout) = layout {
+		return layout.regions(bin);
+	}
+
+	bin.sections.iter().filter(|section| {
+		section.flags.0 & elf::SHF_ALLOC.0 != 0 && section.size != 0
+	}).map(|section| {
+		let start = section.addr;
+		let end = section.addr + section.size;
+
+		let segment = bin.segments.is_empty() || bin.segments.iter().any(|p| {
+			p.progtype == elf::PT_LOAD && p.flags.0 & elf::PF_W.0 != 0 && p.vaddr <= start && end <= p.vaddr + p.memsz
+		});
+
+		Region {
+			start: start,
+			end: end,
+			write: section.flags.0 & elf::SHF_WRITE.0 != 0 && section.flags.0 & elf::SHF_EXECINSTR.0 == 0 && segment,
+		}
+	}).collect()
+}
+
+/// Finds the addresses which direct calls may target. These are the functions of `bin`,
+/// the symbols named in `imports` and the undefined symbols of a relocatable object.
+/// An import must name `STT_FUNC` symbols in executable sections or an undefined symbol the layout resolves.
+/// It's an error if it names other symbols, like data objects. Names without a symbol in `bin` are ignored.
+pub fn entries(bin: &Image, layout: Option<&Layout>, imports: &[&str]) -> Result<Vec<u64>, &'static str> {
+	let mut entries: Vec<u64> = functions(bin, layout).iter().map(|code| code.address).collect();
+
+	let mut invalid = false;
+
+	bin.for_each_symbol(|sym, section| {
+		let name = bin.symbol_name(sym, section).unwrap();
+
+		if !imports.contains(&name) {
+			return;
+		}
+
+		let function = sym.sym_type() == elf::STT_FUNC && sym.section_index.section().map(|s| code_section(bin, layout, s).is_some()) == Some(true);
+
+		if function {
+			entries.extend(address(layout, sym));
+		} else if !layout.map(|l| l.externals.iter().any(|e| e.name == name)).unwrap_or(false) {
+			invalid = true;
+		}
+	});
+
+	if invalid {
+		return Err("an imported symbol is not a function in an executable section or an undefined symbol");
+	}
+
+	if let Some(layout) = layout {
+		entries.extend(layout.externals.iter().map(|e| e.address));
+	}
+
+	entries.sort();
+	entries.dedup();
+
+	Ok(entries)
+}
+
+/// The name of the section holding jump target tables
+pub const TARGETS_SECTION: &'static str = ".avery.targets";
+
+/// Reads the jump target tables from the `.avery.targets` section of `bin`, if there is one.
+/// They are keyed by the address of their function, so functions with the same symbol name get their own tables.
+///
+/// The section is a sequence of little-endian records, one for each function:
+///
+/// * the length of the name of the function's section as a u32, followed by the name
+/// * the offset of the function in that section as a u64
+/// * the number of jump targets as a u32
+/// * for each jump target, its offset from the start of the function and the stack offset there as u32s
+///
+/// The targets must be sorted and include the function entry with a stack offset of 0.
+/// Targets in cold parts are given by their offset from the function's address too, which wraps around if the part is
+/// placed before the function. `jump_targets` moves them to their offsets in the joined parts.
+pub fn targets(bin: &Image, layout: Option<&Layout>) -> Result<Option<BTreeMap<u64, Vec<(u64, u32)>>>, &'static str> {
+	let section = match bin.sections.iter().find(|s| bin.section_name(s) == Ok(TARGETS_SECTION)) {
+		Some(section) => section,
+		None => return Ok(None),
+	};
+
+	fn take<'s>(data: &'s [u8], offset: &mut usize, len: usize) -> Result<&'s [u8], &'static str> {
+		let bytes = data.get(*offset..(*offset + len)).ok_or("truncated jump target table")?;
+		*offset += len;
+		Ok(bytes)
+	}
+
+	let data = section.data(bin);
+	let mut offset = 0;
+
+	let mut tables = BTreeMap::new();
+
+	while offset < data.len() {
+		let len = LittleEndian::read_u32(take(data, &mut offset, 4)?) as usize;
+		let name = str::from_utf8(take(data, &mut offset, len)?).map_err(|_| "jump target table section name is not UTF-8")?;
+		let function = LittleEndian::read_u64(take(data, &mut offset, 8)?);
+
+		let mut sections = bin.sections.iter().enumerate().filter(|&(_, s)| bin.section_name(s) == Ok(name));
+
+		let s = match (sections.next(), sections.next()) {
+			(Some((s, _)), None) => s,
+			(None, _) => return Err("jump target table refers to a missing section"),
+			(Some(_), Some(_)) => return Err("jump target table refers to an ambiguous section name"),
+		};
+
+		let address = layout.map(|l| l.addrs[s]).unwrap_or(bin.sections[s].addr) + function;
+
+		let count = LittleEndian::read_u32(take(data, &mut offset, 4)?);
+		let mut targets = Vec::new();
+
+		for _ in 0..count {
+			let target = take(data, &mut offset, 8)?;
+			targets.push((LittleEndian::read_u32(&target[0..4]) as u64, LittleEndian::read_u32(&target[4..8])));
+		}
+
+		if tables.insert(address, targets).is_some() {
+			return Err("duplicate jump target table");
+		}
+	}
+
+	Ok(Some(tables))
+}
+
+/// Finds the contents of the read-only data sections of `bin`, which are the only places switch jump tables may be read from.
+/// A section is read-only if it isn't writable, isn't executable and isn't in a writable `PT_LOAD` segment.
+pub fn rodata(bin: &Image, layout: Option<&Layout>) -> Vec<(u64, Vec<u8>)> {
+	bin.sections.iter().enumerate().filter(|&(_, section)| {
+		let flags = section.flags.0;
+
+		let segment = bin.segments.iter().any(|p| {
+			p.progtype == elf::PT_LOAD && p.flags.0 & elf::PF_W.0 != 0 && p.vaddr < section.addr + section.size && section.addr < p.vaddr + p.memsz
+		});
+
+		section.shtype == elf::SHT_PROGBITS && section.size != 0 && !segment &&
+			flags & elf::SHF_ALLOC.0 != 0 && flags & (elf::SHF_WRITE.0 | elf::SHF_EXECINSTR.0) == 0
+	}).map(|(i, section)| {
+		match layout {
+			Some(layout) => (layout.addrs[i], layout.data.get(&i).cloned().unwrap_or(section.data(bin).to_vec())),
+			None => (section.addr, section.data(bin).to_vec()),
+		}
+	}).collect()
+}
+
+/// Checks that every byte of the `PT_LOAD` segment `header` of `bin` was checked by `report`, if it's executable.
+/// `data` is the contents of the segment in the file and `report` must be the result of `verify` for `bin`.
+/// The segment must be read-only and every function of `report` must have passed, since cold parts may be in another segment.
+/// Executable sections in the segment must be at their address in `data`, since `verify` checked their code and padding.
+/// Other bytes may only be `int3` or `nop` padding, so the segment can't hold data or a zero-filled tail.
+pub fn check_segment(bin: &Image, report: &Report, header: &elf::ProgramHeader, data: &[u8]) -> Result<(), &'static str> {
+	if header.flags.0 & elf::PF_X.0 == 0 {
+		return Ok(());
+	}
+
+	if header.flags.0 & elf::PF_W.0 != 0 {
+		return Err("executable segment is writable");
+	}
+
+	if !report.passed() {
+		return Err("executable segment holds code which failed verification");
+	}
+
+	let start = header.vaddr;
+	let end = start.checked_add(header.memsz).ok_or("executable segment is out of bounds")?;
+	let loaded = start + cmp::min(header.memsz, data.len() as u64);
+
+	let mut code = Vec::new();
+
+	for section in bin.sections.iter() {
+		let flags = elf::SHF_ALLOC.0 | elf::SHF_EXECINSTR.0;
+
+		if section.flags.0 & flags != flags || section.size == 0 || section.addr >= end || section.addr.saturating_add(section.size) <= start {
+			continue;
+		}
+
+		let inside = section.shtype == elf::SHT_PROGBITS && section.addr >= start && section.addr.saturating_add(section.size) <= loaded &&
+			section.offset.wrapping_sub(header.offset) == section.addr - start;
+
+		if !inside {
+			return Err("executable section is not at its address in the executable segment");
+		}
+
+		code.push((section.addr, section.addr + section.size));
+	}
+
+	let mut address = start;
+
+	while address < end {
+		if let Some(&(_, section_end)) = code.iter().find(|&&(s, e)| s <= address && address < e) {
+			address = section_end;
+			continue;
+		}
+
+		let next = code.iter().map(|&(s, _)| s).filter(|&s| s > address).min().unwrap_or(end);
+		let offset = (address - start) as usize;
+		let stop = (cmp::min(next, loaded) - start) as usize;
+
+		match if offset < stop { x86_decoder::padding_len(&data[offset..stop]) } else { None } {
+			Some(len) => address += len as u64,
+			None => return Err("executable segment has bytes which are not verified code or padding"),
+		}
+	}
+
+	Ok(())
+}
+
+/// Reads and checks the indirect call table in the `.avery.calls` section of `bin`, if there is one.
+/// Every slot must jump to one of the sorted `entries`.
+pub fn call_table(bin: &Image, layout: Option<&Layout>, entries: &[u64]) -> Result<Option<CallTable>, &'static str> {
+	let i = match bin.sections.iter().position(|s| bin.section_name(s) == Ok(cfi::CALLS_SECTION)) {
+		Some(i) => i,
+		None => return Ok(None),
+	};
+
+	let section = &bin.sections[i];
+
+	if section.shtype != elf::SHT_PROGBITS || section.flags.0 & elf::SHF_EXECINSTR.0 == 0 || section.flags.0 & elf::SHF_WRITE.0 != 0 {
+		return Err("indirect call table is not in a read-only executable section");
+	}
+
+	let (start, data) = match layout {
+		Some(layout) => (layout.addrs[i], layout.data.get(&i).map(|d| &d[..]).unwrap_or(section.data(bin))),
+		None => (section.addr, section.data(bin)),
+	};
+
+	cfi::check_table(start, data, entries).map(Some)
+}
+
+/// Describes `bin` for verifying its functions against `policy`
+pub fn context(bin: &Image, layout: Option<&Layout>, imports: &[&str], policy: &Policy) -> Result<Context, &'static str> {
+	let entries = entries(bin, layout, imports)?;
+
+	Ok(Context {
+		regions: Some(regions(bin, layout)),
+		calls: call_table(bin, layout, &entries)?,
+		entries: Some(entries),
+		targets: targets(bin, layout)?,
+		rodata: rodata(bin, layout),
+		policy: policy.clone(),
+	})
+}
+
+/// The jump targets of `code` from the `.avery.targets` table of `ctx`, if it has them, as offsets in the joined
+/// parts of `code` which are loaded at `parts`. Targets outside of the parts are moved past the end, so they are rejected.
+pub fn jump_targets(code: &Code, parts: &Parts, ctx: &Context) -> Option<Vec<(u64, u32)>> {
+	let table = match ctx.targets.as_ref().and_then(|t| t.get(&code.address)) {
+		Some(table) => table,
+		None => return None,
+	};
+
+	if code.cold.is_empty() {
+		return Some(table.clone());
+	}
+
+	let mut targets: Vec<(u64, u32)> = table.iter().map(|&(offset, stack_offset)| {
+		let address = code.address.wrapping_add(offset as u32 as i32 as i64 as u64);
+		(parts.offset(address).unwrap_or(parts.len) as u64, stack_offset)
+	}).collect();
+
+	targets.sort();
+
+	Some(targets)
+}
+
+/// Verifies a function, in a single pass if `ctx` has a jump target table for it.
+pub fn verify_function(code: &Code, ctx: &Context) -> Function {
+	let (data, parts) = code.joined();
+
+	let result = match jump_targets(code, &parts, ctx) {
+		Some(targets) => x86_decoder::decode_with_targets(&data, &parts, ctx, &targets),
+		None => x86_decoder::decode_parts(&data, &parts, ctx),
+	};
+
+	function(code, result)
+}
+
+/// Verifies a function like `verify_function`, also returning its decoded instructions
+pub fn listing(code: &Code, ctx: &Context) -> (Vec<Line>, Function) {
+	let (data, parts) = code.joined();
+
+	let targets = match jump_targets(code, &parts, ctx) {
+		Some(targets) => Ok(targets),
+		None => x86_decoder::targets(&data, &parts, ctx),
+	};
+
+	let (lines, result) = match targets {
+		Ok(targets) => x86_decoder::listing(&data, &parts, ctx, &targets),
+		Err(failure) => (Vec::new(), Err(failure)),
+	};
+
+	(lines, function(code, result))
+}
+
+fn function(code: &Code, result: Result<Frame, Failure>) -> Function {
+	let (frame, failure) = match result {
+		Ok(frame) => (Some(frame), None),
+		Err(mut failure) => {
+			failure.symbol = Some(code.name.to_string());
+			(None, Some(failure))
+		}
+	};
+
+	Function {
+		name: code.name.to_string(),
+		address: code.address,
+		size: code.data.len() as u64 + code.cold.iter().map(|part| part.data.len() as u64).sum::<u64>(),
+		failure: failure,
+		frame: frame,
+	}
+}
+
+/// Verifies `functions` one after another
+pub fn verify_each(functions: &[Code], ctx: &Context) -> Vec<Function> {
+	functions.iter().map(|code| verify_function(code, ctx)).collect()
+}
+
+/// Verifies the functions in `bin` picked by `selection` against `policy`, using `verify_functions` to verify them.
+/// Direct calls may target any function of `bin` and the symbols named in `imports`.
+/// Functions are checked independently, so a rejected function does not stop the others from being checked.
+/// The padding between functions is checked unless `selection` picks symbols, and is reported as failed functions.
+pub fn verify_image<V>(bin: &Image, imports: &[&str], policy: &Policy, selection: &Selection, verify_functions: V) -> Result<Report, &'static str>
+	where V: FnOnce(&[Code], &Context) -> Vec<Function>
+{
+	let layout = layout(bin)?;
+	let ctx = context(bin, layout.as_ref(), imports, policy)?;
+
+	Ok(verify_with(bin, layout.as_ref(), &functions(bin, layout.as_ref()), &ctx, selection, verify_functions))
+}
+
+/// Verifies the `functions` of `bin` picked by `selection` in `ctx` like `verify_image`, for callers which need
+/// the layout, the functions or the context themselves. `functions` must be the functions of `bin` placed by `layout`.
+pub fn verify_with<V>(bin: &Image, layout: Option<&Layout>, functions: &[Code], ctx: &Context, selection: &Selection, verify_functions: V) -> Report
+	where V: FnOnce(&[Code], &Context) -> Vec<Function>
+{
+	let selected: Vec<Code> = functions.iter().filter(|code| selection.selects(code)).cloned().collect();
+
+	let mut functions = verify_functions(&selected, ctx);
+
+	functions.extend(selection.padding(bin, layout));
+
+	Report {
+		functions: functions,
+		externals: layout.iter().flat_map(|l| l.externals.iter().map(|e| e.name.clone())).collect(),
+	}
+}
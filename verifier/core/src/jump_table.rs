@@ -0,0 +1,186 @@
+use collections::vec::Vec;
+use byteorder::{ByteOrder, LittleEndian};
+use x86_decoder::{Context, Cursor, DecoderError, FunctionState, Inst, Operation, Parts, Reg, Rm};
+use matcher::{NoMatch, Rex, rex, expect, modrm};
+
+/// The most entries accepted in a jump table
+const MAX_ENTRIES: u64 = 0x10000;
+
+/// A matched jump table dispatch. Offsets are from the start of the function.
+struct Dispatch {
+	/// The largest index passing the bounds check
+	bound: u64,
+	/// Target of the bounds check
+	default: i64,
+	table: i64,
+	/// Registers written by the sequence
+	clobbers: Vec<Reg>,
+}
+
+/// Matches the sequence LLVM emits for a switch with a jump table in position independent code:
+///
+/// ```text
+/// cmp index, bound
+/// ja default
+/// mov index32, src32      ; optional
+/// lea table, [rip + jump_table]
+/// movsxd dest, dword ptr [table + 4 * index]
+/// add dest, table
+/// jmp dest
+/// ```
+///
+/// The index must be known to be within bounds, so either the compare must be 64-bit or the index must be zero extended by the `mov`.
+fn parse(c: &mut Cursor) -> Result<Dispatch, NoMatch> {
+	// cmp index, imm8 | imm32
+	let r = rex(c)?;
+	let op = c.next()?;
+	let (mode, ext, rm) = modrm(c, Rex { r: 0, ..r })?;
+	expect(mode == 3 && ext.0 == 7)?;
+	let wide = r.w;
+	let mut index = Reg(rm | r.b << 3);
+	let bound = match op {
+		0x83 => c.next()? as i8 as i64,
+		0x81 => c.next_u32()? as i32 as i64,
+		_ => return Err(NoMatch),
+	};
+
+	// ja rel8 | rel32
+	let default = match c.next()? {
+		0x77 => c.next()? as i8 as i64,
+		0x0F => {
+			expect(c.next()? == 0x87)?;
+			c.next_u32()? as i32 as i64
+		}
+		_ => return Err(NoMatch),
+	};
+	let default = c.offset as i64 + default;
+
+	let mut clobbers = Vec::new();
+
+	// An optional mov of the index
+	let saved = *c;
+	let r = rex(c)?;
+	let op = c.next()?;
+	let mut mov = false;
+
+	if op == 0x89 || op == 0x8B {
+		let (mode, reg, rm) = modrm(c, r)?;
+		let rm = Reg(rm | r.b << 3);
+		let (dst, src) = if op == 0x89 { (rm, reg) } else { (reg, rm) };
+		if mode == 3 && src == index && (!r.w || wide) {
+			index = dst;
+			mov = true;
+		}
+	}
+
+	if mov {
+		clobbers.push(index);
+	} else {
+		*c = saved;
+	}
+
+	// The upper bits of the index are unknown after a 32-bit compare unless it is zero extended
+	expect(wide || mov)?;
+
+	// lea table, [rip + disp32]
+	let r = rex(c)?;
+	expect(r.w && c.next()? == 0x8D)?;
+	let (mode, table, rm) = modrm(c, r)?;
+	expect(mode == 0 && rm == 5 && table != index)?;
+	let disp = c.next_u32()? as i32 as i64;
+	let table_offset = c.offset as i64 + disp;
+
+	// movsxd dest, dword ptr [table + 4 * index]
+	let r = rex(c)?;
+	expect(r.w && c.next()? == 0x63)?;
+	let (mode, dest, rm) = modrm(c, r)?;
+	expect(mode == 0 && rm == 4 && dest != table)?;
+	let sib = c.next()?;
+	// Index 4 without REX.X means there is no index
+	expect(sib >> 6 == 2 && ((sib >> 3) & 7 != 4 || r.x == 1) && sib & 7 != 5)?;
+	expect(Reg((sib >> 3) & 7 | r.x << 3) == index && Reg(sib & 7 | r.b << 3) == table)?;
+
+	// add dest, table
+	let r = rex(c)?;
+	expect(r.w)?;
+	let op = c.next()?;
+	let (mode, reg, rm) = modrm(c, r)?;
+	let rm = Reg(rm | r.b << 3);
+	expect(mode == 3 && match op {
+		0x01 => rm == dest && reg == table,
+		0x03 => reg == dest && rm == table,
+		_ => false,
+	})?;
+
+	// jmp dest
+	let r = rex(c)?;
+	expect(c.next()? == 0xFF)?;
+	let (mode, ext, rm) = modrm(c, Rex { r: 0, ..r })?;
+	expect(mode == 3 && ext.0 == 4 && Reg(rm | r.b << 3) == dest)?;
+
+	clobbers.push(table);
+	clobbers.push(dest);
+
+	// The stack pointer can't be used as a temporary
+	expect(clobbers.iter().all(|&r| r != Reg(4)))?;
+
+	expect(bound >= 0 && (bound as u64) < MAX_ENTRIES)?;
+
+	Ok(Dispatch {
+		bound: bound as u64,
+		default: default,
+		table: table_offset,
+		clobbers: clobbers,
+	})
+}
+
+/// Recognizes a bounds checked jump through a jump table in a read-only data section of `ctx` at the cursor.
+/// The whole sequence is returned as a single terminating instruction which may jump to the default target and
+/// every table entry. Returns `None` and leaves the cursor unchanged if the code doesn't match.
+pub fn recognize(c: &mut Cursor, parts: &Parts, state: &mut FunctionState, ctx: &Context) -> Result<Option<(Inst, usize, usize)>, DecoderError> {
+	let start = c.offset;
+
+	let dispatch = match parse(c) {
+		Ok(dispatch) => dispatch,
+		Err(NoMatch) => {
+			c.offset = start;
+			return Ok(None);
+		}
+	};
+
+	let end = c.offset as i64;
+	let address = parts.address(start).wrapping_add((dispatch.table - start as i64) as u64);
+	let size = (dispatch.bound + 1) * 4;
+
+	let data = ctx.rodata.iter().filter(|&&(base, ref data)| {
+		address >= base && address - base + size <= data.len() as u64
+	}).map(|&(base, ref data)| &data[((address - base) as usize)..((address - base + size) as usize)]).next();
+
+	let data = match data {
+		Some(data) => data,
+		None => return Err(DecoderError::InvalidJumpTable),
+	};
+
+	let ops_index = state.ops.len();
+
+	for &reg in &dispatch.clobbers {
+		state.op(Operation::ClobReg(reg))?;
+	}
+
+	// Entries are relative to the start of the table, while jumps are relative to the end of the instruction
+	let table = data.chunks(4).map(|entry| dispatch.table + LittleEndian::read_i32(entry) as i64 - end).collect();
+
+	let inst = Inst {
+		jmp: Some(dispatch.default - end),
+		rm: Rm::None,
+		term: true,
+		call: false,
+		callee: None,
+		data: None,
+		table: table,
+		sequence: true,
+		kind: None,
+	};
+
+	Ok(Some((inst, c.offset - start, ops_index)))
+}
@@ -0,0 +1,39 @@
+#![feature(alloc, collections)]
+#![feature(stmt_expr_attributes)]
+#![feature(inclusive_range_syntax)]
+#![feature(question_mark)]
+#![allow(dead_code)]
+#![no_std]
+
+#[cfg(feature = "std")]
+#[macro_use]
+extern crate std;
+
+extern crate alloc;
+#[macro_use]
+extern crate collections;
+extern crate elfloader;
+extern crate byteorder;
+
+/// Prints decoding traces in debug builds when the `std` feature is enabled
+macro_rules! trace {
+	($($arg:tt)*) => ({
+		#[cfg(all(debug_assertions, feature = "std"))]
+		println!($($arg)*);
+	})
+}
+
+mod x86_opcodes;
+mod x86_vex_opcodes;
+mod x86_dfa;
+mod dfa;
+pub mod x86_decoder;
+pub mod policy;
+mod dataflow;
+mod matcher;
+mod jump_table;
+mod data_stack;
+pub mod cfi;
+pub mod image;
+pub mod stack;
+pub mod relocate;
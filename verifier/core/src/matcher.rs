@@ -0,0 +1,47 @@
+use x86_decoder::{Cursor, CursorError, Reg};
+
+/// The bytes don't match the expected instruction sequence
+pub struct NoMatch;
+
+impl From<CursorError> for NoMatch {
+	fn from(_: CursorError) -> NoMatch {
+		NoMatch
+	}
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct Rex {
+	pub w: bool,
+	pub r: u8,
+	pub x: u8,
+	pub b: u8,
+}
+
+/// Reads an optional REX prefix
+pub fn rex(c: &mut Cursor) -> Result<Rex, NoMatch> {
+	let byte = c.peek()?;
+	if byte & 0xF0 != 0x40 {
+		return Ok(Rex::default());
+	}
+	c.next()?;
+	Ok(Rex {
+		w: byte & 8 != 0,
+		r: (byte >> 2) & 1,
+		x: (byte >> 1) & 1,
+		b: byte & 1,
+	})
+}
+
+pub fn expect(cond: bool) -> Result<(), NoMatch> {
+	if cond {
+		Ok(())
+	} else {
+		Err(NoMatch)
+	}
+}
+
+/// Returns the mode, reg and rm fields of a ModRM byte
+pub fn modrm(c: &mut Cursor, rex: Rex) -> Result<(u8, Reg, u8), NoMatch> {
+	let modrm = c.next()?;
+	Ok((modrm >> 6, Reg((modrm >> 3) & 7 | rex.r << 3), modrm & 7))
+}
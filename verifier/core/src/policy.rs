@@ -0,0 +1,245 @@
+use core::cmp;
+use collections::string::{String, ToString};
+use collections::vec::Vec;
+use x86_decoder::{self, Reg, MASK_REG};
+
+/// Legacy prefix bits, in the format used by the generated decoders
+pub const LOCK: u32 = 1;
+pub const REP: u32 = 2;
+pub const REPNE: u32 = 4;
+pub const OP_SIZE: u32 = 8;
+
+const PREFIXES: &'static [(&'static str, u32, u8)] = &[
+	("lock", LOCK, x86_decoder::P_LOCK),
+	("rep", REP, x86_decoder::P_REP),
+	("repne", REPNE, x86_decoder::P_REPNE),
+	("opsize", OP_SIZE, x86_decoder::P_OP_SIZE),
+];
+
+const SEGMENTS: &'static [(&'static str, u8)] = &[("fs", x86_decoder::P_SEG_FS), ("gs", x86_decoder::P_SEG_GS)];
+
+const REGS: &'static [&'static str] = &["rax", "rcx", "rdx", "rbx", "rsp", "rbp", "rsi", "rdi",
+	"r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15"];
+
+fn register(name: &str) -> Result<Reg, String> {
+	REGS.iter().position(|&r| r == name).map(|r| Reg(r as u8)).ok_or_else(|| {
+		format!("`{}` is not a general purpose register", name)
+	})
+}
+
+fn error_at(line: usize, e: &str) -> String {
+	format!("line {}: {}", line, e)
+}
+
+/// The prefix bits of the mandatory prefixes in `bytes`
+pub fn prefix_bits(bytes: &[u8]) -> u32 {
+	PREFIXES.iter().filter(|p| bytes.contains(&p.2)).fold(0, |bits, p| bits | p.1)
+}
+
+/// An instruction encoding rejected by a policy
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Forbidden {
+	pub name: String,
+	/// The VEX opcode map, or `None` for legacy encodings
+	pub map: Option<u8>,
+	/// The opcode bytes. These include the escape bytes for legacy encodings.
+	pub bytes: Vec<u8>,
+	/// The opcode extension in the ModRM reg field
+	pub ext: Option<u8>,
+	/// The mandatory 66, F2 or F3 prefix as prefix bits
+	pub prefixes: u32,
+	/// The mandatory prefixes used by any instruction with the same opcode
+	pub selectors: u32,
+}
+
+impl Forbidden {
+	/// Checks if this is the instruction with `opcode`, which starts after the legacy, REX and VEX prefixes.
+	/// The 66, F2 and F3 prefixes select instructions in the same order as the generated decoders.
+	pub fn matches(&self, map: Option<u8>, prefixes: u32, opcode: &[u8]) -> bool {
+		if map != self.map || !opcode.starts_with(&self.bytes) {
+			return false;
+		}
+
+		if let Some(ext) = self.ext {
+			match opcode.get(self.bytes.len()) {
+				Some(&modrm) if (modrm >> 3) & 7 == ext => (),
+				_ => return false,
+			}
+		}
+
+		let selected = [OP_SIZE, REPNE, REP].iter().cloned().find(|&bit| prefixes & self.selectors & bit != 0).unwrap_or(0);
+
+		selected == self.prefixes
+	}
+}
+
+/// The size of the guard page below each data stack
+pub const DATA_STACK_GUARD: u32 = 0x1000;
+
+/// A separate data stack in a callee-saved register, as described in notes/IPC.txt.
+///
+/// Functions using it must start with a prologue which allocates the frame and probes the guard page:
+///
+/// ```text
+/// sub reg, frame
+/// or gs:[reg], 0
+/// ```
+///
+/// and return with `add reg, frame; ret`. The register may not be written to in any other way.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DataStack {
+	pub reg: Reg,
+	/// The size of the guard page. Frames can't be larger, so the probe can't skip over it.
+	pub guard: u32,
+}
+
+/// The rules functions are verified against
+#[derive(Clone, Debug)]
+pub struct Policy {
+	/// The segment override prefix of sandboxed memory accesses
+	pub segment: u8,
+	/// The register holding the sandbox address mask. It may never be written to.
+	pub mask_reg: Reg,
+	/// The legacy prefixes which may be used, as prefix bits
+	pub prefixes: u32,
+	/// Instructions of the effect table which are rejected
+	pub forbidden: Vec<Forbidden>,
+	/// The largest native stack frame a function may use, as in `x86_decoder::Frame::depth`
+	pub max_frame: Option<u32>,
+	/// The data stack functions allocate their frames on, if any
+	pub data_stack: Option<DataStack>,
+}
+
+impl Default for Policy {
+	/// The isolation scheme the compiler emits, using gs: and r15
+	fn default() -> Policy {
+		Policy {
+			segment: x86_decoder::P_SEG_GS,
+			mask_reg: MASK_REG,
+			prefixes: LOCK | REP | REPNE | OP_SIZE,
+			forbidden: Vec::new(),
+			max_frame: None,
+			data_stack: None,
+		}
+	}
+}
+
+impl Policy {
+	/// Parses a policy description. Each line is a `key = value` pair, and `#` starts a comment:
+	///
+	/// ```text
+	/// segment = gs
+	/// mask = r15
+	/// prefixes = lock rep repne opsize
+	/// forbid = int int3
+	/// max-frame = 4096
+	/// data-stack = r12
+	/// data-stack-guard = 4096
+	/// ```
+	///
+	/// Keys which are left out keep the values of the default policy. `forbid` may be repeated.
+	/// The data stack is only used if `data-stack` is given, with a guard page of `DATA_STACK_GUARD` bytes unless `data-stack-guard` is given.
+	/// `encodings` finds the encodings of an instruction name, which is done with the effect table in the `verifier` crate.
+	/// Errors start with the number of the line they are on.
+	pub fn parse<F: Fn(&str) -> Vec<Forbidden>>(text: &str, encodings: F) -> Result<Policy, String> {
+		let mut policy = Policy::default();
+		// The values and line numbers of keys which are checked against other keys
+		let mut guard = None;
+		let mut mask_line = 0;
+		let mut data_stack_line = 0;
+
+		for (i, line) in text.lines().enumerate() {
+			let line = line.split('#').next().unwrap().trim();
+
+			if line.is_empty() {
+				continue;
+			}
+
+			let error = |e: String| error_at(i + 1, &e);
+
+			let (key, value) = match line.find('=') {
+				Some(p) => (line[..p].trim(), line[(p + 1)..].trim()),
+				None => return Err(error(format!("expected `key = value`, found `{}`", line))),
+			};
+
+			match key {
+				"segment" => {
+					policy.segment = SEGMENTS.iter().find(|s| s.0 == value).map(|s| s.1).ok_or_else(|| {
+						error(format!("`{}` is not fs or gs, which are the only segments with a base", value))
+					})?;
+				}
+				"mask" => {
+					let reg = register(value).map_err(&error)?;
+					if reg == Reg(4) {
+						return Err(error("the stack pointer can't be the mask register".to_string()));
+					}
+					policy.mask_reg = reg;
+					mask_line = i + 1;
+				}
+				"prefixes" => {
+					policy.prefixes = 0;
+					for name in value.split_whitespace() {
+						let bit = PREFIXES.iter().find(|p| p.0 == name).map(|p| p.1).ok_or_else(|| {
+							error(format!("unknown prefix `{}`", name))
+						})?;
+						policy.prefixes |= bit;
+					}
+				}
+				"forbid" => {
+					for name in value.split_whitespace() {
+						let forbidden = encodings(name);
+						if forbidden.is_empty() {
+							return Err(error(format!("unknown instruction `{}`", name)));
+						}
+						policy.forbid(forbidden);
+					}
+				}
+				"max-frame" => {
+					policy.max_frame = Some(value.parse().map_err(|_| error(format!("invalid frame size `{}`", value)))?);
+				}
+				"data-stack" => {
+					let reg = register(value).map_err(&error)?;
+					if reg == Reg(4) {
+						return Err(error("the stack pointer can't be the data stack register".to_string()));
+					}
+					policy.data_stack = Some(DataStack {
+						reg: reg,
+						guard: DATA_STACK_GUARD,
+					});
+					data_stack_line = i + 1;
+				}
+				"data-stack-guard" => {
+					guard = Some((value.parse().map_err(|_| error(format!("invalid guard page size `{}`", value)))?, i + 1));
+				}
+				_ => return Err(error(format!("unknown key `{}`", key))),
+			}
+		}
+
+		match (policy.data_stack.as_mut(), guard) {
+			(Some(data_stack), Some((guard, _))) => data_stack.guard = guard,
+			(None, Some((_, line))) => return Err(error_at(line, "`data-stack-guard` is given without `data-stack`")),
+			_ => (),
+		}
+
+		// Reported on the later of the two keys
+		if policy.data_stack.map(|d| d.reg) == Some(policy.mask_reg) {
+			return Err(error_at(cmp::max(mask_line, data_stack_line), "the data stack register can't be the mask register"));
+		}
+
+		Ok(policy)
+	}
+
+	/// Adds instruction encodings to the forbidden ones
+	pub fn forbid(&mut self, forbidden: Vec<Forbidden>) {
+		for f in forbidden {
+			if !self.forbidden.contains(&f) {
+				self.forbidden.push(f);
+			}
+		}
+	}
+
+	/// Finds the forbidden instruction with `opcode`, see `Forbidden::matches`
+	pub fn forbids(&self, map: Option<u8>, prefixes: u32, opcode: &[u8]) -> Option<&Forbidden> {
+		self.forbidden.iter().find(|f| f.matches(map, prefixes, opcode))
+	}
+}
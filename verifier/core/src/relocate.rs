@@ -0,0 +1,256 @@
+use core::str;
+use collections::string::{String, ToString};
+use collections::vec::Vec;
+use collections::btree_map::BTreeMap;
+use byteorder::{ByteOrder, LittleEndian};
+use elfloader::{Image, elf};
+use elfloader::elf::SectionHeader;
+use x86_decoder::Region;
+
+const SHN_UNDEF: u16 = 0;
+const SHN_LORESERVE: u16 = 0xff00;
+const SHN_ABS: u16 = 0xfff1;
+const SHN_COMMON: u16 = 0xfff2;
+
+const R_X86_64_NONE: u32 = 0;
+const R_X86_64_64: u32 = 1;
+const R_X86_64_PC32: u32 = 2;
+const R_X86_64_PLT32: u32 = 4;
+const R_X86_64_GOTPCREL: u32 = 9;
+const R_X86_64_32: u32 = 10;
+const R_X86_64_32S: u32 = 11;
+const R_X86_64_PC64: u32 = 24;
+const R_X86_64_GOTPCRELX: u32 = 41;
+const R_X86_64_REX_GOTPCRELX: u32 = 42;
+
+const SYM_SIZE: usize = 24;
+const RELA_SIZE: usize = 24;
+
+/// The size of the data region of an undefined symbol. Its data is only known at link time,
+/// so RIP-relative reads within this many bytes of the symbol are allowed.
+/// The symbol may resolve to read-only data or code, so the region can't be written.
+pub const EXTERNAL_SIZE: u64 = 0x10000;
+
+/// An undefined symbol referenced by the relocations of an object
+#[derive(Clone, Debug)]
+pub struct External {
+	pub name: String,
+	/// The placeholder address calls and data references to the symbol are resolved to.
+	/// It's the start of a read-only region of `EXTERNAL_SIZE` bytes.
+	pub address: u64,
+	/// The placeholder address of the symbol's GOT entry, if it is referenced through the GOT
+	pub got: Option<u64>,
+}
+
+/// The sections of a relocatable object placed at addresses, along with their relocated contents.
+/// Sections are placed in order starting at 0 and undefined symbols are given placeholder regions after them.
+#[derive(Clone, Debug, Default)]
+pub struct Layout {
+	/// The address of each section
+	pub addrs: Vec<u64>,
+	/// The relocated data of each allocated section which has relocations
+	pub data: BTreeMap<usize, Vec<u8>>,
+	pub externals: Vec<External>,
+	/// GOT entries for defined symbols
+	pub got: Vec<u64>,
+}
+
+impl Layout {
+	/// The placed allocated sections, along with the regions of undefined symbols and the GOT entries which may be read
+	pub fn regions(&self, bin: &Image) -> Vec<Region> {
+		let mut regions: Vec<Region> = bin.sections.iter().enumerate().filter(|&(_, section)| {
+			section.flags.0 & elf::SHF_ALLOC.0 != 0 && section.size != 0
+		}).map(|(i, section)| {
+			Region {
+				start: self.addrs[i],
+				end: self.addrs[i] + section.size,
+				write: section.flags.0 & elf::SHF_WRITE.0 != 0 && section.flags.0 & elf::SHF_EXECINSTR.0 == 0,
+			}
+		}).collect();
+
+		regions.extend(self.externals.iter().map(|e| Region {
+			start: e.address,
+			end: e.address + EXTERNAL_SIZE,
+			write: false,
+		}));
+
+		let got = self.externals.iter().filter_map(|e| e.got).chain(self.got.iter().cloned());
+
+		regions.extend(got.map(|address| Region {
+			start: address,
+			end: address + 8,
+			write: false,
+		}));
+
+		regions
+	}
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Symbol<'s> {
+	name: &'s str,
+	section: u16,
+	value: u64,
+}
+
+fn symbol<'s>(bin: &Image<'s>, symtab: &'s SectionHeader, index: usize) -> Result<Symbol<'s>, &'static str> {
+	let data = symtab.data(bin);
+	let start = index * SYM_SIZE;
+
+	if start + SYM_SIZE > data.len() {
+		return Err("relocation symbol index out of bounds");
+	}
+
+	let data = &data[start..(start + SYM_SIZE)];
+
+	let strtab = bin.sections.get(symtab.link as usize).ok_or("symbol table has no string table")?.data(bin);
+	let name = LittleEndian::read_u32(&data[0..4]) as usize;
+	let name = strtab.get(name..).ok_or("symbol name out of bounds")?;
+	let name = &name[0..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+
+	Ok(Symbol {
+		name: str::from_utf8(name).map_err(|_| "symbol name is not UTF-8")?,
+		section: LittleEndian::read_u16(&data[6..8]),
+		value: LittleEndian::read_u64(&data[8..16]),
+	})
+}
+
+fn align(value: u64, align: u64) -> u64 {
+	let align = if align == 0 { 1 } else { align };
+	(value + align - 1) / align * align
+}
+
+/// Places the sections of the relocatable object `bin` and applies the relocations of its allocated sections.
+/// Unsupported relocation types are only rejected in code. They are left unapplied in data sections,
+/// since data is only read for switch jump tables.
+pub fn layout(bin: &Image) -> Result<Layout, &'static str> {
+	let mut layout = Layout::default();
+	let mut end = 0;
+
+	for section in bin.sections {
+		if section.flags.0 & elf::SHF_ALLOC.0 != 0 {
+			end = align(end, section.addralign);
+			layout.addrs.push(end);
+			end += section.size;
+		} else {
+			layout.addrs.push(0);
+		}
+	}
+
+	// Placeholders for undefined symbols and GOT entries follow the sections
+	let mut next = align(end, 16);
+	let mut place = |size| {
+		let address = next;
+		next += size;
+		address
+	};
+
+	let mut got = BTreeMap::new();
+
+	for rela in bin.sections.iter().filter(|s| s.shtype == elf::SHT_RELA) {
+		let target = rela.info as usize;
+
+		let section = bin.sections.get(target).ok_or("relocation section has an invalid target")?;
+
+		if section.shtype != elf::SHT_PROGBITS || section.flags.0 & elf::SHF_ALLOC.0 == 0 {
+			continue;
+		}
+
+		let executable = section.flags.0 & elf::SHF_EXECINSTR.0 != 0;
+
+		let symtab = bin.sections.get(rela.link as usize).ok_or("relocation section has no symbol table")?;
+
+		// Several relocation sections may apply to the same section
+		let mut contents = layout.data.remove(&target).unwrap_or_else(|| section.data(bin).to_vec());
+		let entries = rela.data(bin);
+
+		for entry in entries.chunks(RELA_SIZE) {
+			if entry.len() != RELA_SIZE {
+				return Err("truncated relocation entry");
+			}
+
+			let offset = LittleEndian::read_u64(&entry[0..8]);
+			let info = LittleEndian::read_u64(&entry[8..16]);
+			let addend = LittleEndian::read_u64(&entry[16..24]);
+			let kind = info as u32;
+
+			if kind == R_X86_64_NONE {
+				continue;
+			}
+
+			let sym = symbol(bin, symtab, (info >> 32) as usize)?;
+
+			let undefined = sym.section == SHN_UNDEF || sym.section == SHN_COMMON;
+
+			let s = if undefined {
+				match layout.externals.iter().position(|e| e.name == sym.name) {
+					Some(i) => layout.externals[i].address,
+					None => {
+						let address = place(EXTERNAL_SIZE);
+						layout.externals.push(External {
+							name: sym.name.to_string(),
+							address: address,
+							got: None,
+						});
+						address
+					}
+				}
+			} else if sym.section == SHN_ABS {
+				sym.value
+			} else if sym.section < SHN_LORESERVE {
+				let base = *layout.addrs.get(sym.section as usize).ok_or("symbol has an invalid section")?;
+				base + sym.value
+			} else {
+				return Err("symbol has an unsupported section index");
+			};
+
+			let p = layout.addrs[target].wrapping_add(offset);
+
+			let (value, size) = match kind {
+				R_X86_64_64 => (s.wrapping_add(addend), 8),
+				R_X86_64_PC64 => (s.wrapping_add(addend).wrapping_sub(p), 8),
+				R_X86_64_32 | R_X86_64_32S => (s.wrapping_add(addend), 4),
+				R_X86_64_PC32 | R_X86_64_PLT32 => (s.wrapping_add(addend).wrapping_sub(p), 4),
+				R_X86_64_GOTPCREL | R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX => {
+					let entry = if undefined {
+						let external = layout.externals.iter_mut().find(|e| e.name == sym.name).unwrap();
+						match external.got {
+							Some(entry) => entry,
+							None => {
+								let entry = place(16);
+								external.got = Some(entry);
+								entry
+							}
+						}
+					} else {
+						*got.entry(s).or_insert_with(|| {
+							let entry = place(16);
+							layout.got.push(entry);
+							entry
+						})
+					};
+					(entry.wrapping_add(addend).wrapping_sub(p), 4)
+				}
+				_ if executable => return Err("unsupported relocation type in code"),
+				_ => continue,
+			};
+
+			match offset.checked_add(size) {
+				Some(end) if end <= contents.len() as u64 => (),
+				_ => return Err("relocation offset out of bounds"),
+			}
+
+			let offset = offset as usize;
+
+			if size == 8 {
+				LittleEndian::write_u64(&mut contents[offset..(offset + 8)], value);
+			} else {
+				LittleEndian::write_u32(&mut contents[offset..(offset + 4)], value as u32);
+			}
+		}
+
+		layout.data.insert(target, contents);
+	}
+
+	Ok(layout)
+}
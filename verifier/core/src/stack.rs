@@ -0,0 +1,133 @@
+use core::cmp;
+use core::fmt;
+use collections::string::String;
+use collections::vec::Vec;
+use collections::btree_map::BTreeMap;
+use collections::btree_set::BTreeSet;
+use image::Function;
+
+/// The size of the return address pushed by a call
+const RETURN_ADDRESS: u64 = 8;
+
+/// Why the stack depth of a function has no static bound
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Unbounded {
+	/// The function or one of its callees was rejected, so its frame isn't known
+	Unverified(String),
+	/// The function may call itself through this cycle of functions
+	Recursion(Vec<String>),
+	/// An indirect call at this address may target any function in the call table
+	IndirectCall(u64),
+	/// A direct call to this address, which is an import or an external symbol
+	UnknownCallee(u64),
+}
+
+impl fmt::Display for Unbounded {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Unbounded::Unverified(ref name) => write!(f, "{} was not verified", name),
+			Unbounded::Recursion(ref cycle) => {
+				write!(f, "recursion through {}", cycle[0])?;
+				for name in &cycle[1..] {
+					write!(f, " -> {}", name)?;
+				}
+				Ok(())
+			}
+			Unbounded::IndirectCall(address) => write!(f, "indirect call at {:#x}", address),
+			Unbounded::UnknownCallee(address) => write!(f, "call to {:#x} outside the image", address),
+		}
+	}
+}
+
+#[derive(Clone)]
+enum State {
+	Unvisited,
+	/// On the current path of the search, so reaching it again is recursion
+	Active,
+	Done(Result<u64, Unbounded>),
+}
+
+struct Graph<'s> {
+	functions: &'s [Function],
+	by_address: BTreeMap<u64, usize>,
+	states: Vec<State>,
+	path: Vec<usize>,
+}
+
+impl<'s> Graph<'s> {
+	fn visit(&mut self, i: usize) -> Result<u64, Unbounded> {
+		match self.states[i] {
+			State::Done(ref result) => return result.clone(),
+			State::Active => {
+				let start = self.path.iter().position(|&j| j == i).unwrap();
+				return Err(Unbounded::Recursion(self.path[start..].iter().map(|&j| self.functions[j].name.clone()).collect()));
+			}
+			State::Unvisited => (),
+		}
+
+		self.states[i] = State::Active;
+		self.path.push(i);
+
+		let result = self.depth(i);
+
+		self.path.pop();
+		self.states[i] = State::Done(result.clone());
+
+		result
+	}
+
+	fn depth(&mut self, i: usize) -> Result<u64, Unbounded> {
+		let functions = self.functions;
+		let function = &functions[i];
+		let frame = function.frame.as_ref().ok_or_else(|| Unbounded::Unverified(function.name.clone()))?;
+
+		let mut depth = frame.depth as u64;
+
+		for call in &frame.calls {
+			let callee = call.callee.ok_or(Unbounded::IndirectCall(call.address))?;
+			let j = *self.by_address.get(&callee).ok_or(Unbounded::UnknownCallee(callee))?;
+			depth = cmp::max(depth, call.stack_offset as u64 + RETURN_ADDRESS + self.visit(j)?);
+		}
+
+		Ok(depth)
+	}
+}
+
+/// Finds the worst-case native stack depth of each of `functions` using the direct call graph.
+/// The depth is the number of bytes used below the stack pointer at the function's entry, so it doesn't include
+/// the return address pushed by the caller. The results are in the same order as `functions`.
+pub fn worst_case(functions: &[Function]) -> Vec<Result<u64, Unbounded>> {
+	let mut by_address = BTreeMap::new();
+
+	for (i, function) in functions.iter().enumerate() {
+		by_address.entry(function.address).or_insert(i);
+	}
+
+	let mut graph = Graph {
+		functions: functions,
+		by_address: by_address,
+		states: vec![State::Unvisited; functions.len()],
+		path: Vec::new(),
+	};
+
+	(0..functions.len()).map(|i| graph.visit(i)).collect()
+}
+
+/// Finds the functions which aren't called directly by any of `functions`.
+/// These are entered from outside the image or through the call table.
+pub fn entry_points(functions: &[Function]) -> Vec<bool> {
+	let mut called = BTreeSet::new();
+
+	for function in functions {
+		for call in function.frame.iter().flat_map(|frame| &frame.calls) {
+			if let Some(callee) = call.callee {
+				// Recursive calls don't make a function any less of an entry point
+				if callee != function.address {
+					called.insert(callee);
+				}
+			}
+		}
+	}
+
+	functions.iter().map(|function| !called.contains(&function.address)).collect()
+}
@@ -0,0 +1,1728 @@
+use core::cmp;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering, ATOMIC_BOOL_INIT, ATOMIC_USIZE_INIT};
+use collections::string::String;
+use collections::vec::Vec;
+use collections::btree_map::BTreeMap;
+use x86_opcodes;
+use x86_vex_opcodes;
+use dfa;
+use dataflow;
+use jump_table;
+use cfi::{self, CallTable};
+use data_stack;
+use policy::{self, DataStack, Policy};
+
+pub static DEBUG: bool = cfg!(debug_assertions);
+
+pub const P_LOCK: u8 = 0xF0;
+pub const P_REP: u8 = 0xF3;
+pub const P_REPNE: u8 = 0xF2;
+pub const P_OP_SIZE: u8 = 0x66;
+pub const P_SEG_FS: u8 = 0x64;
+pub const P_SEG_GS: u8 = 0x65;
+
+/// Formats bytes as hex without allocating
+pub struct Hex<'s>(pub &'s [u8]);
+
+impl<'s> fmt::Display for Hex<'s> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for b in self.0 {
+			write!(f, "{:02x}", b)?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DecoderError {
+	OutofBounds,
+	SegmentOverrideOnBranch,
+	InvalidPrefixes,
+	UnknownInstruction,
+	InstructionTooLong,
+	SegmentedStackAccess,
+	NonSegmentedMemAccess,
+	ComplexAdressing,
+	AbsoluteAdressing,
+	StackIsNotRestored,
+	StackUnderflow,
+	StackOverflow,
+	JumpOutsideOfFunction,
+	PopOfMaskReg,
+	StackClobbered,
+	UnknownJumpTarget,
+	UnbalancedStackJump,
+	MaskRegClobbered,
+	UnmaskedAccess,
+	PartialSlotAccess,
+	SegmentedRipAccess,
+	RipAccessOutOfBounds,
+	RipWriteToReadOnly,
+	InvalidCallTarget,
+	MisalignedJumpTarget,
+	InvalidTargetTable,
+	InvalidJumpTable,
+	UncheckedIndirectCall,
+	InvalidIndirectCall,
+	UncheckedIndirectJump,
+	ForbiddenInstruction,
+	FrameTooLarge,
+	DataStackClobbered,
+	MisplacedDataStackPrologue,
+	DataFrameTooLarge,
+	DataStackIsNotRestored,
+	UnprobedDataStackAccess,
+	DataStackAccessOutOfFrame,
+	InvalidPadding,
+}
+
+impl fmt::Display for DecoderError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let desc = match *self {
+			DecoderError::OutofBounds => "instruction runs past the end of the function",
+			DecoderError::SegmentOverrideOnBranch => "segment override on a branch",
+			DecoderError::InvalidPrefixes => "prefixes not allowed on this instruction",
+			DecoderError::UnknownInstruction => "unknown or disallowed instruction",
+			DecoderError::InstructionTooLong => "instruction is longer than 15 bytes",
+			DecoderError::SegmentedStackAccess => "sandbox segment override on a stack access",
+			DecoderError::NonSegmentedMemAccess => "memory access without the sandbox segment override",
+			DecoderError::ComplexAdressing => "memory operand uses an index register",
+			DecoderError::AbsoluteAdressing => "memory operand uses an absolute address",
+			DecoderError::StackIsNotRestored => "return with a non-zero stack offset",
+			DecoderError::StackUnderflow => "stack underflow",
+			DecoderError::StackOverflow => "stack overflow",
+			DecoderError::JumpOutsideOfFunction => "jump outside of the function",
+			DecoderError::PopOfMaskReg => "pop into the mask register",
+			DecoderError::StackClobbered => "stack pointer clobbered",
+			DecoderError::UnknownJumpTarget => "jump to a target which was not discovered",
+			DecoderError::UnbalancedStackJump => "stack offset differs at jump target",
+			DecoderError::MaskRegClobbered => "write to the mask register",
+			DecoderError::UnmaskedAccess => "sandboxed base register is not masked on every path",
+			DecoderError::PartialSlotAccess => "stack access partially overlaps a slot holding a masked value",
+			DecoderError::SegmentedRipAccess => "sandbox segment override on a RIP-relative access",
+			DecoderError::RipAccessOutOfBounds => "RIP-relative access outside of the image's sections",
+			DecoderError::RipWriteToReadOnly => "RIP-relative write to a read-only section",
+			DecoderError::InvalidCallTarget => "call target is not the start of a function or an allowed import",
+			DecoderError::MisalignedJumpTarget => "jump target is not on an instruction boundary",
+			DecoderError::InvalidTargetTable => "jump target table is not sorted or is out of bounds",
+			DecoderError::InvalidJumpTable => "switch jump table is not in a read-only data section",
+			DecoderError::UncheckedIndirectCall => "indirect call is not masked into the call table",
+			DecoderError::InvalidIndirectCall => "indirect call mask does not select a slot of the call table",
+			DecoderError::UncheckedIndirectJump => "indirect jump is not a jump table dispatch",
+			DecoderError::ForbiddenInstruction => "instruction forbidden by the policy",
+			DecoderError::FrameTooLarge => "stack frame is larger than the policy allows",
+			DecoderError::DataStackClobbered => "write to the data stack register outside of the prologue and epilogue",
+			DecoderError::MisplacedDataStackPrologue => "data stack prologue is not at the start of the function",
+			DecoderError::DataFrameTooLarge => "data stack frame is larger than the guard page",
+			DecoderError::DataStackIsNotRestored => "return without releasing the data stack frame",
+			DecoderError::UnprobedDataStackAccess => "data stack access in a function without a data stack prologue",
+			DecoderError::DataStackAccessOutOfFrame => "data stack access outside of the function's frame",
+			DecoderError::InvalidPadding => "bytes which are not decoded as code are not int3 or nop padding",
+		};
+		write!(f, "{}", desc)
+	}
+}
+
+/// A rejected instruction along with where it was found.
+#[derive(Clone, Debug)]
+pub struct Failure {
+	pub error: DecoderError,
+	/// The function symbol, if decoding was done on behalf of an image
+	pub symbol: Option<String>,
+	/// Offset of the instruction from the start of the function
+	pub offset: usize,
+	pub address: u64,
+	/// The bytes consumed by the decoder, at least one
+	pub bytes: Vec<u8>,
+	/// Stack offset tracked before the instruction
+	pub stack_offset: u32,
+	/// The jump target for branch related errors, or the address of a rejected RIP-relative access
+	pub target: Option<u64>,
+}
+
+impl Failure {
+	pub fn new(error: DecoderError, c: &Cursor, start: usize, parts: &Parts, stack_offset: u32) -> Failure {
+		let end = cmp::min(cmp::max(c.offset, start + 1), c.data.len());
+		Failure {
+			error: error,
+			symbol: None,
+			offset: start,
+			address: parts.address(start),
+			bytes: c.data[start..end].to_vec(),
+			stack_offset: stack_offset,
+			target: None,
+		}
+	}
+
+	fn target(mut self, target: u64) -> Failure {
+		self.target = Some(target);
+		self
+	}
+}
+
+impl fmt::Display for Failure {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if let Some(ref symbol) = self.symbol {
+			write!(f, "{}+{:#x}: ", symbol, self.offset)?;
+		}
+		write!(f, "{} at {:#x} [{}], stack offset {:#x}", self.error, self.address, Hex(&self.bytes), self.stack_offset)?;
+		if let Some(target) = self.target {
+			write!(f, ", target {:#x}", target)?;
+		}
+		Ok(())
+	}
+}
+
+impl From<CursorError> for DecoderError {
+    fn from(e: CursorError) -> DecoderError {
+        DecoderError::OutofBounds
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
+#[repr(packed)]
+pub struct Reg(pub u8);
+
+/// The register holding the sandbox address mask in the default policy
+pub const MASK_REG: Reg = Reg(15);
+
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum Operation {
+	ClobReg(Reg),
+	ClobStack(i32, u8),
+	/// A read of a stack location of the given size which doesn't move a value the dataflow check tracks
+	ReadStack(i32, u8),
+	MoveRegs(Reg, Reg),
+	MoveToStack(i32, Reg),
+	MoveFromStack(Reg, i32),
+	AndRegFromReg(Reg, Reg),
+	AndRegFromStack(Reg, i32),
+	AndStackFromReg(i32, Reg),
+}
+
+impl Operation {
+	fn clobs_reg(&self) -> Option<Reg> {
+		match *self {
+			Operation::ClobReg(r) |
+			Operation::MoveRegs(r, _) |
+			Operation::AndRegFromReg(r, _) |
+			Operation::AndRegFromStack(r, _) |
+			Operation::MoveFromStack(r, _) => Some(r),
+			Operation::ClobStack(..) |
+			Operation::ReadStack(..) |
+			Operation::MoveToStack(..) |
+			Operation::AndStackFromReg(..) => None,
+		}
+	}
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum Rm {
+	None,
+	Reg(Reg),
+	Stack(i32),
+	Base(Reg, i32),
+	/// Displacement from the end of the instruction
+	Rip(i32),
+}
+
+/// Where the bytes of a function are loaded. A function which the compiler split into parts, like `f` and `f.cold`,
+/// is decoded as the concatenation of its parts, and jumps between the parts are followed.
+/// Instructions can't run past the end of a part.
+#[derive(Clone, Debug)]
+pub struct Parts {
+	/// The offset of each part in the bytes of the function and its address, sorted by offset
+	pub starts: Vec<(usize, u64)>,
+	pub len: usize,
+}
+
+impl Parts {
+	/// A function of `len` bytes loaded at `address` in one piece
+	pub fn new(address: u64, len: usize) -> Parts {
+		Parts {
+			starts: vec![(0, address)],
+			len: len,
+		}
+	}
+
+	fn part(&self, offset: usize) -> usize {
+		self.starts.iter().rposition(|&(start, _)| start <= offset).unwrap_or(0)
+	}
+
+	/// The address of the byte at `offset`
+	pub fn address(&self, offset: usize) -> u64 {
+		let (start, address) = self.starts[self.part(offset)];
+		address.wrapping_add(offset.wrapping_sub(start) as u64)
+	}
+
+	/// The end of the part containing the byte at `offset`
+	pub fn end(&self, offset: usize) -> usize {
+		self.starts.get(self.part(offset) + 1).map(|&(start, _)| start).unwrap_or(self.len)
+	}
+
+	/// The offset of the byte at `address`, if it's in the function
+	pub fn offset(&self, address: u64) -> Option<usize> {
+		self.starts.iter().enumerate().filter_map(|(i, &(start, base))| {
+			let end = self.starts.get(i + 1).map(|&(start, _)| start).unwrap_or(self.len);
+			if address >= base && address - base < (end - start) as u64 {
+				Some(start + (address - base) as usize)
+			} else {
+				None
+			}
+		}).next()
+	}
+}
+
+/// A RIP-relative memory access
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub struct Data {
+	pub disp: i32,
+	pub size: usize,
+	pub write: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub struct Inst {
+	pub jmp: Option<i64>,
+	pub rm: Rm,
+	pub term: bool,
+	pub call: bool,
+	/// Displacement of a direct call target from the end of the instruction
+	pub callee: Option<i64>,
+	pub data: Option<Data>,
+	/// Displacements of the entries of a switch jump table from the end of the instruction
+	pub table: Vec<i64>,
+	/// This is a recognized sequence of instructions which is verified as a unit
+	pub sequence: bool,
+	/// The instruction kind in the opcode tables, see `InstKind` in gen_decoder. `None` for recognized sequences.
+	pub kind: Option<u8>,
+}
+
+/// A part of the image which may be accessed with RIP-relative addressing
+#[derive(Copy, Clone, Debug)]
+pub struct Region {
+	pub start: u64,
+	pub end: u64,
+	pub write: bool,
+}
+
+/// What is known about the image a function is verified in.
+/// The checks which need a field are skipped if it is `None`.
+#[derive(Clone, Debug, Default)]
+pub struct Context {
+	/// Regions which may be accessed with RIP-relative addressing
+	pub regions: Option<Vec<Region>>,
+	/// Sorted addresses which direct calls may target
+	pub entries: Option<Vec<u64>>,
+	/// Jump targets and their stack offsets emitted by the compiler, keyed by function address
+	pub targets: Option<BTreeMap<u64, Vec<(u64, u32)>>>,
+	/// The addresses and contents of read-only data sections, which switch jump tables are read from
+	pub rodata: Vec<(u64, Vec<u8>)>,
+	/// The table indirect calls are masked into
+	pub calls: Option<CallTable>,
+	pub policy: Policy,
+}
+
+#[derive(Debug)]
+pub struct CursorError;
+
+#[derive(Copy, Clone)]
+pub struct Cursor<'s> {
+	pub data: &'s [u8],
+	pub offset: usize,
+}
+
+impl<'s> Cursor<'s> {
+	pub fn remaining(&self) -> &'s [u8] {
+		if self.offset > self.data.len() {
+			&[]
+		} else {
+			&self.data[self.offset..]
+		}
+	}
+
+	pub fn peek(&self) -> Result<u8, CursorError> {
+		match self.data.get(self.offset) {
+			Some(&b) => Ok(b),
+			None => return Err(CursorError)
+		}
+	}
+
+	pub fn matches(&mut self, v: u8) -> Result<bool, CursorError> {
+		Ok(if self.peek()? == v {
+			self.next()?;
+			true
+		} else {
+			false
+		})
+	}
+
+	pub fn rewind(&mut self, bytes: usize) -> Result<(), CursorError> {
+		if self.offset < bytes {
+			Err(CursorError)
+		} else {
+			self.offset -= bytes;
+			Ok(())
+		}
+	}
+
+	pub fn skip(&mut self, bytes: usize) -> Result<(), CursorError> {
+		self.offset += bytes;
+		if self.offset > self.data.len() {
+			Err(CursorError)
+		} else {
+			Ok(())
+		}
+	}
+
+	pub fn next(&mut self) -> Result<u8, CursorError> {
+		let byte = self.peek()?;
+		self.offset += 1;
+		Ok(byte)
+	}
+
+	pub fn next_u32(&mut self) -> Result<u32, CursorError> {
+		let mut v = self.next()? as u32;
+		v |= (self.next()? as u32) << 8;
+		v |= (self.next()? as u32) << 16;
+		v |= (self.next()? as u32) << 24;
+		Ok(v)
+	}
+}
+
+/// The fields of a VEX prefix
+#[derive(Copy, Clone, Debug)]
+struct Vex {
+	/// The REX bits in the same format as a REX prefix
+	rex: u32,
+	/// The opcode map, 1 for 0F, 2 for 0F 38 and 3 for 0F 3A
+	map: u8,
+	/// The implied prefix in the format of the legacy prefix bits
+	prefixes: u32,
+	/// Selects 256-bit vectors
+	l: bool,
+	/// The inverted VEX.vvvv register
+	vvvv: u8,
+}
+
+impl Vex {
+	fn parse(c: &mut Cursor) -> Result<Vex, CursorError> {
+		let (r, x, b, map, w, last) = if c.next()? == 0xC5 {
+			let byte = c.next()? as u32;
+			(byte >> 7, 1, 1, 1, 0, byte)
+		} else {
+			let first = c.next()? as u32;
+			let byte = c.next()? as u32;
+			(first >> 7, (first >> 6) & 1, (first >> 5) & 1, first & 0x1F, byte >> 7, byte)
+		};
+
+		Ok(Vex {
+			// R, X and B are stored inverted
+			rex: 0x40 | w << 3 | (r ^ 1) << 2 | (x ^ 1) << 1 | (b ^ 1),
+			map: map as u8,
+			prefixes: match last & 3 {
+				1 => 8,
+				2 => 2,
+				3 => 4,
+				_ => 0,
+			},
+			l: last & 4 != 0,
+			vvvv: (!(last >> 3) & 0xF) as u8,
+		})
+	}
+}
+
+pub struct FunctionState {
+	pub stack_offset: u32,
+	pub ops: Vec<Operation>,
+	/// The mask register of the policy
+	pub mask_reg: Reg,
+	pub data_stack: Option<DataStack>,
+	/// The size of the data stack frame allocated by the prologue
+	pub data_frame: Option<u32>,
+}
+
+impl FunctionState {
+	pub fn new(policy: &Policy) -> FunctionState {
+		FunctionState {
+			stack_offset: 0,
+			ops: Vec::new(),
+			mask_reg: policy.mask_reg,
+			data_stack: policy.data_stack,
+			data_frame: None,
+		}
+	}
+
+	/// Registers which can't be written to and hold addresses within the sandbox at function entry
+	pub fn trusted(&self) -> Vec<Reg> {
+		let mut regs = vec![self.mask_reg];
+		regs.extend(self.data_stack.map(|d| d.reg));
+		regs
+	}
+
+	pub fn op(&mut self, op: Operation) -> Result<(), DecoderError> {
+		if op.clobs_reg() == Some(self.mask_reg) {
+			return Err(DecoderError::MaskRegClobbered);
+		}
+		if op.clobs_reg().is_some() && op.clobs_reg() == self.data_stack.map(|d| d.reg) {
+			return Err(DecoderError::DataStackClobbered);
+		}
+		self.ops.push(op);
+		Ok(())
+	}
+
+	/// Moves rsp down by `bytes`, which is negative when the stack shrinks
+	pub fn grow_stack(&mut self, bytes: i64) -> Result<(), DecoderError> {
+		let offset = self.stack_offset as i64 + bytes;
+		if offset < 0 || offset > u32::max_value() as i64 {
+			return Err(DecoderError::StackOverflow);
+		}
+		self.stack_offset = offset as u32;
+		Ok(())
+	}
+}
+
+/// Decodes a single instruction with the default policy
+pub fn inst(c: &mut Cursor, state: &mut FunctionState) -> Result<(Inst, usize, usize), DecoderError> {
+	inst_with_policy(c, state, &Policy::default())
+}
+
+pub fn inst_with_policy(c: &mut Cursor, state: &mut FunctionState, policy: &Policy) -> Result<(Inst, usize, usize), DecoderError> {
+	inst_with_decoder(c, state, policy, TABLE_DECODER.load(Ordering::Relaxed))
+}
+
+/// Decodes a single instruction, using the table driven opcode decoder if `table` is set, regardless of `TABLE_DECODER`
+pub fn inst_with_decoder(c: &mut Cursor, state: &mut FunctionState, policy: &Policy, table: bool) -> Result<(Inst, usize, usize), DecoderError> {
+	fn def() -> Inst {
+		Inst {
+			jmp: None,
+			rm: Rm::None,
+			term: false,
+			call: false,
+			callee: None,
+			data: None,
+			table: Vec::new(),
+			sequence: false,
+			kind: None,
+		}
+	}
+
+	let ops_index = state.ops.len();
+
+	let start_offset = c.offset;
+
+	let segment_override = c.matches(policy.segment)?;
+
+	let mut prefixes = 0;
+
+	if c.matches(P_OP_SIZE)? {
+		prefixes |= policy::OP_SIZE;
+	}
+	if c.matches(P_LOCK)? {
+		prefixes |= policy::LOCK;
+	}
+	if c.matches(P_REP)? {
+		prefixes |= policy::REP;
+	}
+	if c.matches(P_REPNE)? {
+		prefixes |= policy::REPNE;
+	}
+	if prefixes & !policy.prefixes != 0 {
+		return Err(DecoderError::InvalidPrefixes);
+	}
+
+	let operand_size_override = prefixes & 8 != 0;
+
+	let rex = c.peek()? as u32;
+	let rex = match rex {
+		0x40...0x4F => {
+			c.next();
+			rex
+		}
+		_ => 0
+	};
+
+	let opcode = c.peek()?;
+
+	// The opcode tables decode `call r/m` and `jmp r/m` like any other r/m instruction, so indirect branches
+	// outside of the sequences recognized by `cfi` and `jump_table` are rejected here. Far branches are unknown.
+	if opcode == 0xFF {
+		let mut modrm = *c;
+		modrm.next()?;
+		match (modrm.peek()? >> 3) & 7 {
+			2 => return Err(DecoderError::UncheckedIndirectCall),
+			4 => return Err(DecoderError::UncheckedIndirectJump),
+			_ => (),
+		}
+	}
+
+	// VEX prefixes can't follow legacy prefixes other than segment overrides or REX
+	let vex = if rex == 0 && prefixes == 0 && (opcode == 0xC4 || opcode == 0xC5) {
+		Some(Vex::parse(c)?)
+	} else {
+		None
+	};
+
+	// VEX.pp selects instructions like the implied 66, F3 and F2 prefixes, but doesn't change the operand size
+	let (rex, prefixes, vex_l) = match vex {
+		Some(ref vex) => (vex.rex, vex.prefixes, vex.l),
+		None => (rex, prefixes, false),
+	};
+
+	let opcode_offset = c.offset;
+
+	let mut format = match vex {
+		Some(ref vex) if table => dfa::decode_vex(c, vex.map, prefixes)?,
+		Some(ref vex) => x86_vex_opcodes::decode(c, vex.map, prefixes)?,
+		None if table => dfa::decode(c, prefixes)?,
+		None => x86_opcodes::decode(c, prefixes)?,
+	} as u32;
+
+	// Ensure prefixes are legal
+	if !prefixes | (format & 0xF) != !0 {
+		trace!("(Invalid prefixes {} on {})", prefixes, format);
+		return Err(DecoderError::InvalidPrefixes);
+	}
+
+	if !policy.forbidden.is_empty() && policy.forbids(vex.map(|vex| vex.map), prefixes, &c.data[opcode_offset..]).is_some() {
+		return Err(DecoderError::ForbiddenInstruction);
+	}
+
+	format >>= 4;
+
+	let rex_w = rex & 8 != 0;
+
+	// Opsize - 3 bits
+	let op_size: u32 = match format & 7 {
+		0 => 1,
+		1 => if rex_w { 8 } else { if operand_size_override { 2 } else { 4 } },
+		2 => 2,
+		3 => 8,
+		4 => 16,
+		5 => if vex_l { 32 } else { 16 },
+		6 => 4,
+		_ => panic!(),
+	};
+	format >>= 3;
+
+	/*trace!("(Opsize = {}, operand_size_override = {})",  op_size, operand_size_override);*/
+
+	let case = format & 0x1F;
+	format >>= 5;
+
+	trace!("(Case {})", case);
+
+	let modrm_ignore = |c: &mut Cursor| -> Result<Reg, DecoderError> {
+		let modrm = c.next()? as u32;
+		let mode = modrm >> 6;
+		let reg = ((modrm >> 3) & 7) | ((rex & 4) << 1);
+		let rm_norex = modrm & 7;
+
+		let off = match mode {
+			1 => c.skip(1)?,
+			2 => c.skip(4)?,
+			_ => (),
+		};
+
+		if mode != 3 {
+			if rm_norex == 4 {
+				// Parse SIB byte
+
+				let sib = c.next()? as u32;
+				let base_norex = sib & 7;
+				if mode == 0 && base_norex == 5 {
+					c.skip(4)?;
+				}
+			} else {
+				if mode == 0 && rm_norex == 5 {
+					c.skip(4)?
+				}
+			}
+		}
+
+		Ok(Reg(reg as u8))
+	};
+
+	let modrm = |c: &mut Cursor| -> Result<(Rm, Reg), DecoderError> {
+		let modrm = c.next()? as u32;
+		let mode = modrm >> 6;
+		let reg = ((modrm >> 3) & 7) | ((rex & 4) << 1);
+		let rm_norex = modrm & 7;
+
+		//println!("mode:{} reg:{} rm: {}", mode ,reg ,rm);
+
+		let sib = if mode != 3 && rm_norex == 4 {
+			c.next()? as u32
+		} else {
+			0
+		};
+
+		let off = match mode {
+			0 | 3 => 0,
+			1 => c.next()? as i8 as i32,
+			2 => c.next_u32()? as i32,
+			_ => panic!(),
+		};
+
+		let rm_rex = rm_norex | (rex & 1) << 3;
+
+		let rm = if mode == 3 {
+			Rm::Reg(Reg(rm_rex as u8))
+		} else {
+			if rm_norex == 4 {
+				// Parse SIB byte
+
+				let base_norex = sib & 7;
+				let index = ((sib >> 3) & 7) | (rex & 2) << 2;
+				let scale = sib >> 6;
+
+				if mode == 0 && base_norex == 5 {
+					return Err(DecoderError::AbsoluteAdressing)
+				}
+
+				if index != 4 {
+					return Err(DecoderError::ComplexAdressing);
+				}
+
+				let base_rex = base_norex | (rex & 1) << 3;
+
+				if base_rex == 4 {
+					if segment_override {
+						return Err(DecoderError::SegmentedStackAccess)
+					}
+					Rm::Stack(off)
+				} else {
+					if !segment_override {
+						return Err(DecoderError::NonSegmentedMemAccess)
+					}
+					Rm::Base(Reg(base_rex as u8), off)
+				}
+			} else {
+				if mode == 0 && rm_norex == 5 {
+					if segment_override {
+						return Err(DecoderError::SegmentedRipAccess)
+					}
+					// The displacement is checked against the image in `decode` once the instruction length is known
+					Rm::Rip(c.next_u32()? as i32)
+				} else {
+					if !segment_override {
+						return Err(DecoderError::NonSegmentedMemAccess)
+					}
+					Rm::Base(Reg(rm_rex as u8), off)
+				}
+			}
+		};
+
+		Ok((rm, Reg(reg as u8)))
+	};
+
+	// The register is encoded in the low bits of the opcode for Push, Pop and ClobRegRex
+	let reg_rex = || Reg(((opcode as u32 & 7) | (rex & 1) << 3) as u8);
+
+	let result = match case {
+		// Illegal
+		0 => return Err(DecoderError::UnknownInstruction),
+		// WriteRm
+		1 => {
+			let (rm, _) = modrm(c)?;
+
+			match rm {
+				Rm::Stack(s) => state.op(Operation::ClobStack(s, op_size as u8))?,
+				Rm::Reg(r) => state.op(Operation::ClobReg(r))?,
+				_ => ()
+			};
+
+			Inst {
+				rm: rm,
+				..def()
+			}
+		}
+		// ReadRmToReg
+		2 => {
+			let (rm, reg) = modrm(c)?;
+
+			match rm {
+				Rm::Stack(s) => state.op(Operation::ReadStack(s, op_size as u8))?,
+				_ => (),
+			};
+
+			state.op(Operation::ClobReg(reg))?;
+
+			Inst {
+				rm: rm,
+				..def()
+			}
+		}
+		// ReadRm
+		3 => {
+			let (rm, reg) = modrm(c)?;
+
+			match rm {
+				Rm::Stack(s) => state.op(Operation::ReadStack(s, op_size as u8))?,
+				_ => (),
+			};
+
+			Inst {
+				rm: rm,
+				..def()
+			}
+		}
+		// Store
+		4 => {
+			let (rm, reg) = modrm(c)?;
+
+			match rm {
+				Rm::Stack(s) => state.op(if op_size == 8 {
+					Operation::MoveToStack(s, reg)
+				} else {
+					Operation::ClobStack(s, op_size as u8)
+				})?,
+				Rm::Reg(r) => state.op(if op_size == 8 {
+					Operation::MoveRegs(r, reg)
+				} else {
+					Operation::ClobReg(r)
+				})?,
+				_ => (),
+			};
+
+			Inst {
+				rm: rm,
+				..def()
+			}
+		}
+		// Load
+		5 => {
+			let (rm, reg) = modrm(c)?;
+
+			match rm {
+				Rm::Stack(s) if op_size != 8 => state.op(Operation::ReadStack(s, op_size as u8))?,
+				_ => (),
+			};
+
+			state.op(match rm {
+				Rm::Stack(s) if op_size == 8 => Operation::MoveFromStack(reg, s),
+				Rm::Reg(r) if op_size == 8 => Operation::MoveRegs(reg, r),
+				_ => Operation::ClobReg(reg),
+			})?;
+
+			Inst {
+				rm: rm,
+				..def()
+			}
+		}
+		// AndRmFromReg
+		6 => {
+			let (rm, reg) = modrm(c)?;
+
+			match rm {
+				Rm::Stack(s) => state.op(if op_size == 8 {
+					Operation::AndStackFromReg(s, reg)
+				} else {
+					Operation::ClobStack(s, op_size as u8)
+				})?,
+				Rm::Reg(r) => state.op(if op_size == 8 {
+					Operation::AndRegFromReg(r, reg)
+				} else {
+					Operation::ClobReg(r)
+				})?,
+				_ => (),
+			};
+
+			Inst {
+				rm: rm,
+				..def()
+			}
+		}
+		// AndRmToReg
+		7 => {
+			let (rm, reg) = modrm(c)?;
+
+			match rm {
+				Rm::Stack(s) if op_size != 8 => state.op(Operation::ReadStack(s, op_size as u8))?,
+				_ => (),
+			};
+
+			state.op(match rm {
+				Rm::Stack(s) if op_size == 8 => Operation::AndRegFromStack(reg, s),
+				Rm::Reg(r) if op_size == 8 => Operation::AndRegFromReg(reg, r),
+				_ => Operation::ClobReg(reg),
+			})?;
+
+			Inst {
+				rm: rm,
+				..def()
+			}
+		}
+		// Lea
+		8 => {
+			let reg = modrm_ignore(c)?;
+
+			state.op(Operation::ClobReg(reg))?;
+
+			Inst {
+				..def()
+			}
+		}
+		// Push
+		9 => {
+			let reg = reg_rex();
+
+			state.op(Operation::MoveToStack(-8, reg))?;
+
+			match state.stack_offset.checked_add(8) {
+				Some(v) => state.stack_offset = v,
+				None => return Err(DecoderError::StackOverflow),
+			}
+
+			Inst {
+				..def()
+			}
+		}
+		// Pop
+		10 => {
+			let reg = reg_rex();
+
+			if reg == state.mask_reg {
+				return Err(DecoderError::PopOfMaskReg);
+			}
+
+			state.op(Operation::MoveFromStack(reg, 0))?;
+
+			match state.stack_offset.checked_sub(8) {
+				Some(v) => state.stack_offset = v,
+				None => return Err(DecoderError::StackUnderflow),
+			}
+
+			Inst {
+				..def()
+			}
+		}
+		// ClobRegRex
+		11 => {
+			let reg = reg_rex();
+
+			state.op(Operation::ClobReg(reg))?;
+
+			Inst {
+				..def()
+			}
+		}
+		// CheckAddr
+		12 => return Err(DecoderError::AbsoluteAdressing),
+		// CallRm
+		13 => {
+			// Indirect calls are only allowed as part of the sequence recognized by `cfi::recognize`
+			return Err(DecoderError::UncheckedIndirectCall);
+		}
+		// Call32
+		14 => { 
+			if segment_override {
+				return Err(DecoderError::SegmentOverrideOnBranch);
+			} else {
+				let offset = c.next_u32()? as i32 as i64;
+				Inst {
+					call: true,
+					callee: Some(offset),
+					..def()
+				}
+			}
+		}
+		// Jmp32 | Jcc32
+		15 | 21 => { 
+			if segment_override {
+				return Err(DecoderError::SegmentOverrideOnBranch);
+			} else {
+				let offset = c.next_u32()? as i32 as i64;
+				Inst {
+					jmp: Some(offset),
+					term: case == 15,
+					..def()
+				}
+			}
+		}
+		// Jmp8 | Jcc8
+		16 | 20 => {
+			if segment_override {
+				return Err(DecoderError::SegmentOverrideOnBranch);
+			} else {
+				let offset = c.next()? as i8 as i64;
+				Inst {
+					jmp: Some(offset),
+					term: case == 16,
+					..def()
+				}
+			}
+		}
+		// Ud2
+		17 => { 
+			Inst {
+				term: true,
+				..def()
+			}
+		}
+		// None
+		18 => {
+			Inst {
+				..def()
+			}
+		}
+		// Ret
+		19 => {
+			if segment_override {
+				return Err(DecoderError::SegmentOverrideOnBranch);
+			} else {
+				if state.stack_offset != 0 {
+					return Err(DecoderError::StackIsNotRestored);
+				}
+
+				// The frame is released by the epilogue recognized by `data_stack::recognize`
+				if state.data_frame.is_some() {
+					return Err(DecoderError::DataStackIsNotRestored);
+				}
+
+				Inst {
+					term: true,
+					..def()
+				}
+			}
+		}
+		// 20 => Jmp8 | Jcc8
+		// 21 => Jmp32 | Jcc32
+		// XchgRm
+		22 => {
+			let (rm, reg) = modrm(c)?;
+
+			state.op(Operation::ClobReg(reg))?;
+
+			match rm {
+				Rm::Stack(s) => state.op(Operation::ClobStack(s, op_size as u8))?,
+				Rm::Reg(r) =>  state.op(Operation::ClobReg(r))?,
+				_ => ()
+			};
+
+			Inst {
+				rm: rm,
+				..def()
+			}
+
+		}
+		// AddRmImm
+		23 => {
+			let (rm, _) = modrm(c)?;
+
+			match rm {
+				Rm::Stack(s) => state.op(Operation::ClobStack(s, op_size as u8))?,
+				Rm::Reg(Reg(4)) => {
+					if op_size != 8 {
+						return Err(DecoderError::StackClobbered);
+					}
+					// The immediate is sign extended
+					let imm = match opcode {
+						0x81 => {
+							let i = c.next_u32()?;
+							c.rewind(4)?;
+							i as i32
+						}
+						0x83 => c.peek()? as i8 as i32,
+						_ => panic!(),
+					};
+
+					state.grow_stack(-(imm as i64))?;
+				},
+				Rm::Reg(r) => state.op(Operation::ClobReg(r))?,
+				_ => ()
+			};
+
+			Inst {
+				rm: rm,
+				..def()
+			}
+		}
+		// SubRmImm
+		24 => {
+			let (rm, _) = modrm(c)?;
+
+			match rm {
+				Rm::Stack(s) => state.op(Operation::ClobStack(s, op_size as u8))?,
+				Rm::Reg(Reg(4)) => {
+					if op_size != 8 {
+						return Err(DecoderError::StackClobbered);
+					}
+					// The immediate is sign extended
+					let imm = match opcode {
+						0x81 => {
+							let i = c.next_u32()?;
+							c.rewind(4)?;
+							i as i32
+						}
+						0x83 => c.peek()? as i8 as i32,
+						_ => panic!(),
+					};
+
+					state.grow_stack((imm as i64))?;
+				},
+				Rm::Reg(r) => state.op(Operation::ClobReg(r))?,
+				_ => ()
+			};
+
+			Inst {
+				rm: rm,
+				..def()
+			}
+		}
+		// NopRm
+		25 => {
+			modrm_ignore(c)?;
+			def()
+		}
+		// VecWriteRm
+		26 => {
+			let (rm, _) = modrm(c)?;
+
+			// The register operand is a vector register, so only stores have effects
+			match rm {
+				Rm::Stack(s) => state.op(Operation::ClobStack(s, op_size as u8))?,
+				_ => ()
+			};
+
+			Inst {
+				rm: rm,
+				..def()
+			}
+		}
+		// VecReadRm
+		27 => {
+			let (rm, _) = modrm(c)?;
+
+			match rm {
+				Rm::Stack(s) => state.op(Operation::ReadStack(s, op_size as u8))?,
+				_ => ()
+			};
+
+			Inst {
+				rm: rm,
+				..def()
+			}
+		}
+		_ => panic!(),
+	};
+
+	format >>= 3;
+
+	let imm = format & 3;
+	/*trace!("(Imm {})", imm);*/
+	// Imm type - 2 bits
+	match imm {
+		0 => (),
+		1 => c.skip(1)?,
+		2 => c.skip(cmp::min(op_size, 4) as usize)?,
+		3 => c.skip(cmp::min(op_size, 8) as usize)?,
+		_ => panic!(),
+	};
+	format >>= 2;
+
+	// Skip the ModRM bit
+	format >>= 1;
+
+	if format & 1 != 0 {
+		state.op(Operation::ClobReg(Reg(0)))?;
+	}
+	if format & 2 != 0 {
+		state.op(Operation::ClobReg(Reg(2)))?;
+	}
+
+	let len = c.offset - start_offset;
+
+	if len >= 16 {
+		return Err(DecoderError::InstructionTooLong);
+	}
+
+	if let Rm::Base(base, off) = result.rm {
+		if Some(base) == state.data_stack.map(|d| d.reg) {
+			match state.data_frame {
+				Some(frame) if off >= 0 && off as u64 + op_size as u64 <= frame as u64 => (),
+				Some(_) => return Err(DecoderError::DataStackAccessOutOfFrame),
+				None => return Err(DecoderError::UnprobedDataStackAccess),
+			}
+		}
+	}
+
+	let result = Inst {
+		kind: Some(case as u8),
+		..result
+	};
+
+	let result = match result.rm {
+		Rm::Rip(disp) => Inst {
+			data: Some(Data {
+				disp: disp,
+				size: op_size as usize,
+				// WriteRm, Store, AndRmFromReg, XchgRm, AddRmImm, SubRmImm and VecWriteRm write to memory
+				write: match case {
+					1 | 4 | 6 | 22 | 23 | 24 | 26 => true,
+					_ => false,
+				},
+			}),
+			..result
+		},
+		_ => result,
+	};
+
+	Ok((result, len, ops_index))
+}
+
+/// Decodes a recognized instruction sequence or a single instruction at the cursor
+fn sequence_or_inst(c: &mut Cursor, parts: &Parts, state: &mut FunctionState, ctx: &Context) -> Result<(Inst, usize, usize), DecoderError> {
+	if let Some(dispatch) = jump_table::recognize(c, parts, state, ctx)? {
+		return Ok(dispatch);
+	}
+
+	if let Some(call) = cfi::recognize(c, parts, state, ctx)? {
+		return Ok(call);
+	}
+
+	if let Some(sequence) = data_stack::recognize(c, state, ctx)? {
+		return Ok(sequence);
+	}
+
+	inst_with_policy(c, state, &ctx.policy)
+}
+
+/// A decoded instruction as seen by the dataflow analysis
+#[derive(Clone, Debug)]
+pub struct Step {
+	pub offset: usize,
+	pub len: usize,
+	/// Stack offsets before and after the instruction
+	pub stack_offset: u32,
+	pub stack_after: u32,
+	/// The range of operations in `FunctionState::ops` recorded by the instruction
+	pub ops: (usize, usize),
+	pub rm: Rm,
+	/// Index of the jump target, if this is a direct jump
+	pub jmp: Option<usize>,
+	/// Indices of the jump targets of a switch jump table
+	pub table: Vec<usize>,
+	pub term: bool,
+	pub call: bool,
+	/// The address of the callee, if this is a direct call
+	pub callee: Option<u64>,
+}
+
+impl Step {
+	/// The number of bytes used below the stack pointer at function entry by the instruction
+	pub fn depth(&self) -> u32 {
+		let below = match self.rm {
+			// Accesses below the stack pointer, like the red zone
+			Rm::Stack(off) if off < 0 => self.stack_offset.saturating_add(off.wrapping_neg() as u32),
+			_ => 0,
+		};
+		cmp::max(cmp::max(self.stack_offset, self.stack_after), below)
+	}
+}
+
+/// A call made by a function
+#[derive(Copy, Clone, Debug)]
+pub struct CallSite {
+	/// Offset of the call instruction in the joined parts of the function
+	pub offset: usize,
+	/// The address of the call instruction
+	pub address: u64,
+	/// Stack offset at the call, not counting the return address it pushes
+	pub stack_offset: u32,
+	/// The address of the callee, or `None` for indirect calls through the call table
+	pub callee: Option<u64>,
+}
+
+/// The native stack usage of a verified function
+#[derive(Clone, Debug)]
+pub struct Frame {
+	/// The number of bytes the function uses below the stack pointer at its entry, not counting its calls
+	pub depth: u32,
+	pub calls: Vec<CallSite>,
+}
+
+impl Frame {
+	fn new(steps: &[Step], parts: &Parts) -> Frame {
+		Frame {
+			depth: steps.iter().map(|step| step.depth()).max().unwrap_or(0),
+			calls: steps.iter().filter(|step| step.call).map(|step| {
+				CallSite {
+					offset: step.offset,
+					address: parts.address(step.offset),
+					stack_offset: step.stack_offset,
+					callee: step.callee,
+				}
+			}).collect(),
+		}
+	}
+}
+
+/// The number of instructions decoded, including the jump target discovery pass
+pub static INSTRUCTIONS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Decode opcodes with the table driven decoder generated by `avery-verify gen-decoder --dfa` instead of the generated `match`
+pub static TABLE_DECODER: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// Counts instructions for a single pass and adds them to `INSTRUCTIONS` when the pass ends,
+/// so threads verifying functions in parallel don't contend on the counter for every instruction.
+struct InstructionCount(usize);
+
+impl Drop for InstructionCount {
+	fn drop(&mut self) {
+		INSTRUCTIONS.fetch_add(self.0, Ordering::Relaxed);
+	}
+}
+
+/// Discovers the jump targets of the function in `data` loaded at `parts` and the stack offsets there
+pub fn targets(data: &[u8], parts: &Parts, ctx: &Context) -> Result<Vec<(u64, u32)>, Failure> {
+	let mut state = FunctionState::new(&ctx.policy);
+
+	let mut count = InstructionCount(0);
+
+	let mut targets = Vec::new();
+	targets.push((0 as u64, 0));
+
+	trace!("Jump target discovery:");
+
+	let mut i = 0;
+
+	while i < targets.len() {
+		let mut c = Cursor {
+			data: &data[..parts.end(targets[i].0 as usize)],
+			offset: targets[i].0 as usize,
+		};
+
+		state.stack_offset = targets[i].1;
+
+		trace!("Label:");
+
+		loop {
+			count.0 += 1;
+
+			let start = c.offset;
+			let address = parts.address(start);
+
+			let stack_offset = state.stack_offset;
+
+			let (inst, len, ops_index) = sequence_or_inst(&mut c, parts, &mut state, ctx).map_err(|e| Failure::new(e, &c, start, parts, stack_offset))?;
+
+			trace!("o {:08x} - s {:08x}: {}", address, stack_offset, Hex(&c.data[start..c.offset]));
+
+			for &target in inst.jmp.iter().chain(&inst.table) {
+				let off = (address + len as u64).wrapping_add(target as u64);
+				trace!("Jump target {:#x}", off);
+				if let Some(real_off) = parts.offset(off) {
+					let real_off = real_off as u64;
+					if let Err(i) = targets.binary_search_by_key(&real_off, |&(t, s)| t) {
+						trace!("Inserting target {:#x}", real_off);
+						targets.insert(i, (real_off, state.stack_offset));
+					}
+				} else {
+					trace!("Jump outside of symbol {:#x} at {:#x}", off, address);
+					return Err(Failure::new(DecoderError::JumpOutsideOfFunction, &c, start, parts, stack_offset).target(off));
+				}
+			}
+
+			if inst.term {
+				break
+			}
+		}
+
+		i += 1;
+	}
+
+	trace!("Done with function {:?}", targets);
+
+	Ok(targets)
+}
+
+/// Returns the length of the `int3` or `nop` instruction at the start of `data`
+pub fn padding_len(data: &[u8]) -> Option<usize> {
+	// Compilers pad with `nop` forms which have operand size and `cs` prefixes
+	let prefixes = data.iter().take_while(|&&b| b == 0x66 || b == 0x2E).count();
+
+	match &data[prefixes..] {
+		rest if rest.first() == Some(&0xCC) && prefixes == 0 => Some(1),
+		rest if rest.first() == Some(&0x90) => Some(prefixes + 1),
+		rest if rest.len() >= 3 && rest[0] == 0x0F && rest[1] == 0x1F && (rest[2] >> 3) & 7 == 0 => {
+			// nop r/m with a memory operand or a register
+			let modrm = rest[2];
+			let sib = if modrm >> 6 != 3 && modrm & 7 == 4 { 1 } else { 0 };
+			let disp = match modrm >> 6 {
+				0 if modrm & 7 == 5 => 4,
+				0 if modrm & 7 == 4 && rest.get(3).map(|s| s & 7) == Some(5) => 4,
+				1 => 1,
+				2 => 4,
+				_ => 0,
+			};
+			let len = prefixes + 3 + sib + disp;
+
+			if len <= data.len() && len < 16 {
+				Some(len)
+			} else {
+				None
+			}
+		}
+		_ => None,
+	}
+}
+
+/// Checks that the bytes of a function which no instruction was decoded from are `int3` or `nop` padding,
+/// since they are executable too. Padding doesn't run past the end of a part.
+fn check_unreached(data: &[u8], parts: &Parts, steps: &[Step]) -> Result<(), Failure> {
+	let mut covered: Vec<(usize, usize)> = steps.iter().map(|step| (step.offset, step.offset + step.len)).collect();
+
+	covered.sort();
+	covered.push((data.len(), data.len()));
+
+	let mut offset = 0;
+
+	for (start, end) in covered {
+		while offset < start {
+			match padding_len(&data[offset..cmp::min(start, parts.end(offset))]) {
+				Some(len) => offset += len,
+				None => {
+					let c = Cursor {
+						data: data,
+						offset: offset + 1,
+					};
+					return Err(Failure::new(DecoderError::InvalidPadding, &c, offset, parts, 0));
+				}
+			}
+		}
+
+		offset = cmp::max(offset, end);
+	}
+
+	Ok(())
+}
+
+/// Checks that a RIP-relative access of `size` bytes at `address` lies within a single region.
+/// Writes must also be to a writable region.
+fn check_data(regions: &[Region], address: u64, size: usize, write: bool) -> Result<(), DecoderError> {
+	let end = match address.checked_add(size as u64) {
+		Some(end) => end,
+		None => return Err(DecoderError::RipAccessOutOfBounds),
+	};
+
+	match regions.iter().find(|r| address >= r.start && end <= r.end) {
+		Some(r) if write && !r.write => Err(DecoderError::RipWriteToReadOnly),
+		Some(_) => Ok(()),
+		None => Err(DecoderError::RipAccessOutOfBounds),
+	}
+}
+
+/// Verifies the function in `data` loaded at `disp_off` within the image described by `ctx`.
+pub fn decode(data: &[u8], disp_off: u64, ctx: &Context) -> Result<Frame, Failure> {
+	decode_parts(data, &Parts::new(disp_off, data.len()), ctx)
+}
+
+/// Verifies the function in `data` loaded at `parts` within the image described by `ctx`.
+pub fn decode_parts(data: &[u8], parts: &Parts, ctx: &Context) -> Result<Frame, Failure> {
+	let targets = targets(data, parts, ctx)?;
+	decode_with_targets(data, parts, ctx, &targets)
+}
+
+/// Verifies the function in `data` in a single pass using a sorted list of jump targets and their stack offsets,
+/// instead of discovering them. Every target must be on an instruction boundary and every jump must be to a target.
+/// Bytes which aren't reached from the entry or a target must be `int3` or `nop` padding.
+pub fn decode_with_targets(data: &[u8], parts: &Parts, ctx: &Context, targets: &[(u64, u32)]) -> Result<Frame, Failure> {
+	let mut state = FunctionState::new(&ctx.policy);
+
+	let mut steps = Vec::new();
+	let mut blocks = Vec::new();
+
+	decode_steps(data, parts, ctx, targets, &mut state, &mut steps, &mut blocks)?;
+
+	check_unreached(data, parts, &steps)?;
+
+	dataflow::check(data, parts, targets, &blocks, &steps, &state.ops, &state.trusted())?;
+
+	trace!("Done with function");
+
+	Ok(Frame::new(&steps, parts))
+}
+
+/// Decodes the blocks starting at each of the jump `targets` into `steps`.
+/// Blocks are ranges of `steps`. On failure, the steps decoded before the failing instruction are kept.
+fn decode_steps(data: &[u8], parts: &Parts, ctx: &Context, targets: &[(u64, u32)], state: &mut FunctionState, steps: &mut Vec<Step>, blocks: &mut Vec<(usize, usize)>) -> Result<(), Failure> {
+	let sorted = targets.windows(2).all(|w| w[0].0 < w[1].0);
+
+	if targets.first() != Some(&(0, 0)) || !sorted || targets.last().unwrap().0 >= data.len() as u64 {
+		let c = Cursor {
+			data: data,
+			offset: 0,
+		};
+		return Err(Failure::new(DecoderError::InvalidTargetTable, &c, 0, parts, 0));
+	}
+
+	let mut count = InstructionCount(0);
+
+	trace!("Disassembly:");
+
+	let mut i = 0;
+
+	while i < targets.len() {
+		let mut c = Cursor {
+			data: &data[..parts.end(targets[i].0 as usize)],
+			offset: targets[i].0 as usize,
+		};
+
+		state.stack_offset = targets[i].1;
+
+		let first_step = steps.len();
+
+		trace!("Label:");
+
+		loop {
+			count.0 += 1;
+
+			let start = c.offset;
+			let address = parts.address(start);
+
+			let stack_offset = state.stack_offset;
+
+			let (inst, len, ops_index) = sequence_or_inst(&mut c, parts, state, ctx).map_err(|e| Failure::new(e, &c, start, parts, stack_offset))?;
+
+			trace!("{:08x} - {:02x}: {}", address, stack_offset, Hex(&c.data[start..c.offset]));
+
+			if let (Some(data), Some(regions)) = (inst.data, ctx.regions.as_ref()) {
+				let target = (address + len as u64).wrapping_add(data.disp as i64 as u64);
+				check_data(regions, target, data.size, data.write).map_err(|e| Failure::new(e, &c, start, parts, stack_offset).target(target))?;
+			}
+
+			let callee = inst.callee.map(|callee| (address + len as u64).wrapping_add(callee as u64));
+
+			if let (Some(target), Some(entries)) = (callee, ctx.entries.as_ref()) {
+				if entries.binary_search(&target).is_err() {
+					return Err(Failure::new(DecoderError::InvalidCallTarget, &c, start, parts, stack_offset).target(target));
+				}
+			}
+
+			let mut jmps = Vec::new();
+
+			for &target in inst.jmp.iter().chain(&inst.table) {
+				let off = (address + len as u64).wrapping_add(target as u64);
+				trace!("Jump target {:#x}", off);
+				if let Some(real_off) = parts.offset(off) {
+					let real_off = real_off as u64;
+					if let Ok(index) = targets.binary_search_by_key(&real_off, |&(t, s)| t) {
+						if targets[index].1 != state.stack_offset {
+							return Err(Failure::new(DecoderError::UnbalancedStackJump, &c, start, parts, state.stack_offset).target(off));
+						}
+						// Running the prologue again would allocate another data stack frame
+						if index == 0 && state.data_frame.is_some() {
+							return Err(Failure::new(DecoderError::MisplacedDataStackPrologue, &c, start, parts, state.stack_offset).target(off));
+						}
+						jmps.push(index);
+					} else {
+						return Err(Failure::new(DecoderError::UnknownJumpTarget, &c, start, parts, state.stack_offset).target(off));
+					}
+				} else {
+					trace!("Jump outside of symbol {:#x} at {:#x}", off, address);
+					return Err(Failure::new(DecoderError::JumpOutsideOfFunction, &c, start, parts, stack_offset).target(off));
+				}
+			}
+
+			steps.push(Step {
+				offset: start,
+				len: len,
+				stack_offset: stack_offset,
+				stack_after: state.stack_offset,
+				ops: (ops_index, state.ops.len()),
+				rm: inst.rm,
+				jmp: inst.jmp.and(jmps.first().cloned()),
+				table: if inst.jmp.is_some() { jmps[1..].to_vec() } else { jmps },
+				term: inst.term,
+				call: inst.call,
+				callee: callee,
+			});
+
+			if let Some(max) = ctx.policy.max_frame {
+				if steps.last().unwrap().depth() > max {
+					return Err(Failure::new(DecoderError::FrameTooLarge, &c, start, parts, stack_offset));
+				}
+			}
+
+			if inst.term {
+				break
+			}
+
+			if let Some(target) = targets.get(i + 1) {
+				if target.0 == c.offset as u64 {
+					trace!("Implicit jump target");
+
+					// We are at the next jump target
+					// Make sure it's stack offset matches
+					if target.1 != state.stack_offset {
+						return Err(Failure::new(DecoderError::UnbalancedStackJump, &c, start, parts, state.stack_offset).target(parts.address(target.0 as usize)));
+					}
+					break
+				}
+
+				if target.0 < c.offset as u64 {
+					return Err(Failure::new(DecoderError::MisalignedJumpTarget, &c, start, parts, stack_offset).target(parts.address(target.0 as usize)));
+				}
+			}
+		}
+
+		blocks.push((first_step, steps.len()));
+
+		i += 1;
+	}
+
+	Ok(())
+}
+
+/// What is proven to be masked before an instruction
+#[derive(Clone, Debug)]
+pub struct Masked {
+	pub regs: Vec<Reg>,
+	/// Offsets of the masked stack slots from the stack pointer at function entry
+	pub slots: Vec<i64>,
+}
+
+/// An instruction of an annotated disassembly
+#[derive(Clone, Debug)]
+pub struct Line {
+	pub offset: usize,
+	pub len: usize,
+	/// Stack offset before the instruction
+	pub stack_offset: u32,
+	/// The instruction starts a block at a jump target
+	pub label: bool,
+	/// The operations recorded for the dataflow analysis
+	pub ops: Vec<Operation>,
+	/// What is masked before the instruction, if the dataflow analysis reached it
+	pub masked: Option<Masked>,
+}
+
+/// Verifies the function in `data` like `decode_with_targets`, returning the decoded instructions along with the result.
+/// If verification fails, the instructions decoded before the failure are returned.
+pub fn listing(data: &[u8], parts: &Parts, ctx: &Context, targets: &[(u64, u32)]) -> (Vec<Line>, Result<Frame, Failure>) {
+	let mut state = FunctionState::new(&ctx.policy);
+
+	let mut steps = Vec::new();
+	let mut blocks = Vec::new();
+
+	let result = decode_steps(data, parts, ctx, targets, &mut state, &mut steps, &mut blocks).and_then(|()| {
+		check_unreached(data, parts, &steps)
+	}).and_then(|()| {
+		dataflow::check(data, parts, targets, &blocks, &steps, &state.ops, &state.trusted())
+	}).map(|()| Frame::new(&steps, parts));
+
+	let masked = if blocks.len() == targets.len() {
+		dataflow::masked(targets, &blocks, &steps, &state.ops, &state.trusted())
+	} else {
+		vec![None; steps.len()]
+	};
+
+	let lines = steps.iter().zip(masked).map(|(step, masked)| {
+		Line {
+			offset: step.offset,
+			len: step.len,
+			stack_offset: step.stack_offset,
+			label: targets.binary_search_by_key(&(step.offset as u64), |&(t, _)| t).is_ok(),
+			ops: state.ops[step.ops.0..step.ops.1].to_vec(),
+			masked: masked,
+		}
+	}).collect();
+
+	(lines, result)
+}
+
+/// Finds the boundaries of up to `context` instructions on either side of the instruction at `offset`,
+/// returned as (offset, length) pairs. The boundaries come from a linear sweep from the start of `data`,
+/// so they are only meant for diagnostics.
+pub fn window(data: &[u8], offset: usize, context: usize) -> Vec<(usize, usize)> {
+	let mut state = FunctionState::new(&Policy::default());
+
+	let mut c = Cursor {
+		data: data,
+		offset: 0,
+	};
+
+	let mut insts = Vec::new();
+	let mut after = None;
+
+	while c.offset < data.len() {
+		let start = c.offset;
+
+		// Keep the stack tracking out of the way, we only care about lengths here
+		state.stack_offset = 0x4000_0000;
+
+		let result = inst(&mut c, &mut state);
+
+		let end = cmp::min(cmp::max(c.offset, start + 1), data.len());
+		insts.push((start, end - start));
+
+		if start >= offset && after.is_none() {
+			after = Some(insts.len() - 1);
+		}
+
+		if let Some(i) = after {
+			if insts.len() > i + context {
+				break
+			}
+		}
+
+		match result {
+			Ok(..) |
+			Err(DecoderError::StackIsNotRestored) |
+			Err(DecoderError::StackUnderflow) => (),
+			Err(_) => break,
+		}
+
+		c.offset = end;
+	}
+
+	let center = after.unwrap_or(insts.len());
+	let first = center.saturating_sub(context);
+	insts.drain(..first);
+	insts
+}
+
+/// Splits `data` into instructions with a linear sweep, returned as (offset, length) pairs.
+/// Recognized sequences are a single instruction. Where an instruction is rejected, it ends where the decoder stopped,
+/// so every byte of `data` is covered.
+pub fn instructions(data: &[u8], disp_off: u64, ctx: &Context) -> Vec<(usize, usize)> {
+	sweep(data, disp_off, ctx).into_iter().map(|(offset, len, _)| (offset, len)).collect()
+}
+
+/// Like `instructions`, but also returns each decoded instruction, or `None` if it was rejected
+pub fn sweep(data: &[u8], disp_off: u64, ctx: &Context) -> Vec<(usize, usize, Option<Inst>)> {
+	let mut state = FunctionState::new(&ctx.policy);
+	let parts = Parts::new(disp_off, data.len());
+
+	let mut c = Cursor {
+		data: data,
+		offset: 0,
+	};
+
+	let mut insts = Vec::new();
+
+	while c.offset < data.len() {
+		let start = c.offset;
+
+		// Keep the stack tracking out of the way, we only care about the instructions here
+		state.stack_offset = 0x4000_0000;
+
+		let mut inst = sequence_or_inst(&mut c, &parts, &mut state, ctx).ok().map(|(inst, _, _)| inst);
+
+		if inst.is_none() {
+			// Returns require an empty stack
+			c.offset = start;
+			state.stack_offset = 0;
+			inst = sequence_or_inst(&mut c, &parts, &mut state, ctx).ok().map(|(inst, _, _)| inst);
+		}
+
+		let end = cmp::min(cmp::max(c.offset, start + 1), data.len());
+		insts.push((start, end - start, inst));
+
+		c.offset = end;
+	}
+
+	insts
+}
@@ -0,0 +1,445 @@
+use x86_decoder::{Cursor, CursorError};
+pub fn decode(c: &mut Cursor, map: u8, prefixes: u32) -> Result<u32, CursorError> {
+	Ok(match map {
+		1 => {
+			match try!(c.next()) {
+				0x10 => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vmovupd */ 0x20dd8);
+					}
+					if prefixes & 4 != 0 {
+						return Ok(/* vmovsd */ 0x20db4);
+					}
+					if prefixes & 2 != 0 {
+						return Ok(/* vmovss */ 0x20de2);
+					} /* vmovups */
+					0x20dd0
+				}
+				0x11 => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vmovupd */ 0x20d58);
+					}
+					if prefixes & 4 != 0 {
+						return Ok(/* vmovsd */ 0x20d34);
+					}
+					if prefixes & 2 != 0 {
+						return Ok(/* vmovss */ 0x20d62);
+					} /* vmovups */
+					0x20d50
+				}
+				0x28 => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vmovapd */ 0x20dd8);
+					} /* vmovaps */
+					0x20dd0
+				}
+				0x29 => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vmovapd */ 0x20d58);
+					} /* vmovaps */
+					0x20d50
+				}
+				0x2a => {
+					if prefixes & 4 != 0 {
+						return Ok(/* vcvtsi2sd */ 0x20d94);
+					}
+					if prefixes & 2 != 0 {
+						return Ok(/* vcvtsi2ss */ 0x20d92);
+					}
+					0
+				}
+				0x2c => {
+					if prefixes & 4 != 0 {
+						return Ok(/* vcvttsd2si */ 0x20134);
+					}
+					if prefixes & 2 != 0 {
+						return Ok(/* vcvttss2si */ 0x20162);
+					}
+					0
+				}
+				0x2e => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vucomisd */ 0x201b8);
+					} /* vucomiss */
+					0x201e0
+				}
+				0x50 => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vmovmskpd */ 0x20158);
+					} /* vmovmskps */
+					0x20150
+				}
+				0x51 => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vsqrtpd */ 0x20dd8);
+					}
+					if prefixes & 4 != 0 {
+						return Ok(/* vsqrtsd */ 0x20db4);
+					}
+					if prefixes & 2 != 0 {
+						return Ok(/* vsqrtss */ 0x20de2);
+					} /* vsqrtps */
+					0x20dd0
+				}
+				0x54 => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vandpd */ 0x20dd8);
+					} /* vandps */
+					0x20dd0
+				}
+				0x55 => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vandnpd */ 0x20dd8);
+					} /* vandnps */
+					0x20dd0
+				}
+				0x56 => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vorpd */ 0x20dd8);
+					} /* vorps */
+					0x20dd0
+				}
+				0x57 => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vxorpd */ 0x20dd8);
+					} /* vxorps */
+					0x20dd0
+				}
+				0x58 => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vaddpd */ 0x20dd8);
+					}
+					if prefixes & 4 != 0 {
+						return Ok(/* vaddsd */ 0x20db4);
+					}
+					if prefixes & 2 != 0 {
+						return Ok(/* vaddss */ 0x20de2);
+					} /* vaddps */
+					0x20dd0
+				}
+				0x59 => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vmulpd */ 0x20dd8);
+					}
+					if prefixes & 4 != 0 {
+						return Ok(/* vmulsd */ 0x20db4);
+					}
+					if prefixes & 2 != 0 {
+						return Ok(/* vmulss */ 0x20de2);
+					} /* vmulps */
+					0x20dd0
+				}
+				0x5a => {
+					if prefixes & 4 != 0 {
+						return Ok(/* vcvtsd2ss */ 0x20db4);
+					}
+					if prefixes & 2 != 0 {
+						return Ok(/* vcvtss2sd */ 0x20de2);
+					}
+					0
+				}
+				0x5c => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vsubpd */ 0x20dd8);
+					}
+					if prefixes & 4 != 0 {
+						return Ok(/* vsubsd */ 0x20db4);
+					}
+					if prefixes & 2 != 0 {
+						return Ok(/* vsubss */ 0x20de2);
+					} /* vsubps */
+					0x20dd0
+				}
+				0x5d => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vminpd */ 0x20dd8);
+					}
+					if prefixes & 4 != 0 {
+						return Ok(/* vminsd */ 0x20db4);
+					}
+					if prefixes & 2 != 0 {
+						return Ok(/* vminss */ 0x20de2);
+					} /* vminps */
+					0x20dd0
+				}
+				0x5e => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vdivpd */ 0x20dd8);
+					}
+					if prefixes & 4 != 0 {
+						return Ok(/* vdivsd */ 0x20db4);
+					}
+					if prefixes & 2 != 0 {
+						return Ok(/* vdivss */ 0x20de2);
+					} /* vdivps */
+					0x20dd0
+				}
+				0x5f => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vmaxpd */ 0x20dd8);
+					}
+					if prefixes & 4 != 0 {
+						return Ok(/* vmaxsd */ 0x20db4);
+					}
+					if prefixes & 2 != 0 {
+						return Ok(/* vmaxss */ 0x20de2);
+					} /* vmaxps */
+					0x20dd0
+				}
+				0x64 => {
+					// Multiple prefixes
+					/* vpcmpgtb */
+					0x20dd8
+				}
+				0x65 => {
+					// Multiple prefixes
+					/* vpcmpgtw */
+					0x20dd8
+				}
+				0x66 => {
+					// Multiple prefixes
+					/* vpcmpgtd */
+					0x20dd8
+				}
+				0x6c => {
+					// Multiple prefixes
+					/* vpunpcklqdq */
+					0x20dd8
+				}
+				0x6d => {
+					// Multiple prefixes
+					/* vpunpckhqdq */
+					0x20dd8
+				}
+				0x6e => {
+					// Multiple prefixes
+					/* vmov */
+					0x20d98
+				}
+				0x6f => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vmovdqa */ 0x20dd8);
+					}
+					if prefixes & 2 != 0 {
+						return Ok(/* vmovdqu */ 0x20dd2);
+					}
+					0
+				}
+				0x70 => {
+					// Multiple prefixes
+					/* vpshufd */
+					0x28dd8
+				}
+				0x74 => {
+					// Multiple prefixes
+					/* vpcmpeqb */
+					0x20dd8
+				}
+				0x75 => {
+					// Multiple prefixes
+					/* vpcmpeqw */
+					0x20dd8
+				}
+				0x76 => {
+					// Multiple prefixes
+					/* vpcmpeqd */
+					0x20dd8
+				}
+				0x77 => {
+					/* vzeroupper */
+					0x910
+				}
+				0x7e => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vmov */ 0x20098);
+					}
+					if prefixes & 2 != 0 {
+						return Ok(/* vmovq */ 0x20db2);
+					}
+					0
+				}
+				0x7f => {
+					if prefixes & 8 != 0 {
+						return Ok(/* vmovdqa */ 0x20d58);
+					}
+					if prefixes & 2 != 0 {
+						return Ok(/* vmovdqu */ 0x20d52);
+					}
+					0
+				}
+				0xd4 => {
+					// Multiple prefixes
+					/* vpaddq */
+					0x20dd8
+				}
+				0xd5 => {
+					// Multiple prefixes
+					/* vpmullw */
+					0x20dd8
+				}
+				0xd6 => {
+					// Multiple prefixes
+					/* vmovq */
+					0x20d38
+				}
+				0xd7 => {
+					// Multiple prefixes
+					/* vpmovmskb */
+					0x20158
+				}
+				0xda => {
+					// Multiple prefixes
+					/* vpminub */
+					0x20dd8
+				}
+				0xdb => {
+					// Multiple prefixes
+					/* vpand */
+					0x20dd8
+				}
+				0xde => {
+					// Multiple prefixes
+					/* vpmaxub */
+					0x20dd8
+				}
+				0xdf => {
+					// Multiple prefixes
+					/* vpandn */
+					0x20dd8
+				}
+				0xeb => {
+					// Multiple prefixes
+					/* vpor */
+					0x20dd8
+				}
+				0xef => {
+					// Multiple prefixes
+					/* vpxor */
+					0x20dd8
+				}
+				0xf8 => {
+					// Multiple prefixes
+					/* vpsubb */
+					0x20dd8
+				}
+				0xf9 => {
+					// Multiple prefixes
+					/* vpsubw */
+					0x20dd8
+				}
+				0xfa => {
+					// Multiple prefixes
+					/* vpsubd */
+					0x20dd8
+				}
+				0xfb => {
+					// Multiple prefixes
+					/* vpsubq */
+					0x20dd8
+				}
+				0xfc => {
+					// Multiple prefixes
+					/* vpaddb */
+					0x20dd8
+				}
+				0xfd => {
+					// Multiple prefixes
+					/* vpaddw */
+					0x20dd8
+				}
+				0xfe => {
+					// Multiple prefixes
+					/* vpaddd */
+					0x20dd8
+				}
+				_ => 0,
+			}
+		}
+		2 => {
+			match try!(c.next()) {
+				0x0 => {
+					// Multiple prefixes
+					/* vpshufb */
+					0x20dd8
+				}
+				0x17 => {
+					// Multiple prefixes
+					/* vptest */
+					0x201d8
+				}
+				0x18 => {
+					// Multiple prefixes
+					/* vbroadcastss */
+					0x20de8
+				}
+				0x19 => {
+					// Multiple prefixes
+					/* vbroadcastsd */
+					0x20db8
+				}
+				0x58 => {
+					// Multiple prefixes
+					/* vpbroadcastd */
+					0x20de8
+				}
+				0x59 => {
+					// Multiple prefixes
+					/* vpbroadcastq */
+					0x20db8
+				}
+				0x78 => {
+					// Multiple prefixes
+					/* vpbroadcastb */
+					0x20d88
+				}
+				0x79 => {
+					// Multiple prefixes
+					/* vpbroadcastw */
+					0x20da8
+				}
+				_ => 0,
+			}
+		}
+		3 => {
+			match try!(c.next()) {
+				0x0 => {
+					// Multiple prefixes
+					/* vpermq */
+					0x28dd8
+				}
+				0x6 => {
+					// Multiple prefixes
+					/* vperm2f128 */
+					0x28dd8
+				}
+				0x18 => {
+					// Multiple prefixes
+					/* vinsertf128 */
+					0x28dc8
+				}
+				0x19 => {
+					// Multiple prefixes
+					/* vextractf128 */
+					0x28d48
+				}
+				0x38 => {
+					// Multiple prefixes
+					/* vinserti128 */
+					0x28dc8
+				}
+				0x39 => {
+					// Multiple prefixes
+					/* vextracti128 */
+					0x28d48
+				}
+				0x46 => {
+					// Multiple prefixes
+					/* vperm2i128 */
+					0x28dd8
+				}
+				_ => 0,
+			}
+		}
+		_ => 0,
+	})
+}
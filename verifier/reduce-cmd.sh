@@ -1 +1 @@
-llc $1 -filetype=obj -o=reduce.o && ./target/release/verify -b -f reduce.o
\ No newline at end of file
+llc $1 -filetype=obj -o=reduce.o && ./target/release/avery-verify check -b reduce.o
\ No newline at end of file
@@ -0,0 +1,115 @@
+use std::str;
+
+pub const MAGIC: &'static [u8] = b"!<arch>\n";
+
+/// The magic at the start of the LLVM bytecode members of rlibs
+pub const RLIB_BYTECODE_OBJECT_MAGIC: &'static [u8] = b"RUST_OBJECT";
+
+const ELF_MAGIC: &'static [u8] = b"\x7fELF";
+
+const HEADER_SIZE: usize = 60;
+
+/// A member of an `ar` archive
+#[derive(Clone, Debug)]
+pub struct Member<'s> {
+	pub name: &'s str,
+	pub data: &'s [u8],
+}
+
+impl<'s> Member<'s> {
+	/// Returns true for members containing LLVM bytecode instead of machine code
+	pub fn is_bytecode(&self) -> bool {
+		self.data.starts_with(RLIB_BYTECODE_OBJECT_MAGIC) || self.name.ends_with(".bytecode.deflate")
+	}
+
+	pub fn is_object(&self) -> bool {
+		self.data.starts_with(ELF_MAGIC)
+	}
+}
+
+pub fn is_archive(data: &[u8]) -> bool {
+	data.starts_with(MAGIC)
+}
+
+fn field<'s>(header: &'s [u8], start: usize, len: usize) -> Result<&'s str, &'static str> {
+	str::from_utf8(&header[start..(start + len)]).map(|s| s.trim_right()).map_err(|_| "invalid archive member header")
+}
+
+/// Lists the members of an `ar` archive, supporting both the GNU and BSD name formats.
+/// The symbol table and the long name table are not included.
+pub fn members<'s>(data: &'s [u8]) -> Result<Vec<Member<'s>>, &'static str> {
+	if !is_archive(data) {
+		return Err("not an archive");
+	}
+
+	let mut members = Vec::new();
+	let mut names: &[u8] = &[];
+	let mut offset = MAGIC.len();
+
+	while offset < data.len() {
+		if offset + HEADER_SIZE > data.len() {
+			return Err("truncated archive member header");
+		}
+
+		let header = &data[offset..(offset + HEADER_SIZE)];
+
+		if &header[58..60] != b"`\n" {
+			return Err("invalid archive member header");
+		}
+
+		let size: usize = field(header, 48, 10)?.parse().map_err(|_| "invalid archive member size")?;
+
+		let start = offset + HEADER_SIZE;
+		let end = match start.checked_add(size) {
+			Some(end) if end <= data.len() => end,
+			_ => return Err("truncated archive member"),
+		};
+
+		let mut content = &data[start..end];
+		let name = field(header, 0, 16)?;
+
+		// Members are aligned to 2 bytes
+		offset = end + (end & 1);
+
+		let name = if name == "/" || name == "/SYM64/" {
+			continue;
+		} else if name == "//" {
+			names = content;
+			continue;
+		} else if name.starts_with("#1/") {
+			// BSD names are stored at the start of the member
+			let len: usize = name[3..].parse().map_err(|_| "invalid archive member name")?;
+			if len > content.len() {
+				return Err("invalid archive member name");
+			}
+			let name = &content[0..len];
+			content = &content[len..];
+			str::from_utf8(name).map_err(|_| "invalid archive member name")?.trim_right_matches('\0')
+		} else if name.starts_with("/") {
+			// GNU long names are stored in the `//` member and end with "/\n"
+			let start: usize = name[1..].parse().map_err(|_| "invalid archive member name")?;
+			let name = names.get(start..).ok_or("invalid archive member name")?;
+			let name = &name[0..name.iter().position(|&b| b == b'\n').unwrap_or(name.len())];
+			str::from_utf8(name).map_err(|_| "invalid archive member name")?.trim_right_matches('/')
+		} else {
+			name.trim_right_matches('/')
+		};
+
+		// BSD symbol tables may also use long names
+		if name.starts_with("__.SYMDEF") {
+			continue;
+		}
+
+		members.push(Member {
+			name: name,
+			data: content,
+		});
+	}
+
+	Ok(members)
+}
+
+/// Splits the members of an archive into ELF objects and the skipped members, like bytecode and metadata
+pub fn objects<'s>(data: &'s [u8]) -> Result<(Vec<Member<'s>>, Vec<Member<'s>>), &'static str> {
+	members(data).map(|members| members.into_iter().partition(|m| !m.is_bytecode() && m.is_object()))
+}
@@ -0,0 +1,687 @@
+extern crate verifier;
+extern crate elfloader;
+extern crate getopts;
+extern crate time;
+
+use getopts::Options;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use time::PreciseTime;
+use verifier::{archive, image, x86_decoder, disasm, reduce, stack, stats, table, table_file};
+use verifier::disasm::Cases;
+use verifier::table_file::EffectTable;
+use verifier::x86_decoder::{Failure, Line, Operation, Reg};
+use verifier::policy::{self, Policy};
+
+fn diagnostic(code: &image::Code, failure: &Failure, cases: &Cases, effects: Option<&EffectTable>) {
+	println!("error: {}", failure.error);
+	println!("  --> {}+{:#x} at {:#x}", code.name, failure.offset, failure.address);
+	println!("   = stack offset {:#x}", failure.stack_offset);
+	if let Some(target) = failure.target {
+		println!("   = target {:#x}", target);
+	}
+	println!("   = bytes {}", table::bytes(&failure.bytes));
+	if let Some(effects) = effects {
+		match effects.find(&failure.bytes) {
+			Some(effects) => println!("   = effects {:?}", effects),
+			None => println!("   = effects (not in the effect table)"),
+		}
+	}
+
+	// The failure may be in a cold part
+	let (data, parts) = code.joined();
+	let data = &data[..parts.end(failure.offset)];
+
+	for (offset, len) in x86_decoder::window(data, failure.offset, 3) {
+		let address = parts.address(offset);
+		let bytes = &data[offset..(offset + len)];
+		let desc = disasm::describe(bytes, address, cases).map(|d| d.0).unwrap_or("(not in the effect table)".to_string());
+		let marker = if offset == failure.offset { "-->" } else { "   " };
+		println!("  {} {:08x}  {: <24} {}", marker, address, table::bytes(bytes), desc);
+	}
+	println!("");
+}
+
+fn reg(r: Reg) -> &'static str {
+	disasm::REGS64[r.0 as usize]
+}
+
+fn slot(off: i32) -> String {
+	format!("[rsp{}]", disasm::sign_hex(off as i64, true, false))
+}
+
+fn operation(op: &Operation) -> String {
+	match *op {
+		Operation::ClobReg(r) => format!("{} = ?", reg(r)),
+		Operation::ClobStack(off, size) => format!("{}:{} = ?", slot(off), size),
+		Operation::ReadStack(off, size) => format!("read {}:{}", slot(off), size),
+		Operation::MoveRegs(dst, src) => format!("{} = {}", reg(dst), reg(src)),
+		Operation::MoveToStack(off, src) => format!("{} = {}", slot(off), reg(src)),
+		Operation::MoveFromStack(dst, off) => format!("{} = {}", reg(dst), slot(off)),
+		Operation::AndRegFromReg(dst, src) => format!("{} &= {}", reg(dst), reg(src)),
+		Operation::AndRegFromStack(dst, off) => format!("{} &= {}", reg(dst), slot(off)),
+		Operation::AndStackFromReg(off, src) => format!("{} &= {}", slot(off), reg(src)),
+	}
+}
+
+/// The stack offset, recorded operations and masked values of an instruction
+fn annotations(line: &Line) -> String {
+	let mut notes = format!("; stack {:#x}", line.stack_offset);
+
+	if !line.ops.is_empty() {
+		notes.push_str(&format!("; {}", line.ops.iter().map(operation).collect::<Vec<_>>().join(", ")));
+	}
+
+	match line.masked {
+		Some(ref masked) => {
+			let regs = masked.regs.iter().map(|&r| reg(r).to_string());
+			// Slots are relative to the stack pointer at function entry
+			let slots = masked.slots.iter().map(|&s| format!("[entry{}]", disasm::sign_hex(s, true, false)));
+			notes.push_str(&format!("; masked {}", regs.chain(slots).collect::<Vec<_>>().join(", ")));
+		}
+		None => notes.push_str("; unreachable"),
+	}
+
+	notes
+}
+
+fn error(failure: &Failure) {
+	print!("    ^ error: {}", failure.error);
+	if let Some(target) = failure.target {
+		print!(", target {:#x}", target);
+	}
+	println!("");
+}
+
+/// Prints the instructions of a function as they were decoded, along with the verdict
+fn print_listing(code: &image::Code, lines: &[Line], function: &image::Function, cases: &Cases) {
+	println!("{} ({:#x}):", code.name, code.address);
+
+	let (data, parts) = code.joined();
+
+	for line in lines {
+		let address = parts.address(line.offset);
+
+		if line.label {
+			println!(".L{:x}:", address);
+		}
+
+		// Recognized sequences are a single line, but are printed instruction by instruction
+		let end = line.offset + line.len;
+		let mut offset = line.offset;
+		let mut notes = annotations(line);
+
+		while offset < end {
+			let data = &data[offset..end];
+			let (desc, len) = disasm::describe(data, parts.address(offset), cases).unwrap_or(("(not in the effect table)".to_string(), data.len()));
+			let len = std::cmp::min(len, data.len());
+			println!("  {:08x}  {: <24} {: <40} {}", parts.address(offset), table::bytes(&data[..len]), desc, notes);
+			notes = String::new();
+			offset += len;
+		}
+
+		if let Some(ref failure) = function.failure {
+			if failure.offset == line.offset {
+				error(failure);
+			}
+		}
+	}
+
+	if let Some(ref failure) = function.failure {
+		// Decoding stopped at an instruction which has no line
+		if lines.iter().all(|line| line.offset != failure.offset) {
+			println!("  {:08x}  {: <24}", failure.address, table::bytes(&failure.bytes));
+			error(failure);
+		}
+	}
+
+	println!("{} {}", if function.passed() { "ok  " } else { "FAIL" }, code.name);
+	println!("");
+}
+
+/// Verifies a single ELF file, printing an annotated disassembly of each function
+fn disassemble_object(bin: &elfloader::Image, imports: &[&str], policy: &Policy, selection: &image::Selection, cases: &Cases) -> Result<image::Report, &'static str> {
+	let layout = image::layout(bin)?;
+	let ctx = image::context(bin, layout.as_ref(), imports, policy)?;
+
+	let functions = selection.functions(bin, layout.as_ref()).iter().map(|code| {
+		let (lines, function) = image::listing(code, &ctx);
+		print_listing(code, &lines, &function, cases);
+		function
+	}).collect();
+
+	Ok(image::Report {
+		functions: functions,
+		externals: layout.iter().flat_map(|l| l.externals.iter().map(|e| e.name.clone())).collect(),
+	})
+}
+
+/// The file name of the reproducer for `code` from the object named `object`, which is a path or an archive path
+/// followed by the member in parentheses. It has the file name of the object, the member, the symbol and its address.
+fn reproducer_name(object: &str, code: &image::Code) -> String {
+	let (path, member) = match object.find('(') {
+		Some(i) if object.ends_with(')') => (&object[..i], Some(&object[(i + 1)..(object.len() - 1)])),
+		_ => (object, None),
+	};
+
+	let file = Path::new(path).file_name().and_then(|f| f.to_str()).unwrap_or(path);
+
+	let name = match member {
+		Some(member) => format!("{}-{}-{}-{:x}", file, member, code.name, code.address),
+		None => format!("{}-{}-{:x}", file, code.name, code.address),
+	};
+
+	let name: String = name.chars().map(|c| if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' { c } else { '_' }).collect();
+	format!("{}.s", name)
+}
+
+/// Minimizes the `functions` rejected in `report` and writes an assembly reproducer for each into `dir`
+fn reduce_object(object: &str, report: &image::Report, functions: &[image::Code], ctx: &x86_decoder::Context, dir: &str) -> Result<(), &'static str> {
+	let cases = disasm::cases(false);
+
+	fs::create_dir_all(dir).map_err(|_| "unable to create the reproducer directory")?;
+
+	for function in report.functions.iter().filter(|f| !f.passed()) {
+		let code = match functions.iter().find(|c| c.address == function.address && c.name == function.name) {
+			Some(code) => code,
+			None => continue,
+		};
+
+		// A reproducer is a single function, so the part of the function holding the failure is reduced on its own.
+		// Jumps between the parts leave the reduced part.
+		let address = function.failure.as_ref().map(|f| f.address).unwrap_or(code.address);
+		let code = match code.cold.iter().find(|part| address >= part.address && address - part.address < part.data.len() as u64) {
+			Some(part) => part.clone(),
+			None => image::Code {
+				cold: Vec::new(),
+				..code.clone()
+			},
+		};
+		let code = &code;
+
+		let (_, parts) = code.joined();
+		let targets = image::jump_targets(code, &parts, ctx);
+
+		let reduced = match reduce::reduce(code.data, code.address, ctx, targets.as_ref().map(|t| &t[..])) {
+			Some(reduced) => reduced,
+			None => {
+				println!("unable to reduce {}: it is not rejected on its own", code.name);
+				continue
+			}
+		};
+
+		let path = Path::new(dir).join(reproducer_name(object, code));
+
+		File::create(&path).and_then(|mut f| f.write_all(reduce::assembly(code.name, code.address, &reduced, &cases).as_bytes())).map_err(|_| "unable to write the reproducer")?;
+
+		println!("reduced {} from {} to {} bytes: {} ({})", code.name, code.data.len(), reduced.bytes().len(), table::bytes(&reduced.bytes()), reduced.error);
+		println!("  wrote {}", path.display());
+	}
+
+	Ok(())
+}
+
+/// Verifies and prints the results for a single ELF file
+fn verify_object(name: &str, data: &[u8], imports: &[&str], policy: &Policy, threads: usize, selection: &image::Selection, brief: bool, reduce: Option<&str>, effects: Option<&EffectTable>) -> Result<image::Report, &'static str> {
+	let bin = elfloader::Image::new(data)?;
+	let layout = image::layout(&bin)?;
+
+	if image::targets(&bin, layout.as_ref())?.is_some() {
+		println!("Using jump target tables from {}", image::TARGETS_SECTION);
+	}
+
+	let ctx = image::context(&bin, layout.as_ref(), imports, policy)?;
+	let functions = image::functions(&bin, layout.as_ref());
+	let report = image::verify_with(&bin, layout.as_ref(), &functions, &ctx, selection, |functions, ctx| image::verify_functions(functions, ctx, threads));
+
+	// The instruction formats are only needed to print failures
+	let mut cases = None;
+
+	for function in &report.functions {
+		match function.failure {
+			Some(ref failure) => {
+				println!("FAIL {} ({:#x})", function.name, function.address);
+				match functions.iter().find(|c| c.address == function.address && c.name == function.name) {
+					Some(code) => {
+						if cases.is_none() {
+							cases = Some(disasm::cases(false));
+						}
+						diagnostic(code, failure, cases.as_ref().unwrap(), effects)
+					}
+					// Invalid padding between functions
+					None => {
+						println!("error: {}", failure.error);
+						println!("  --> {} at {:#x}", function.name, failure.address);
+						println!("   = bytes {}", table::bytes(&failure.bytes));
+						println!("");
+					}
+				}
+				if brief {
+					writeln!(std::io::stderr(), "{}", failure).unwrap();
+				}
+			}
+			None => println!("ok   {} ({:#x})", function.name, function.address),
+		}
+	}
+
+	for external in &report.externals {
+		println!("extern {} (must be resolved at link time)", external);
+	}
+
+	if let Some(dir) = reduce {
+		reduce_object(name, &report, &functions, &ctx, dir)?;
+	}
+
+	Ok(report)
+}
+
+/// Prints the worst-case stack depth of the functions in `report` if `print` is set.
+/// Returns false if the depth of a function is unbounded or exceeds `limit`.
+fn check_stack(report: &image::Report, limit: Option<u64>, print: bool) -> bool {
+	let entries = stack::entry_points(&report.functions);
+	let mut passed = true;
+
+	for ((function, depth), entry) in report.functions.iter().zip(report.stack_depths()).zip(entries) {
+		let within = match (&depth, limit) {
+			(&Ok(depth), Some(limit)) => depth <= limit,
+			(&Ok(_), None) => true,
+			(&Err(_), limit) => limit.is_none(),
+		};
+
+		if !within {
+			passed = false;
+		} else if !print {
+			continue;
+		}
+
+		let kind = if entry { "entry" } else { "function" };
+
+		match depth {
+			Ok(depth) => println!("{} stack {} bytes for {} {}", if within { "ok  " } else { "FAIL" }, depth, kind, function.name),
+			Err(reason) => println!("{} stack unbounded for {} {}: {}", if within { "ok  " } else { "FAIL" }, kind, function.name, reason),
+		}
+	}
+
+	passed
+}
+
+/// Verifies the inputs without printing anything with one of the opcode decoders.
+/// Returns the number of instructions and the time per instruction in nanoseconds.
+fn bench(inputs: &[(&str, &[u8])], imports: &[&str], policy: &Policy, threads: usize, selection: &image::Selection, table: bool) -> (usize, Option<f64>) {
+	x86_decoder::TABLE_DECODER.store(table, Ordering::Relaxed);
+	x86_decoder::INSTRUCTIONS.store(0, Ordering::Relaxed);
+
+	let start = PreciseTime::now();
+
+	for &(_, data) in inputs {
+		if let Ok(bin) = elfloader::Image::new(data) {
+			image::verify(&bin, imports, policy, threads, selection).ok();
+		}
+	}
+
+	let time = start.to(PreciseTime::now());
+	let insts = x86_decoder::INSTRUCTIONS.load(Ordering::Relaxed);
+
+	(insts, time.num_nanoseconds().map(|n| n as f64 / insts as f64))
+}
+
+/// Exit code when a function, member or stack depth was rejected
+const EXIT_FAILED: i32 = 1;
+/// Exit code for invalid arguments, unreadable or malformed inputs and generator errors
+const EXIT_ERROR: i32 = 2;
+
+const COMMANDS: &'static [(&'static str, &'static str)] = &[
+	("check", "verify ELF files and archives"),
+	("disasm", "print an annotated disassembly of each function"),
+	("stats", "report histograms of the instructions, functions and errors of ELF files and archives"),
+	("gen-table", "generate the effect table"),
+	("gen-decoder", "generate the opcode decoders of verifier_core"),
+	("bench", "report the time per instruction of both opcode decoders"),
+];
+
+fn options(command: &str) -> Options {
+	let mut opts = Options::new();
+	opts.optflag("h", "help", "print the options of the command");
+	opts.optflag("v", "verbose", "print the debug output of the instruction tables");
+
+	match command {
+		"check" | "disasm" | "stats" | "bench" => {
+			opts.optmulti("i", "import", "allow direct calls to the symbol", "<symbol>");
+			opts.optopt("p", "policy", "the sandbox policy to verify against, the compiler's gs: and r15 scheme by default", "<file>");
+			opts.optopt("j", "jobs", "the number of threads to verify with, 4 by default", "<threads>");
+			opts.optflag("t", "table", "decode opcodes with the table driven decoder");
+			opts.optmulti("", "symbol", "only use the function with this name, or names starting with it if it ends with *", "<name>");
+			opts.optmulti("", "section", "only use functions in this section", "<name>");
+		}
+		_ => (),
+	}
+
+	match command {
+		"check" => {
+			opts.optflag("b", "brief", "print a brief error on stderr");
+			opts.optflag("s", "stack", "print the worst-case native stack depth of each function");
+			opts.optopt("", "stack-limit", "reject functions which may use more native stack than this", "<bytes>");
+			opts.optopt("", "reduce", "minimize each rejected function and write an assembly reproducer for it into the directory", "<dir>");
+			opts.optopt("e", "effects", "print the effects of rejected instructions from an effect table written by gen-table", "<file>");
+		}
+		"gen-table" => {
+			opts.optopt("o", "output", "the file to write the table to, effects.table by default", "<file>");
+		}
+		"gen-decoder" => {
+			opts.optflag("", "dfa", "also generate the table driven decoder");
+			opts.optopt("o", "output", "the directory to write the decoders to, core/src by default", "<dir>");
+		}
+		_ => (),
+	}
+
+	opts
+}
+
+fn usage() {
+	println!("Usage: avery-verify <command> [options] [<file>...]");
+	println!("");
+	println!("Commands:");
+	for &(name, desc) in COMMANDS {
+		println!("    {: <12} {}", name, desc);
+	}
+	println!("");
+	println!("Run `avery-verify <command> --help` for the options of a command.");
+}
+
+/// Runs the commands generating the instruction tables, which are checked against Capstone
+#[cfg(feature = "capstone")]
+fn generate(command: &str, matches: &getopts::Matches) -> i32 {
+	use verifier::{gen_decoder, gen_table};
+
+	match command {
+		"gen-table" => {
+			let path = matches.opt_str("o").unwrap_or("effects.table".to_string());
+			gen_table::generate(&path, matches.opt_present("v")).map(|_| 0).unwrap_or_else(fail)
+		}
+		"gen-decoder" => {
+			let dir = matches.opt_str("o").unwrap_or("core/src".to_string());
+			gen_decoder::generate(Path::new(&dir), matches.opt_present("dfa"), matches.opt_present("v")).map(|_| 0).unwrap_or_else(fail)
+		}
+		_ => unreachable!(),
+	}
+}
+
+#[cfg(not(feature = "capstone"))]
+fn generate(command: &str, _: &getopts::Matches) -> i32 {
+	fail(format!("`{}` needs avery-verify to be built with the capstone feature", command))
+}
+
+/// Prints `error` to stderr and exits with `EXIT_ERROR`
+fn fail<T>(error: String) -> T {
+	writeln!(std::io::stderr(), "error: {}", error).unwrap();
+	std::process::exit(EXIT_ERROR)
+}
+
+/// The ELF files named on the command line and how to verify them
+struct Inputs {
+	files: Vec<(String, Vec<u8>)>,
+	imports: Vec<String>,
+	policy: Policy,
+	threads: usize,
+	selection: image::Selection,
+}
+
+impl Inputs {
+	fn new(matches: &getopts::Matches) -> Result<Inputs, String> {
+		if matches.free.is_empty() {
+			return Err("no input files".to_string());
+		}
+
+		let read = |path: &str| {
+			let mut data = Vec::new();
+			File::open(path).and_then(|mut f| f.read_to_end(&mut data)).map_err(|e| format!("unable to read {}: {}", path, e))?;
+			Ok((path.to_string(), data))
+		};
+
+		let policy = match matches.opt_str("p") {
+			Some(path) => {
+				let (_, data) = read(&path)?;
+				let text = String::from_utf8(data).map_err(|_| format!("{} is not UTF-8", path))?;
+				policy::parse(&text).map_err(|e| format!("{}: {}", path, e))?
+			}
+			None => Policy::default(),
+		};
+
+		let threads = match matches.opt_str("j") {
+			Some(j) => j.parse().map_err(|_| format!("invalid number of threads `{}`", j))?,
+			None => 4,
+		};
+
+		Ok(Inputs {
+			files: matches.free.iter().map(|path| read(path)).collect::<Result<_, String>>()?,
+			imports: matches.opt_strs("i"),
+			policy: policy,
+			threads: threads,
+			selection: image::Selection {
+				symbols: matches.opt_strs("symbol"),
+				sections: matches.opt_strs("section"),
+			},
+		})
+	}
+
+	fn imports(&self) -> Vec<&str> {
+		self.imports.iter().map(|s| &s[..]).collect()
+	}
+
+	/// The ELF objects of the inputs, with archives split into their members
+	fn objects(&self) -> Result<Vec<(String, &[u8])>, String> {
+		let mut objects = Vec::new();
+
+		for &(ref path, ref data) in &self.files {
+			if archive::is_archive(data) {
+				let (members, skipped) = archive::objects(data).map_err(|e| format!("{}: {}", path, e))?;
+				for member in skipped {
+					writeln!(std::io::stderr(), "note: skipping {}({}), which is not an ELF object", path, member.name).unwrap();
+				}
+				for member in members {
+					objects.push((format!("{}({})", path, member.name), member.data));
+				}
+			} else {
+				objects.push((path.clone(), &data[..]));
+			}
+		}
+
+		Ok(objects)
+	}
+}
+
+/// Verifies the inputs, printing a diagnostic for each rejected function
+fn check(inputs: &Inputs, matches: &getopts::Matches) -> i32 {
+	let brief = matches.opt_present("b");
+	let stack_limit = match matches.opt_str("stack-limit") {
+		Some(l) => Some(l.parse().unwrap_or_else(|_| fail(format!("invalid stack limit `{}`", l)))),
+		None => None,
+	};
+	let reduce = matches.opt_str("reduce");
+	// The table is validated before anything is verified, so a stale table is an error even if every function passes
+	let effects = matches.opt_str("e").map(|path| {
+		let mut data = Vec::new();
+		File::open(&path).and_then(|mut f| f.read_to_end(&mut data)).unwrap_or_else(|e| fail(format!("unable to read {}: {}", path, e)));
+		EffectTable::load(&data, table_file::current_hash()).unwrap_or_else(|e| fail(format!("{}: {}", path, e)))
+	});
+	let imports = inputs.imports();
+	let objects = inputs.objects().unwrap_or_else(fail);
+
+	let start = PreciseTime::now();
+
+	let mut functions = 0;
+	let mut failed = 0;
+	let mut errors = false;
+	let mut results = Vec::new();
+
+	for &(ref name, data) in &objects {
+		println!("Checking {}", name);
+
+		let passed = match verify_object(name, data, &imports, &inputs.policy, inputs.threads, &inputs.selection, brief, reduce.as_ref().map(|d| &d[..]), effects.as_ref()) {
+			Ok(report) => {
+				functions += report.functions.len();
+				failed += report.failed();
+				let stack = check_stack(&report, stack_limit, matches.opt_present("s"));
+				report.passed() && stack
+			}
+			Err(e) => {
+				writeln!(std::io::stderr(), "error: {}: {}", name, e).unwrap();
+				errors = true;
+				false
+			}
+		};
+
+		results.push((name, passed));
+	}
+
+	let time = start.to(PreciseTime::now());
+
+	if results.len() > 1 {
+		for &(name, passed) in &results {
+			println!("{} {}", if passed { "ok  " } else { "FAIL" }, name);
+		}
+	}
+
+	let insts = x86_decoder::INSTRUCTIONS.load(Ordering::Relaxed);
+
+	let tpi = time.num_nanoseconds().map(|n| n as f64 / insts as f64);
+
+	println!("Done! {} instruction(s) in {}, {:?} ns / instruction", insts, time, tpi);
+	println!("{} function(s), {} failed", functions, failed);
+
+	if errors {
+		EXIT_ERROR
+	} else if results.iter().any(|&(_, passed)| !passed) {
+		EXIT_FAILED
+	} else {
+		0
+	}
+}
+
+/// Prints an annotated disassembly of the selected functions of the inputs
+fn disasm(inputs: &Inputs) -> i32 {
+	// The instruction formats of the effect table, used to print instructions without Capstone
+	let cases = disasm::cases(false);
+	let imports = inputs.imports();
+	let mut code = 0;
+
+	for (name, data) in inputs.objects().unwrap_or_else(fail) {
+		println!("Disassembly of {}", name);
+
+		let result = elfloader::Image::new(data).and_then(|bin| {
+			disassemble_object(&bin, &imports, &inputs.policy, &inputs.selection, &cases)
+		});
+
+		match result {
+			Ok(report) => {
+				for external in &report.externals {
+					println!("extern {} (must be resolved at link time)", external);
+				}
+				if !report.passed() && code == 0 {
+					code = EXIT_FAILED;
+				}
+			}
+			Err(e) => {
+				writeln!(std::io::stderr(), "error: {}: {}", name, e).unwrap();
+				code = EXIT_ERROR;
+			}
+		}
+	}
+
+	code
+}
+
+/// Prints a summary of each input and the statistics of all of them
+fn stats(inputs: &Inputs) -> i32 {
+	let imports = inputs.imports();
+	let mut total = stats::Stats::default();
+	let mut code = 0;
+
+	for (name, data) in inputs.objects().unwrap_or_else(fail) {
+		let result = elfloader::Image::new(data).and_then(|bin| {
+			stats::collect(&bin, &imports, &inputs.policy, inputs.threads, &inputs.selection)
+		});
+
+		match result {
+			Ok(stats) => {
+				println!("{}: {}", name, stats.summary());
+				total.merge(&stats);
+			}
+			Err(e) => {
+				writeln!(std::io::stderr(), "error: {}: {}", name, e).unwrap();
+				code = EXIT_ERROR;
+			}
+		}
+	}
+
+	println!("");
+	print!("{}", total.report());
+
+	code
+}
+
+fn main() {
+	let args: Vec<String> = std::env::args().collect();
+
+	let command = match args.get(1) {
+		Some(command) if COMMANDS.iter().any(|c| c.0 == command) => &command[..],
+		Some(command) if command == "help" || command == "-h" || command == "--help" => {
+			usage();
+			return
+		}
+		Some(command) => {
+			usage();
+			fail(format!("unknown command `{}`", command))
+		}
+		None => {
+			usage();
+			std::process::exit(EXIT_ERROR)
+		}
+	};
+
+	let opts = options(command);
+
+	let matches = opts.parse(&args[2..]).unwrap_or_else(|f| fail(f.to_string()));
+
+	if matches.opt_present("h") {
+		let brief = format!("Usage: avery-verify {} [options] [<file>...]", command);
+		print!("{}", opts.usage(&brief));
+		return
+	}
+
+	if matches.opt_present("v") {
+		unsafe {
+			table::DEBUG = true;
+			disasm::DEBUG = true;
+		}
+	}
+
+	let code = match command {
+		"gen-table" | "gen-decoder" => generate(command, &matches),
+		_ => {
+			x86_decoder::TABLE_DECODER.store(matches.opt_present("t"), Ordering::Relaxed);
+
+			let inputs = Inputs::new(&matches).unwrap_or_else(fail);
+
+			match command {
+				"check" => check(&inputs, &matches),
+				"disasm" => disasm(&inputs),
+				"stats" => stats(&inputs),
+				"bench" => {
+					let objects = inputs.objects().unwrap_or_else(fail);
+					let objects: Vec<_> = objects.iter().map(|&(ref name, data)| (&name[..], data)).collect();
+					for &(name, table) in &[("match", false), ("table", true)] {
+						let (insts, tpi) = bench(&objects, &inputs.imports(), &inputs.policy, inputs.threads, &inputs.selection, table);
+						println!("{} decoder: {} instruction(s), {:?} ns / instruction", name, insts, tpi);
+					}
+					0
+				}
+				_ => unreachable!(),
+			}
+		}
+	};
+
+	std::process::exit(code);
+}
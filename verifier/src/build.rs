@@ -1,3 +1,8 @@
+use std::env;
+
 fn main() {
-    println!("cargo:rustc-link-search=native=../build/pkgs/install/capstone/lib");
-}
\ No newline at end of file
+    // Cargo sets this when the capstone feature is enabled
+    if env::var("CARGO_FEATURE_CAPSTONE").is_ok() {
+        println!("cargo:rustc-link-search=native=../build/pkgs/install/capstone/lib");
+    }
+}
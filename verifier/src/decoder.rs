@@ -1,18 +1,23 @@
 use table;
 use std::cmp;
+#[cfg(feature = "capstone")]
 use std::ptr;
 use effect::{Effect, DecodedOperand, Size, InstFormat, DecodedInst};
 use disasm;
 
 pub static mut BRIEF: bool = false;
 
+// Capstone is only used to check the instruction tables against and isn't needed to verify code
+#[cfg(feature = "capstone")]
 #[link(name = "capstone", kind = "static")]
 extern {}
 
+#[cfg(feature = "capstone")]
 #[path = "../capstone/capstone.rs"]
 #[allow(dead_code, non_snake_case, non_camel_case_types)]
 mod capstone;
 
+#[cfg(feature = "capstone")]
 use self::capstone::csh;
 
 #[derive(Copy, Clone)]
@@ -37,6 +42,7 @@ impl<'s> Cursor<'s> {
 	}
 }
 
+#[cfg(feature = "capstone")]
 pub fn capstone_open() -> csh {
 	use self::capstone::*;
 
@@ -53,6 +59,7 @@ pub fn capstone_open() -> csh {
 	}
 }
 
+#[cfg(feature = "capstone")]
 pub fn capstone_close(mut handle: csh) {
 	use self::capstone::*;
 
@@ -61,12 +68,14 @@ pub fn capstone_close(mut handle: csh) {
 	}
 }
 
+#[cfg(feature = "capstone")]
 #[derive(Debug)]
 enum Reg {
 	IP,
 	GP(usize)
 }
 
+#[cfg(feature = "capstone")]
 fn reg(cr: u16) -> Option<Reg> {
 	use self::capstone::Enum_x86_reg::*;
 
@@ -93,6 +102,7 @@ fn reg(cr: u16) -> Option<Reg> {
 	}
 }
 
+#[cfg(feature = "capstone")]
 pub fn capstone(handle: &mut csh, data: &[u8], disp_off: u64, inst: &DecodedInst, effects: &[Effect]) -> bool {
 	use std::ffi::CStr;
 	use self::capstone::*;
@@ -144,6 +154,7 @@ pub fn capstone(handle: &mut csh, data: &[u8], disp_off: u64, inst: &DecodedInst
 	error
 }
 
+#[cfg(feature = "capstone")]
 pub fn capstone_simple(data: &[u8], disp_off: u64) -> Option<(String, usize)> {
 	use std::ffi::CStr;
 	use std::ptr;
@@ -181,12 +192,10 @@ pub fn capstone_simple(data: &[u8], disp_off: u64) -> Option<(String, usize)> {
 
 pub fn inst(c: &mut Cursor, disp_off: u64, cases: &[(Vec<u8>, Vec<Effect>, InstFormat)]) -> (DecodedInst, Vec<Effect>) {
 	let case = cases.iter().find(|i| c.remaining().starts_with(&i.0[..])).unwrap_or_else(|| {
-		let data = &c.remaining()[0..cmp::min(16, c.remaining().len())];
-		let (desc, len) = capstone_simple(data, 0).unwrap_or(("invalid".to_string(), 1));
-		let bytes = table::bytes(&c.remaining()[0..len]);
+		let bytes = table::bytes(&c.remaining()[0..cmp::min(16, c.remaining().len())]);
 
-		println!("unknown |{}| capstone: {}", bytes, desc);
-		panic!("unknown |{}| capstone: {}", bytes, desc);
+		println!("unknown |{}|", bytes);
+		panic!("unknown |{}|", bytes);
 	});
 
 	c.offset += case.2.bytes.len();
@@ -201,7 +210,6 @@ pub fn find_effect(cursor: &Cursor, cases: &[(Vec<u8>, Vec<Effect>)]) -> Option<
 
 pub fn decode(data: &[u8], func_start: usize, size: usize, disp_off: u64, cases: &[(Vec<u8>, Vec<Effect>, InstFormat)]) {
 	let mut targets = Vec::new();
-	let mut cp = capstone_open();
 	targets.push(func_start);
 
 	let mut i = 0;
@@ -218,7 +226,6 @@ pub fn decode(data: &[u8], func_start: usize, size: usize, disp_off: u64, cases:
 			let start = c.offset;
 			let address = start as u64 + disp_off;
 			print!("{:#08x}: ", address);
-			let cs_data = &c.remaining()[0..cmp::min(16, c.remaining().len())];
 			let (i, effects) = inst(&mut c, address, cases);
 			let mut str = String::new();
 
@@ -236,11 +243,6 @@ pub fn decode(data: &[u8], func_start: usize, size: usize, disp_off: u64, cases:
 			print!("{}", str);
 
 			println!("{: <40} {:?} ({:x}/{:x})", i.desc, effects, c.offset - func_start, size);
-/*
-			if capstone(&mut cp, cs_data, address, &i, &effects) {
-				panic!("Capstone output didn't match");
-			}
-*/
 			if effects.iter().any(|o| match *o { Effect::Jmp32 | Effect::Jmp8 => true, _ => false }) {
 				let op: (DecodedOperand, Size) = i.operands.first().unwrap().clone();
 				let off = match op.0 {
@@ -273,6 +275,4 @@ pub fn decode(data: &[u8], func_start: usize, size: usize, disp_off: u64, cases:
 
 		i += 1;
 	}
-
-	capstone_close(cp);
 }
@@ -31,7 +31,7 @@ const REGS_MMX: &'static [&'static str] = &["mm0", "mm1", "mm2", "mm3", "mm4", "
 	"mm8", "mm9", "mm10", "mm11", "mm12", "mm13", "mm14", "mm15"]; 
 const REGS_SSE: &'static [&'static str] = &["xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm7",
 	"xmm8", "xmm9", "xmm10", "xmm11", "xmm12", "xmm13", "xmm14", "xmm15"]; 
-const REGS64: &'static [&'static str] = &["rax", "rcx", "rdx", "rbx", "rsp", "rbp", "rsi", "rdi",
+pub const REGS64: &'static [&'static str] = &["rax", "rcx", "rdx", "rbx", "rsp", "rbp", "rsi", "rdi",
 	  "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15",
 	  "rip"];  // RIP appended
 const REGS32: &'static [&'static str] = &["eax", "ecx", "edx", "ebx", "esp", "ebp", "esi", "edi",
@@ -110,9 +110,42 @@ enum ModRM {
 	Sib(Disp, usize),
 }
 
+/// The opcode bytes of an instruction with its effects and format
+pub type Cases = Vec<(Vec<u8>, Vec<Effect>, InstFormat)>;
+
+/// Generates every instruction of `ops` sorted by their bytes
+pub fn all_cases(ops: &[Inst]) -> Cases {
+	let mut cases = Vec::new();
+
+	for op in ops {
+		gen_all(op, &mut cases)
+	}
+
+	cases.sort_by(|a, b| a.0.cmp(&b.0));
+	cases
+}
+
+/// Generates every instruction of the description returned by `table::list_insts`
+pub fn cases(verify: bool) -> Cases {
+	let mut ops = Vec::new();
+	table::list_insts(&mut ops, verify);
+	all_cases(&ops)
+}
+
+/// Formats the instruction at the start of `data` using the formats of `cases`. Returns the description and the length.
+pub fn describe(data: &[u8], address: u64, cases: &Cases) -> Option<(String, usize)> {
+	cases.iter().find(|case| data.starts_with(&case.0[..])).map(|case| {
+		let mut c = Cursor {
+			data: data,
+			offset: case.2.bytes.len(),
+		};
+		let inst = parse(&mut c, address, &case.2);
+		(inst.desc, inst.len)
+	})
+}
+
 pub fn gen_all(inst: &Inst, cases: &mut Vec<(Vec<u8>, Vec<Effect>, InstFormat)>) {
 	let name = &inst.name[..];
-	unsafe { DEBUG = name == "lea"; };
 	//unsafe { DEBUG = name == "mov" && !inst.prefix_bytes.is_empty(); };
 	debug!("Generating all {} {}\n", name, table::bytes(&inst.bytes));
 
@@ -546,6 +579,8 @@ pub fn gen_all(inst: &Inst, cases: &mut Vec<(Vec<u8>, Vec<Effect>, InstFormat)>)
 									_ => panic!(),
 								}
 							}
+							// Only legacy encodings are generated
+							(Operand::Vvvv(_), _, _) => panic!(),
 						}
 					}
 
@@ -637,7 +672,7 @@ pub fn gen_all(inst: &Inst, cases: &mut Vec<(Vec<u8>, Vec<Effect>, InstFormat)>)
 	}
 }
 
-fn sign_hex(i: i64, plus: bool, space: bool) -> String {
+pub fn sign_hex(i: i64, plus: bool, space: bool) -> String {
 	let space = if space { " " } else { "" };
 	let plus = if plus { format!("{}+{}", space, space) } else { "".to_string() };
 
@@ -7,6 +7,7 @@ pub enum Size {
 	S64,
 	S128,
 	SRexSize, // S32 without REX_W, S64 with
+	SVexSize, // S128 without VEX.L, S256 with
 	SImmSize,
 	SOpSize,
 }
@@ -35,6 +36,7 @@ pub enum Operand {
 	Reg(Regs),
 	RmOpcode(usize),
 	Mem(Option<usize>),
+	Vvvv(Regs), // Register encoded in VEX.vvvv
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -98,6 +100,8 @@ pub struct Inst {
 	pub desc: String,
 	pub operand_size: Size,
 	pub len: usize,
+	/// The instruction is VEX encoded. `bytes` starts with the escape bytes of the opcode map and `prefix_bytes` holds the implied prefix.
+	pub vex: bool,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -159,7 +163,6 @@ pub enum Disp {
 	Imm32,
 }
 
-// Store in 6 bits - 2 for Rip/None/Imm8/Imm32 - 4 for register
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Mem {
 	Rip,
@@ -167,26 +170,6 @@ pub enum Mem {
 }
 
 impl Mem {
-	pub fn encode(self) -> usize {
-		match self {
-			Mem::Rip => 0,
-			Mem::Mem(r, Disp::None) => 1 | r << 2,
-			Mem::Mem(r, Disp::Imm8) => 2 | r << 2,
-			Mem::Mem(r, Disp::Imm32) => 3 | r << 2,
-		}
-	}
-	
-	pub fn decode(val: usize) -> Self {
-		let r = val >> 2;
-		match val & 3 {
-			0 => Mem::Rip,
-			1 => Mem::Mem(r, Disp::None),
-			2 => Mem::Mem(r, Disp::Imm8),
-			3 => Mem::Mem(r, Disp::Imm32),
-			_ => panic!(),
-		}
-	}
-	
 	pub fn trailing_bytes(self) -> usize {
 		match self {
 			Mem::Rip => 4,
@@ -203,8 +186,6 @@ pub enum StackMem {
 	MemRSP(Disp),
 }
 
-// 10 bits for Mem + usize
-// 6 bits for rest
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Effect {
 	None,
@@ -234,144 +215,6 @@ pub enum Effect {
 }
 
 impl Effect {
-	pub fn encode(list: &[Effect]) -> usize {
-		let first = if let Some(first) = list.first() {
-			first
-		} else {
-			return 0;
-		};
-
-		// data: 10 bits
-		// case: 4 bits
-		let (case, data) = match *first {
-			Effect::None => panic!(),
-
-			Effect::CheckMem(mem) => (1, mem.encode()),
-			Effect::WriteStack(mem) => (2, mem.encode()),
-			Effect::ReadStack(mem) => (3, mem.encode()),
-			Effect::Store(mem, r) => (4, mem.encode() | r << 6), 
-			Effect::Load(r, mem) => (5, mem.encode() | r << 6),
-			Effect::Push(r) => (6, r),
-			Effect::Pop(r) => (7, r),
-			Effect::CheckAddr => (8, 0),
-			Effect::Call(mem) => (9, mem.encode()),
-			Effect::Call32 => (10, 0),
-			Effect::Jmp32 => (11, 0),
-			Effect::Jmp8 => (12, 0),
-			Effect::Ud2 => (13, 0),
-			Effect::Move(f, t) => (14, f << 4 | t),
-
-			// No main case
-			Effect::ClobReg(..) |
-			Effect::Imm8 |
-			Effect::Imm16 |
-			Effect::Imm32 |
-			Effect::Imm64 => (0, 0),
-		};
-
-		let rem_list = if case != 0 {
-			&list[1..list.len()]
-		} else {
-			list
-		};
-
-		// 4 bits for the rest
-		let (rest, rest_data) = match *rem_list {
-			[] => (0, 0),
-			//[Effect::Imm64] => 1,
-			[Effect::Imm32] => (2, 0),
-			[Effect::Imm16] => (3, 0),
-			[Effect::Imm8] => (4, 0),
-			[Effect::ClobReg(r)] => (5, r),
-			[Effect::ClobReg(r), Effect::Imm8] => (6, r),
-			[Effect::ClobReg(r), Effect::Imm16] => (7, r),
-			[Effect::ClobReg(r), Effect::Imm32] => (8, r),
-			[Effect::ClobReg(r), Effect::Imm64] => (9, r),
-			[Effect::ClobReg(0), Effect::ClobReg(2)] => (10, 0),
-			_ => panic!("Don't know how to encode rest {:?}", rem_list),
-		};
-
-		let rest = rest | rest_data << 4;
-
-		let encoded = case | data << 4 | rest << 14 | rest << 14;
-
-		let decoded = Effect::decode(encoded);
-
-		if list != &decoded[..] {
-			panic!("Encoding of {:?} ({:x}) decoded into {:?}", list, encoded, decoded);
-		}
-
-		encoded
-	}
-
-	pub fn decode(mut list: usize) -> Vec<Effect> {
-		let mut result = Vec::new();
-		let case = list & 0xF; // 4 bits
-		list = list >> 4;
-
-		let mem = list & 0x3F; // 6 bits;
-
-		result.push(match case {
-			1 => Effect::CheckMem(Mem::decode(mem)),
-			2 => Effect::WriteStack(Mem::decode(mem)),
-			3 => Effect::ReadStack(Mem::decode(mem)),
-			4 => Effect::Store(Mem::decode(mem), (list >> 6) & 0xF), 
-			5 => Effect::Load((list >> 6) & 0xF, Mem::decode(mem)),
-			6 => Effect::Push(list & 0xF),
-			7 => Effect::Pop(list & 0xF),
-			8 => Effect::CheckAddr,
-			9 => Effect::Call(Mem::decode(mem)),
-			10 => Effect::Call32,
-			11 => Effect::Jmp32,
-			12 => Effect::Jmp8,
-			13 => Effect::Ud2,
-			14 => Effect::Move((list >> 4) & 0xF, list & 0xF),
-
-			0 => Effect::None,
-
-			_ => panic!("Unknown main case {:?}", case),
-		});
-
-		list = list >> 10;
-
-		let case = list & 0xF;
-		list = list >> 4;
-
-		let rest = match case {
-			0 => (),
-			//[Effect::Imm64] => 1,
-			2 => result.push(Effect::Imm32),
-			3 => result.push(Effect::Imm16),
-			4 => result.push(Effect::Imm8),
-			5 => result.push(Effect::ClobReg(list & 0xF)),
-			6 => {
-				result.push(Effect::ClobReg(list & 0xF));
-				result.push(Effect::Imm8);
-			}
-			7 => {
-				result.push(Effect::ClobReg(list & 0xF));
-				result.push(Effect::Imm16);
-			}
-			8 => {
-				result.push(Effect::ClobReg(list & 0xF));
-				result.push(Effect::Imm32);
-			}
-			9 => {
-				result.push(Effect::ClobReg(list & 0xF));
-				result.push(Effect::Imm64);
-			}
-			10 => {
-				result.push(Effect::ClobReg(0));
-				result.push(Effect::ClobReg(2));
-			}
-			_ => panic!("Unknown rest case {:?}", case),
-		};
-
-		result.retain(|v| *v != Effect::None);
-
-		result
-	}
-
 	pub fn sort_key(self) -> usize {
 		match self {
 			Effect::None |
@@ -1,32 +1,15 @@
-#![feature(trace_macros)]
-#![feature(log_syntax)]
-#![feature(plugin)]
-#![feature(const_fn)]
-#![feature(slice_patterns)]
-#![feature(inclusive_range_syntax)]
-#![allow(dead_code)]
-//#![cfg_attr(test, feature(plugin, custom_attribute))]
-//#![cfg_attr(test, plugin(quickcheck_macros))]
-
-//#[cfg(test)]
-//extern crate quickcheck;
-
-extern crate elfloader;
-extern crate byteorder;
-extern crate core;
-extern crate getopts;
-
+use std;
 use std::cell::Cell;
 use std::process::Command;
 use std::path::Path;
 use std::fs::File;
 use std::io::Write;
 use std::collections::HashMap;
-
-mod effect;
-mod decoder;
-mod table;
-mod disasm;
+use std::iter;
+use effect;
+use table;
+#[cfg(feature = "capstone")]
+use decoder;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
 #[repr(packed)]
@@ -63,6 +46,8 @@ pub enum InstKind {
 	Jcc8,
 	Ud2,
 	Ret,
+	VecWriteRm, // Rm <- xmm/ymm
+	VecReadRm, // xmm/ymm <- Rm
 }
 
 // 2 bits
@@ -81,6 +66,8 @@ pub enum Opsize {
 	Size64,
 	Size128,
 	SizeDef,
+	SizeVec, // Size128 without VEX.L, 256 bits with
+	Size32,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
@@ -102,7 +89,77 @@ fn bit(b: bool) -> u32 {
 	if b { 1 } else { 0 }
 }
 
+const KINDS: &'static [InstKind] = &[
+	InstKind::Illegal,
+	InstKind::None,
+	InstKind::NopRm,
+	InstKind::WriteRm,
+	InstKind::AddRmImm,
+	InstKind::SubRmImm,
+	InstKind::ReadRmToReg,
+	InstKind::ReadRm,
+	InstKind::Store,
+	InstKind::Load,
+	InstKind::AndRmFromReg,
+	InstKind::AndRmToReg,
+	InstKind::XchgRm,
+	InstKind::Lea,
+	InstKind::Push(Reg(0)),
+	InstKind::Pop(Reg(0)),
+	InstKind::ClobRegRex(Reg(0)),
+	InstKind::CheckAddr,
+	InstKind::CallRm,
+	InstKind::Call32,
+	InstKind::Jmp32,
+	InstKind::Jmp8,
+	InstKind::Jcc32,
+	InstKind::Jcc8,
+	InstKind::Ud2,
+	InstKind::Ret,
+	InstKind::VecWriteRm,
+	InstKind::VecReadRm,
+];
+
 impl InstKind {
+	/// The kind encoded as `case` in the generated decoders, see `Inst::kind`.
+	/// The register of `Push`, `Pop` and `ClobRegRex` isn't part of the case and is 0.
+	pub fn from_case(case: u8) -> Option<InstKind> {
+		KINDS.iter().cloned().find(|kind| kind.encode() == case as u32)
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			InstKind::Illegal => "Illegal",
+			InstKind::None => "None",
+			InstKind::NopRm => "NopRm",
+			InstKind::WriteRm => "WriteRm",
+			InstKind::AddRmImm => "AddRmImm",
+			InstKind::SubRmImm => "SubRmImm",
+			InstKind::ReadRmToReg => "ReadRmToReg",
+			InstKind::ReadRm => "ReadRm",
+			InstKind::Store => "Store",
+			InstKind::Load => "Load",
+			InstKind::AndRmFromReg => "AndRmFromReg",
+			InstKind::AndRmToReg => "AndRmToReg",
+			InstKind::XchgRm => "XchgRm",
+			InstKind::Lea => "Lea",
+			InstKind::Push(_) => "Push",
+			InstKind::Pop(_) => "Pop",
+			InstKind::ClobRegRex(_) => "ClobRegRex",
+			InstKind::CheckAddr => "CheckAddr",
+			InstKind::CallRm => "CallRm",
+			InstKind::Call32 => "Call32",
+			InstKind::Jmp32 => "Jmp32",
+			InstKind::Jmp8 => "Jmp8",
+			InstKind::Jcc32 => "Jcc32",
+			InstKind::Jcc8 => "Jcc8",
+			InstKind::Ud2 => "Ud2",
+			InstKind::Ret => "Ret",
+			InstKind::VecWriteRm => "VecWriteRm",
+			InstKind::VecReadRm => "VecReadRm",
+		}
+	}
+
 	fn modrm(self) -> bool {
 		match self {
 			InstKind::WriteRm |
@@ -117,6 +174,8 @@ impl InstKind {
 			InstKind::AddRmImm |
 			InstKind::SubRmImm |
 			InstKind::NopRm |
+			InstKind::VecWriteRm |
+			InstKind::VecReadRm |
 			InstKind::Lea => true,
 			InstKind::Illegal |
 			InstKind::Push(_) |
@@ -161,6 +220,8 @@ impl InstKind {
 			InstKind::AddRmImm => 23,
 			InstKind::SubRmImm => 24,
 			InstKind::NopRm => 25,
+			InstKind::VecWriteRm => 26,
+			InstKind::VecReadRm => 27,
 		}
 	}
 }
@@ -184,6 +245,8 @@ impl Opsize {
 			Opsize::Size16 => 2,
 			Opsize::Size64 => 3,
 			Opsize::Size128 => 4,
+			Opsize::SizeVec => 5,
+			Opsize::Size32 => 6,
 		}
 	}
 }
@@ -317,6 +380,17 @@ impl ByteTrie {
 	}
 }
 
+/// Capstone's description of an opcode which isn't in the tables, for the comments of the generated decoders
+#[cfg(feature = "capstone")]
+fn describe(bytes: &[u8]) -> Option<(String, usize)> {
+	decoder::capstone_simple(bytes, 0)
+}
+
+#[cfg(not(feature = "capstone"))]
+fn describe(_: &[u8]) -> Option<(String, usize)> {
+	None
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 enum Multiplexer {
 	Opcode(HashMap<u8, Inst>),
@@ -330,7 +404,7 @@ impl Multiplexer {
 				let entries: Vec<_> = (0..8u8).map(|i| map.get(&i).map(|v| Ok(v)).unwrap_or_else(|| {
 					let mut b = taken.clone();
 					b.extend_from_slice(&[0x1A, 0x1A, 0x1A, 0x1A, 0x1A, 0x1A, 0x1A, 0x1A, 0x1A, 0x1A, 0x1A, 0x1A, 0x1A, 0x1A, 0x1A, 0x1A]);
-					Err(decoder::capstone_simple(&b, 0))
+					Err(describe(&b))
 				})).collect();
 
 				let first = map.values().next().unwrap();
@@ -424,12 +498,137 @@ impl Multiplexer {
 	}
 }
 
-fn main() {
-	let mut ops = Vec::new();
+// Transition tags of the table driven decoder, these must match `dfa.rs`
+const DFA_STATE: u16 = 1 << 14;
+const DFA_PREFIX: u16 = 2 << 14;
+const DFA_OPCODE: u16 = 3 << 14;
+
+/// A table driven decoder with the same output as the generated `match`.
+/// Each state has 256 transitions. A transition either leads to another state or ends decoding with
+/// a group of output words. Prefix groups hold words for the 66, F2 and F3 prefixes, in the order the `match` tests them,
+/// followed by the default word. Opcode groups hold a word for each value of the ModRM reg field.
+/// Identical groups are stored once.
+struct Dfa {
+	transitions: Vec<u16>,
+	prefix_words: Vec<u32>,
+	opcode_words: Vec<u32>,
+	groups: HashMap<(u16, Vec<u32>), u16>,
+}
+
+impl Dfa {
+	fn new() -> Dfa {
+		Dfa {
+			transitions: Vec::new(),
+			prefix_words: Vec::new(),
+			opcode_words: Vec::new(),
+			groups: HashMap::new(),
+		}
+	}
+
+	fn state(&mut self, map: &HashMap<u8, Box<ByteTrie>>) -> u16 {
+		let index = self.transitions.len() / 256;
+		self.transitions.extend(iter::repeat(0).take(256));
+
+		let mut entries = map.iter().collect::<Vec<_>>();
+		entries.sort_by_key(|e| e.0);
+
+		for (&b, tree) in entries {
+			let transition = self.transition(tree);
+			self.transitions[index * 256 + b as usize] = transition;
+		}
+
+		assert!(index < DFA_STATE as usize);
+		index as u16
+	}
+
+	fn transition(&mut self, tree: &ByteTrie) -> u16 {
+		match *tree {
+			ByteTrie::Empty => panic!(),
+			ByteTrie::Map(ref map) => DFA_STATE | self.state(map),
+			ByteTrie::Decoded(ref multi) => {
+				let (tag, words) = multi.words();
+				self.group(tag, words)
+			}
+		}
+	}
+
+	fn group(&mut self, tag: u16, words: Vec<u32>) -> u16 {
+		if let Some(&transition) = self.groups.get(&(tag, words.clone())) {
+			return transition;
+		}
+
+		let list = if tag == DFA_PREFIX { &mut self.prefix_words } else { &mut self.opcode_words };
+		let index = list.len() / words.len();
+		list.extend_from_slice(&words);
+
+		assert!(index < DFA_STATE as usize);
+		let transition = tag | index as u16;
+		self.groups.insert((tag, words), transition);
+		transition
+	}
+
+	fn write_list<T: std::fmt::LowerHex>(writer: &mut Write, name: &str, ty: &str, list: &[T]) {
+		write!(writer, "pub static {}: [{}; {}] = [\n", name, ty, list.len()).unwrap();
+		for line in list.chunks(16) {
+			writer.write_all("\t".as_bytes()).unwrap();
+			for (i, v) in line.iter().enumerate() {
+				write!(writer, "{}{:#x},", if i == 0 { "" } else { " " }, v).unwrap();
+			}
+			writer.write_all("\n".as_bytes()).unwrap();
+		}
+		writer.write_all("];\n".as_bytes()).unwrap();
+	}
+
+	fn write(&self, writer: &mut Write, vex_starts: &[u16]) {
+		writer.write_all("// Generated by avery-verify gen-decoder, see `Dfa` there for the format\n\n".as_bytes()).unwrap();
+		write!(writer, "pub static VEX_STARTS: [u16; {}] = {:?};\n\n", vex_starts.len(), vex_starts).unwrap();
+		Dfa::write_list(writer, "PREFIX_WORDS", "u32", &self.prefix_words);
+		writer.write_all("\n".as_bytes()).unwrap();
+		Dfa::write_list(writer, "OPCODE_WORDS", "u32", &self.opcode_words);
+		writer.write_all("\n".as_bytes()).unwrap();
+		Dfa::write_list(writer, "TRANSITIONS", "u16", &self.transitions);
+	}
+}
+
+impl Multiplexer {
+	fn words(&self) -> (u16, Vec<u32>) {
+		match *self {
+			Multiplexer::Opcode(ref map) => {
+				let words: Vec<u32> = (0..8u8).map(|i| map.get(&i).map(|v| v.1 as u32).unwrap_or(0)).collect();
+				(DFA_OPCODE, words)
+			}
+			Multiplexer::Prefix(ref map) => {
+				let mut words = vec![0; 4];
+				let first = map.values().next().unwrap();
+
+				if map.values().all(|v| v.1 == first.1) {
+					words[3] = first.1 as u32;
+				} else {
+					for (prefixes, inst) in map {
+						let slot = match prefixes[..] {
+							[] => 3,
+							[table::P_OP_SIZE] => 0,
+							[table::P_REPNE] => 1,
+							[table::P_REP] => 2,
+							_ => panic!(),
+						};
+						words[slot] = inst.1 as u32;
+					}
+				}
+
+				(DFA_PREFIX, words)
+			}
+		}
+	}
+}
 
-	unsafe { table::DEBUG = true };
+/// Generates the opcode decoders `x86_opcodes.rs` and `x86_vex_opcodes.rs` into `out`, and the table driven decoder
+/// `x86_dfa.rs` if `dfa` is set. The formats and operands found are listed if `verbose` is set.
+pub fn generate(out: &Path, dfa: bool, verbose: bool) -> Result<(), String> {
+	let mut ops = Vec::new();
 
 	table::list_insts(&mut ops, false);
+	table::list_vex_insts(&mut ops);
 	
 	let mut operands = Vec::new();
 	let mut formats = Vec::new();
@@ -466,6 +665,9 @@ fn main() {
 		}).find(|&(m, _)| m).unwrap_or((false, op.operand_size));
 
 		let opsize = match mem_opsize {
+			// VEX.pp doesn't change the operand size, so 32-bit operands have a fixed size
+			Size::S32 if op.vex => Opsize::Size32,
+			Size::SVexSize => Opsize::SizeVec,
 			Size::S8 => Opsize::Size8,
 			Size::S16 => Opsize::Size16,
 			Size::S32 if !op.prefix_whitelist.contains(&table::P_OP_SIZE) => Opsize::SizeDef,
@@ -552,6 +754,8 @@ fn main() {
 			let ops: Vec<_> = ops.into_iter().filter(|o| {
 				match *o {
 					(Operand::Imm(_), _, _) => false,
+					// VEX.vvvv only names a source vector register
+					(Operand::Vvvv(_), _, _) => false,
 					_ => true,
 				}
 			}).collect();
@@ -560,6 +764,8 @@ fn main() {
 
 			match ops[..] {
 				[] => InstKind::None,
+				[(Operand::Reg(Regs::SSE), _, Access::Write), (Operand::Rm(..), _, Access::Read)] if op.vex => InstKind::VecReadRm,
+				[(Operand::Rm(Regs::SSE), _, Access::Write), (Operand::Reg(Regs::SSE), _, Access::Read)] if op.vex => InstKind::VecWriteRm,
 				[(Operand::Addr, _, _)] => InstKind::CheckAddr,
 				[(Operand::FixRegRex(r, effect::Regs::GP), _, Access::Write)] => InstKind::ClobRegRex(Reg(r as u8)),
 				[(Operand::Rm(..), _, Access::Read)] => {
@@ -631,18 +837,25 @@ fn main() {
 
 	println!("Formats {}:", formats.len());
 
-	for op in &formats {
-		println!("- {:?}", op);
+	if verbose {
+		for op in &formats {
+			println!("- {:?}", op);
+		}
 	}
 
 	println!("Operands {}:", operands.len());
 
-	for op in &operands {
-		println!("- {:?}", op);
+	if verbose {
+		for op in &operands {
+			println!("- {:?}", op);
+		}
 	}
 
 	let mut tree = ByteTrie::Empty;
 
+	// VEX encoded instructions for the 0f, 0f 38 and 0f 3a opcode maps
+	let mut vex_trees = vec![ByteTrie::Empty, ByteTrie::Empty, ByteTrie::Empty];
+
 	let insert_op = |tree: &mut ByteTrie, op, bytes: &[u8]| {
 		let opcode = tree.get_new(bytes, Vec::new());
 
@@ -696,6 +909,20 @@ fn main() {
 
 	for op in &ops {
 		//println!("Inserting {} {}", table::bytes(&op.bytes), op.name);
+		if op.vex {
+			let (map, escape) = if op.bytes.starts_with(&[0x0f, 0x38]) {
+				(1, 2)
+			} else if op.bytes.starts_with(&[0x0f, 0x3a]) {
+				(2, 2)
+			} else if op.bytes.starts_with(&[0x0f]) {
+				(0, 1)
+			} else {
+				panic!("VEX instruction {} is not in an opcode map", op.name)
+			};
+			insert_op(&mut vex_trees[map], op, &op.bytes[escape..]);
+			continue;
+		}
+
 		if op.name == "nop" && &op.bytes == &[0x0f, 0x1f] {
 			let mut bytes = op.bytes.clone();
 			bytes.insert(0, table::P_SEG_CS);
@@ -717,13 +944,50 @@ fn main() {
 
 	//println!("Tree: {:#?}", tree);
 
-	let mut output = File::create(&Path::new("src/x86_opcodes.rs")).unwrap();
+	let create = |name: &str| {
+		let path = out.join(name);
+		File::create(&path).map(|file| (path.clone(), file)).map_err(|e| format!("unable to create {}: {}", path.display(), e))
+	};
+
+	let (path, mut output) = create("x86_opcodes.rs")?;
 
 	output.write_all("use x86_decoder::{Cursor, CursorError};pub fn decode(c: &mut Cursor, prefixes: u32) -> Result<u32, CursorError> { Ok(".as_bytes()).unwrap();
 	tree.write(&mut output, Vec::new());
 	output.write_all(")}".as_bytes()).unwrap();
 
-	Command::new("rustfmt").arg("src/x86_opcodes.rs").output().unwrap();
+	Command::new("rustfmt").arg(&path).output().map_err(|e| format!("unable to run rustfmt: {}", e))?;
+
+	let (path, mut output) = create("x86_vex_opcodes.rs")?;
+
+	output.write_all("use x86_decoder::{Cursor, CursorError};pub fn decode(c: &mut Cursor, map: u8, prefixes: u32) -> Result<u32, CursorError> { Ok(match map { ".as_bytes()).unwrap();
+	for (i, tree) in vex_trees.iter().enumerate() {
+		write!(output, "{} => {{ ", i + 1).unwrap();
+		tree.write(&mut output, Vec::new());
+		output.write_all("}".as_bytes()).unwrap();
+	}
+	output.write_all("_ => 0 })}".as_bytes()).unwrap();
+
+	Command::new("rustfmt").arg(&path).output().map_err(|e| format!("unable to run rustfmt: {}", e))?;
+
+	if dfa {
+		let mut dfa = Dfa::new();
+
+		let root = match tree {
+			ByteTrie::Map(ref map) => dfa.state(map),
+			_ => panic!(),
+		};
+		assert!(root == 0);
+
+		let vex_starts: Vec<u16> = vex_trees.iter().map(|tree| match *tree {
+			ByteTrie::Map(ref map) => dfa.state(map),
+			_ => panic!(),
+		}).collect();
+
+		let (_, mut output) = create("x86_dfa.rs")?;
+		dfa.write(&mut output, &vex_starts);
+
+		println!("DFA: {} states, {} prefix words, {} opcode words", dfa.transitions.len() / 256, dfa.prefix_words.len(), dfa.opcode_words.len());
+	}
 
-	println!("Done!");
+	Ok(())
 }
@@ -0,0 +1,80 @@
+use crossbeam;
+use std::fs::File;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use effect::*;
+use decoder;
+use disasm;
+use table;
+use table_file::{self, EffectTable};
+
+/// Generates the effect table and writes it to `path`. Every instruction is checked against Capstone first,
+/// and nothing is written if they disagree. Returns the number of instructions in the table.
+pub fn generate(path: &str, verbose: bool) -> Result<usize, String> {
+	let mut ops = Vec::new();
+
+	table::list_insts(&mut ops, true);
+
+	let hash = table_file::generator_hash(&ops);
+
+	let cases = &disasm::all_cases(&ops);
+
+	println!("Testing {} entries...", cases.len());
+
+	let error = &AtomicBool::new(false);
+
+	crossbeam::scope(|scope| {
+		for chunk in cases.chunks(cases.len() / 4 + 1) {
+			scope.spawn(move || {
+				let mut cp = decoder::capstone_open();
+				for &(ref bytes, ref effects, ref format) in chunk {
+					let mut xs = bytes.clone();
+					for e in effects.iter() {
+						for _ in 0..e.trailing_bytes() {
+							xs.push(0x1D);
+						}
+					}
+					let mut c = decoder::Cursor {
+						data: &xs,
+						offset: bytes.len(),
+					};
+
+					let inst = disasm::parse(&mut c, 0, format);
+
+					if verbose {
+						println!("Instruction {} {} => {:?}", table::bytes(&xs), inst.desc, effects);
+					}
+
+					if decoder::capstone(&mut cp, &xs, 0, &inst, effects) {
+						error.store(true, Ordering::SeqCst);
+					}
+				}
+				decoder::capstone_close(cp);
+			});
+		}
+	});
+
+	if error.load(Ordering::SeqCst) {
+		return Err("output didn't match Capstone".to_string());
+	}
+
+	println!("Building table...");
+
+	let data = table_file::write(cases, hash).map_err(|e| e.to_string())?;
+
+	// Make sure every effect list survives the encoding
+	let table = EffectTable::load(&data, hash).map_err(|e| e.to_string())?;
+
+	for op in cases {
+		let effects: Vec<_> = op.1.iter().cloned().filter(|&e| e != Effect::None).collect();
+		if table.get(&op.0) != Some(&effects[..]) {
+			return Err(format!("effects of {} {:?} decoded into {:?}", table::bytes(&op.0), op.1, table.get(&op.0)));
+		}
+	}
+
+	File::create(path).and_then(|mut f| f.write_all(&data)).map_err(|e| format!("unable to write {}: {}", path, e))?;
+
+	println!("Wrote {} instructions to {} (hash {:016x})", table.len(), path, hash);
+
+	Ok(table.len())
+}
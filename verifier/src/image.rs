@@ -0,0 +1,27 @@
+use crossbeam;
+use elfloader::Image;
+use x86_decoder::Context;
+use policy::Policy;
+
+pub use verifier_core::image::*;
+
+/// Verifies `functions` using up to `threads` threads.
+/// The results are in the same order as `functions` regardless of the number of threads.
+pub fn verify_functions(functions: &[Code], ctx: &Context, threads: usize) -> Vec<Function> {
+	if threads <= 1 || functions.len() <= 1 {
+		return verify_each(functions, ctx);
+	}
+
+	crossbeam::scope(|scope| {
+		let handles: Vec<_> = functions.chunks(functions.len() / threads + 1).map(|chunk| {
+			scope.spawn(move || verify_each(chunk, ctx))
+		}).collect();
+
+		handles.into_iter().flat_map(|handle| handle.join()).collect()
+	})
+}
+
+/// Verifies the functions in `bin` picked by `selection` against `policy` like `verify_image`, using up to `threads` threads
+pub fn verify(bin: &Image, imports: &[&str], policy: &Policy, threads: usize, selection: &Selection) -> Result<Report, &'static str> {
+	verify_image(bin, imports, policy, selection, |functions, ctx| verify_functions(functions, ctx, threads))
+}
@@ -0,0 +1,32 @@
+#![feature(trace_macros)]
+#![feature(log_syntax)]
+#![feature(plugin)]
+#![feature(const_fn)]
+#![feature(slice_patterns)]
+#![feature(stmt_expr_attributes)]
+#![feature(inclusive_range_syntax)]
+#![feature(question_mark)]
+#![allow(dead_code)]
+
+extern crate elfloader;
+extern crate byteorder;
+extern crate crossbeam;
+extern crate fst;
+extern crate core;
+extern crate verifier_core;
+
+pub use verifier_core::{x86_decoder, cfi, stack, relocate};
+
+pub mod effect;
+pub mod decoder;
+pub mod table;
+pub mod table_file;
+pub mod disasm;
+pub mod policy;
+pub mod image;
+pub mod archive;
+pub mod reduce;
+pub mod stats;
+#[cfg(feature = "capstone")]
+pub mod gen_table;
+pub mod gen_decoder;
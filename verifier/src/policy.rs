@@ -0,0 +1,41 @@
+use table;
+
+pub use verifier_core::policy::*;
+
+/// Finds every encoding of the instruction named `name` in the effect table
+pub fn encodings(name: &str) -> Vec<Forbidden> {
+	let mut ops = Vec::new();
+	table::list_insts(&mut ops, true);
+	table::list_vex_insts(&mut ops);
+
+	ops.iter().filter(|op| op.name == name).map(|op| {
+		let (map, bytes) = if op.vex {
+			// VEX encodings hold the escape bytes of the opcode map in `bytes`
+			match (op.bytes[0], op.bytes.get(1).cloned()) {
+				(0x0f, Some(0x38)) => (Some(2), op.bytes[2..].to_vec()),
+				(0x0f, Some(0x3a)) => (Some(3), op.bytes[2..].to_vec()),
+				_ => (Some(1), op.bytes[1..].to_vec()),
+			}
+		} else {
+			(None, op.bytes.clone())
+		};
+
+		let selectors = ops.iter().filter(|other| other.vex == op.vex && other.bytes == op.bytes).fold(0, |bits, other| {
+			bits | prefix_bits(&other.prefix_bytes)
+		});
+
+		Forbidden {
+			name: name.to_string(),
+			map: map,
+			bytes: bytes,
+			ext: op.opcode.map(|ext| ext as u8),
+			prefixes: prefix_bits(&op.prefix_bytes),
+			selectors: selectors,
+		}
+	}).collect()
+}
+
+/// Parses a policy description, see `Policy::parse`
+pub fn parse(text: &str) -> Result<Policy, String> {
+	Policy::parse(text, encodings)
+}
@@ -0,0 +1,125 @@
+use std::cmp;
+use disasm::{self, Cases};
+use x86_decoder::{self, Context, DecoderError, Parts};
+
+/// A rejected function shrunk to the instructions needed to reproduce its error
+#[derive(Clone, Debug)]
+pub struct Reduced {
+	pub error: DecoderError,
+	pub insts: Vec<Vec<u8>>,
+}
+
+impl Reduced {
+	pub fn bytes(&self) -> Vec<u8> {
+		self.insts.concat()
+	}
+}
+
+/// Removes chunks of `items` while `fails` holds for the remaining ones, trying smaller chunks when none can be removed.
+/// The result is 1-minimal, so removing any single item from it makes `fails` false. At least one item is kept.
+pub fn minimize<T: Clone, F: FnMut(&[T]) -> bool>(mut items: Vec<T>, mut fails: F) -> Vec<T> {
+	let mut chunks = 2;
+
+	while items.len() > 1 {
+		let size = (items.len() + chunks - 1) / chunks;
+		let mut removed = false;
+		let mut start = 0;
+
+		while start < items.len() && items.len() > 1 {
+			let end = cmp::min(start + size, items.len());
+			let candidate: Vec<T> = items[..start].iter().chain(&items[end..]).cloned().collect();
+
+			if !candidate.is_empty() && fails(&candidate) {
+				items = candidate;
+				removed = true;
+			} else {
+				start = end;
+			}
+		}
+
+		if removed {
+			chunks = cmp::max(chunks - 1, 2);
+		} else if size == 1 {
+			break
+		} else {
+			chunks = cmp::min(chunks * 2, items.len());
+		}
+	}
+
+	items
+}
+
+/// Moves the jump `targets` of a function to the offsets of their instructions in `insts`, which are the remaining
+/// instructions of the function along with their original offsets. Targets of removed instructions are dropped.
+fn remap(targets: &[(u64, u32)], insts: &[(usize, Vec<u8>)]) -> Vec<(u64, u32)> {
+	let mut result = vec![(0, 0)];
+	let mut offset = 0;
+
+	for &(original, ref bytes) in insts {
+		if offset != 0 {
+			if let Some(&(_, stack_offset)) = targets.iter().find(|t| t.0 == original as u64) {
+				result.push((offset as u64, stack_offset));
+			}
+		}
+
+		offset += bytes.len();
+	}
+
+	result
+}
+
+/// Delta debugs the function in `data` at `address` at instruction granularity, keeping the instructions needed for
+/// it to be rejected with the same `DecoderError` variant. Instruction boundaries come from `x86_decoder::instructions`.
+/// If the function has a jump target table, `targets` must be it. The targets of the remaining instructions are kept.
+/// Returns `None` if the function isn't rejected.
+pub fn reduce(data: &[u8], address: u64, ctx: &Context, targets: Option<&[(u64, u32)]>) -> Option<Reduced> {
+	let decode = |insts: &[(usize, Vec<u8>)]| {
+		let bytes: Vec<u8> = insts.iter().flat_map(|inst| inst.1.iter().cloned()).collect();
+
+		match targets {
+			Some(targets) => x86_decoder::decode_with_targets(&bytes, &Parts::new(address, bytes.len()), ctx, &remap(targets, insts)),
+			None => x86_decoder::decode(&bytes, address, ctx),
+		}
+	};
+
+	let insts: Vec<(usize, Vec<u8>)> = x86_decoder::instructions(data, address, ctx).into_iter().map(|(offset, len)| {
+		(offset, data[offset..(offset + len)].to_vec())
+	}).collect();
+
+	let error = match decode(&insts) {
+		Ok(_) => return None,
+		Err(failure) => failure.error,
+	};
+
+	let insts = minimize(insts, |insts| {
+		decode(insts).err().map(|failure| failure.error) == Some(error.clone())
+	});
+
+	Some(Reduced {
+		error: error,
+		insts: insts.into_iter().map(|inst| inst.1).collect(),
+	})
+}
+
+/// A standalone assembly file defining the reduced function as the global symbol `reduced`.
+/// The instructions are emitted as bytes so the reproducer assembles to exactly the rejected encodings.
+/// They are described using the instruction formats of `cases`.
+pub fn assembly(name: &str, address: u64, reduced: &Reduced, cases: &Cases) -> String {
+	let mut out = String::new();
+
+	out.push_str(&format!("# Reduced from {} at {:#x}\n", name, address));
+	out.push_str(&format!("# error: {}\n", reduced.error));
+	out.push_str("\t.text\n\t.globl reduced\n\t.type reduced, @function\nreduced:\n");
+
+	let mut offset = 0;
+
+	for inst in &reduced.insts {
+		let desc = disasm::describe(inst, address + offset as u64, cases).map(|d| d.0).unwrap_or("(not in the effect table)".to_string());
+		let list = inst.iter().map(|b| format!("{:#04x}", b)).collect::<Vec<_>>().join(", ");
+		out.push_str(&format!("\t.byte {: <40} # {}\n", list, desc));
+		offset += inst.len();
+	}
+
+	out.push_str("\t.size reduced, .-reduced\n");
+	out
+}
@@ -0,0 +1,290 @@
+use std::collections::BTreeMap;
+use elfloader::Image;
+use gen_decoder::InstKind;
+use image::{self, Code, Function, Selection};
+use policy::Policy;
+use x86_decoder::{self, Context, Rm};
+
+/// The number of times each value was seen
+#[derive(Clone, Debug, Default)]
+pub struct Histogram(pub BTreeMap<String, usize>);
+
+impl Histogram {
+	pub fn add(&mut self, key: &str) {
+		*self.0.entry(key.to_string()).or_insert(0) += 1;
+	}
+
+	pub fn get(&self, key: &str) -> usize {
+		self.0.get(key).cloned().unwrap_or(0)
+	}
+
+	pub fn merge(&mut self, other: &Histogram) {
+		for (key, &count) in &other.0 {
+			*self.0.entry(key.clone()).or_insert(0) += count;
+		}
+	}
+
+	/// Lists the values by decreasing count, with their share of `total`
+	fn write(&self, out: &mut String, title: &str, total: usize) {
+		let mut entries: Vec<_> = self.0.iter().collect();
+		entries.sort_by(|a, b| (b.1, a.0).cmp(&(a.1, b.0)));
+
+		out.push_str(&format!("{}:\n", title));
+
+		for (key, &count) in entries {
+			out.push_str(&format!("    {: <24} {: >8} {: >6.2}%\n", key, count, percent(count, total)));
+		}
+
+		out.push_str("\n");
+	}
+}
+
+fn percent(count: usize, total: usize) -> f64 {
+	if total == 0 {
+		0.0
+	} else {
+		count as f64 * 100.0 / total as f64
+	}
+}
+
+/// Instruction and function statistics of verified code
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+	pub functions: usize,
+	pub failed: usize,
+	/// The number of ranges of invalid padding between functions
+	pub padding: usize,
+	pub bytes: usize,
+	pub insts: usize,
+	/// By `InstKind`. Recognized sequences are counted as `sequence` and instructions the decoder rejected as `rejected`.
+	pub kinds: Histogram,
+	/// By opcode bytes after the prefixes. Opcode groups which select the operation with the ModRM reg field include it, like `83 /5`.
+	pub opcodes: Histogram,
+	pub prefixes: Histogram,
+	/// The addressing mode of the ModRM operand
+	pub addressing: Histogram,
+	/// The number of functions by size in bytes, bucketed by powers of two starting at 16
+	pub sizes: BTreeMap<usize, usize>,
+	/// The number of rejected functions and ranges of invalid padding by `DecoderError` variant
+	pub errors: Histogram,
+}
+
+/// Splits the legacy, REX and VEX prefixes off an instruction and names them and the opcode
+fn describe(bytes: &[u8]) -> (Vec<&'static str>, String) {
+	let mut prefixes = Vec::new();
+	let mut rest = bytes;
+
+	while let Some(&byte) = rest.first() {
+		prefixes.push(match byte {
+			0x26 => "es",
+			0x2E => "cs",
+			0x36 => "ss",
+			0x3E => "ds",
+			0x64 => "fs",
+			0x65 => "gs",
+			0x66 => "operand size (66)",
+			0x67 => "address size (67)",
+			0xF0 => "lock (F0)",
+			0xF2 => "repne (F2)",
+			0xF3 => "rep (F3)",
+			_ => break,
+		});
+		rest = &rest[1..];
+	}
+
+	if let Some(&rex) = rest.first() {
+		if rex & 0xF0 == 0x40 {
+			prefixes.push(if rex & 8 != 0 { "REX.W" } else { "REX" });
+			rest = &rest[1..];
+		}
+	}
+
+	let (map, rest) = match rest.first() {
+		Some(&0xC5) if rest.len() >= 2 => {
+			prefixes.push(if rest[1] & 4 != 0 { "VEX.L" } else { "VEX" });
+			("VEX.0F ", &rest[2..])
+		}
+		Some(&0xC4) if rest.len() >= 3 => {
+			prefixes.push(if rest[2] & 4 != 0 { "VEX.L" } else { "VEX" });
+			(match rest[1] & 0x1F {
+				1 => "VEX.0F ",
+				2 => "VEX.0F38 ",
+				3 => "VEX.0F3A ",
+				_ => "VEX.? ",
+			}, &rest[3..])
+		}
+		Some(&0x0F) => match rest.get(1) {
+			Some(&0x38) => ("0F 38 ", &rest[2..]),
+			Some(&0x3A) => ("0F 3A ", &rest[2..]),
+			_ => ("0F ", &rest[1..]),
+		},
+		_ => ("", rest),
+	};
+
+	let opcode = match rest.first() {
+		Some(&op) => {
+			let group = match (map, op) {
+				("", 0x80...0x83) | ("", 0x8F) | ("", 0xC0...0xC1) | ("", 0xC6...0xC7) |
+				("", 0xD0...0xD3) | ("", 0xF6...0xF7) | ("", 0xFE...0xFF) => true,
+				("0F ", 0x00...0x01) | ("0F ", 0x18...0x1F) | ("0F ", 0x71...0x73) |
+				("0F ", 0xAE) | ("0F ", 0xBA) | ("0F ", 0xC7) => true,
+				("VEX.0F ", 0x71...0x73) | ("VEX.0F ", 0xAE) => true,
+				_ => false,
+			};
+
+			match rest.get(1) {
+				Some(&modrm) if group => format!("{}{:02X} /{}", map, op, (modrm >> 3) & 7),
+				_ => format!("{}{:02X}", map, op),
+			}
+		}
+		None => format!("{}(truncated)", map),
+	};
+
+	(prefixes, opcode)
+}
+
+fn addressing(rm: Rm, ctx: &Context) -> Option<&'static str> {
+	Some(match rm {
+		Rm::None => return None,
+		Rm::Reg(_) => "register",
+		Rm::Stack(_) => "stack",
+		Rm::Base(reg, _) if ctx.policy.data_stack.map(|d| d.reg) == Some(reg) => "data stack",
+		Rm::Base(..) => "base register",
+		Rm::Rip(_) => "RIP-relative",
+	})
+}
+
+impl Stats {
+	/// Adds a function and its verification result. Instructions are found with a linear sweep,
+	/// so the instructions of rejected functions are counted too.
+	pub fn add(&mut self, code: &Code, function: &Function, ctx: &Context) {
+		let size = function.size as usize;
+
+		self.functions += 1;
+		self.bytes += size;
+
+		let bucket = if size < 16 { 0 } else { (size + 1).next_power_of_two() / 2 };
+		*self.sizes.entry(bucket).or_insert(0) += 1;
+
+		if let Some(ref failure) = function.failure {
+			self.failed += 1;
+			self.errors.add(&format!("{:?}", failure.error));
+		}
+
+		for part in Some(code).into_iter().chain(&code.cold) {
+			self.sweep(part, ctx);
+		}
+	}
+
+	/// Adds a range of invalid padding returned by `image::padding`
+	pub fn add_padding(&mut self, gap: &Function) {
+		self.padding += 1;
+
+		if let Some(ref failure) = gap.failure {
+			self.errors.add(&format!("{:?}", failure.error));
+		}
+	}
+
+	fn sweep(&mut self, code: &Code, ctx: &Context) {
+		for (offset, len, inst) in x86_decoder::sweep(code.data, code.address, ctx) {
+			self.insts += 1;
+
+			match inst {
+				Some(ref inst) if inst.sequence => {
+					self.kinds.add("sequence");
+					continue
+				}
+				Some(inst) => {
+					self.kinds.add(inst.kind.and_then(InstKind::from_case).map(|kind| kind.name()).unwrap_or("unknown"));
+
+					if let Some(mode) = addressing(inst.rm, ctx) {
+						self.addressing.add(mode);
+					}
+				}
+				None => self.kinds.add("rejected"),
+			}
+
+			let (prefixes, opcode) = describe(&code.data[offset..(offset + len)]);
+
+			for prefix in prefixes {
+				self.prefixes.add(prefix);
+			}
+
+			self.opcodes.add(&opcode);
+		}
+	}
+
+	pub fn merge(&mut self, other: &Stats) {
+		self.functions += other.functions;
+		self.failed += other.failed;
+		self.padding += other.padding;
+		self.bytes += other.bytes;
+		self.insts += other.insts;
+		self.kinds.merge(&other.kinds);
+		self.opcodes.merge(&other.opcodes);
+		self.prefixes.merge(&other.prefixes);
+		self.addressing.merge(&other.addressing);
+		self.errors.merge(&other.errors);
+
+		for (&bucket, &count) in &other.sizes {
+			*self.sizes.entry(bucket).or_insert(0) += count;
+		}
+	}
+
+	/// A one line summary
+	pub fn summary(&self) -> String {
+		format!("{} function(s), {} failed, {} invalid padding range(s), {} bytes, {} instruction(s)",
+			self.functions, self.failed, self.padding, self.bytes, self.insts)
+	}
+
+	/// The histograms as text. Kinds, opcodes, prefixes and addressing modes are shares of the instructions,
+	/// sizes are shares of the functions and errors are shares of the functions and padding ranges.
+	pub fn report(&self) -> String {
+		let mut out = String::new();
+
+		out.push_str(&format!("{}\n\n", self.summary()));
+
+		self.kinds.write(&mut out, "Instruction kinds", self.insts);
+		self.opcodes.write(&mut out, "Opcodes", self.insts);
+		self.prefixes.write(&mut out, "Prefixes", self.insts);
+		self.addressing.write(&mut out, "Addressing modes", self.insts);
+
+		out.push_str("Function sizes:\n");
+
+		for (&bucket, &count) in &self.sizes {
+			let range = if bucket == 0 {
+				"0-15 bytes".to_string()
+			} else {
+				format!("{}-{} bytes", bucket, bucket * 2 - 1)
+			};
+			out.push_str(&format!("    {: <24} {: >8} {: >6.2}%\n", range, count, percent(count, self.functions)));
+		}
+
+		out.push_str("\n");
+
+		self.errors.write(&mut out, "Errors", self.functions + self.padding);
+
+		out
+	}
+}
+
+/// Verifies the functions in `bin` picked by `selection` like `image::verify` and collects their statistics
+pub fn collect(bin: &Image, imports: &[&str], policy: &Policy, threads: usize, selection: &Selection) -> Result<Stats, &'static str> {
+	let layout = image::layout(bin)?;
+	let ctx = image::context(bin, layout.as_ref(), imports, policy)?;
+
+	let functions = selection.functions(bin, layout.as_ref());
+	let results = image::verify_functions(&functions, &ctx, threads);
+
+	let mut stats = Stats::default();
+
+	for (code, function) in functions.iter().zip(&results) {
+		stats.add(code, function, &ctx);
+	}
+
+	for gap in selection.padding(bin, layout.as_ref()) {
+		stats.add_padding(&gap);
+	}
+
+	Ok(stats)
+}
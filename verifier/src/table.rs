@@ -52,6 +52,8 @@ enum OpOption {
 	Rm,
 	SSE,
 	SSEOff,
+	AVX,
+	Vvvv,
 	Read,
 	Write,
 	Implicit(usize, Access),
@@ -89,130 +91,138 @@ struct State {
 	no_mem: bool,
 }
 
-pub fn list_insts(ops: &mut Vec<Inst>, verify: bool) {
-	let opts = |options: &[OpOption], inst: &mut Inst, def_op_size: Size| {
-		let mut op_size = SOpSize;
-		let mut imm_size = SImmSize;
-		let mut regs = Regs::GP;
-		let mut access = Access::Write;
-		let mut to_read = false;
-
-		for opt in options.iter() {
-			if inst.operands.len() >= 1 && !to_read {
-				to_read = true;
+fn opts(options: &[OpOption], inst: &mut Inst, def_op_size: Size) {
+	let mut op_size = SOpSize;
+	let mut imm_size = SImmSize;
+	let mut regs = Regs::GP;
+	let mut access = Access::Write;
+	let mut to_read = false;
+
+	for opt in options.iter() {
+		if inst.operands.len() >= 1 && !to_read {
+			to_read = true;
+			access = Access::Read;
+		}
+
+		//debug!("Appling option {:?}, opsize = {:?}\n", opt, op_size);
+		match *opt {
+			ImmSize(size) => {
+				imm_size = size;
+			}
+			OpSize(size) => {
+				op_size = size;
+			}
+			OpSizeDef => {
+				op_size = def_op_size;
+			}
+			OpSizePostfix => {
+				inst.op_size_postfix = true;
+			}
+			Implicit(r, access) => {
+				inst.accesses.push((r, access));
+			}
+			Prefix(p) => {
+				inst.prefix_whitelist.push(p);
+			}
+			Read => {
 				access = Access::Read;
 			}
+			Write => {
+				access = Access::Write;
+			}
+			SSEOff => {
+				op_size = op_size;
+				regs = Regs::GP;
+			}
+			OpSizeLimit32 => {
+				op_size = if op_size == S64 { S32 } else { op_size };
+			}
+			SSE => {
+				op_size = S128;
+				regs = Regs::SSE;
+			}
+			AVX => {
+				op_size = SVexSize;
+				regs = Regs::SSE;
+			}
+			NoMem => {
+				inst.no_mem = true;
+			}
+			FixRegRex(reg) => {
+				inst.operands.push((Operand::FixRegRex(reg, regs), op_size, access));
+			}
+			FixReg(reg) => {
+				inst.operands.push((Operand::FixReg(reg, regs), op_size, access));
+			}
+			FixImm(imm) => {
+				inst.operands.push((Operand::FixImm(imm, Lit1), Lit1, Access::Read));
+			}
+			Addr => {
+				inst.operands.push((Operand::Addr, op_size, access));
+			}
+			Imm => {
+				inst.operands.push((Operand::Imm(imm_size), op_size, Access::Read));
+			}
+			Disp => {
+				inst.operands.push((Operand::Disp(imm_size), S64, Access::Read));
+			}
+			Rm => {
+				inst.operands.push((Operand::Rm(regs), op_size, access));
+			}
+			Reg => {
+				inst.operands.push((Operand::Reg(regs), op_size, access));
+			}
+			Vvvv => {
+				inst.operands.push((Operand::Vvvv(regs), op_size, access));
+			}
+			RmOpcode(opcode_ext) => {
+				inst.opcode = Some(opcode_ext);
+				inst.operands.push((Operand::RmOpcode(opcode_ext), op_size, access));
+			}
+			Mem(opcode_ext) => {
+				inst.opcode = opcode_ext;
+				inst.operands.push((Operand::Mem(opcode_ext), op_size, access));
+			}
+			_ => panic!("unhandled {:?}", opt)
+		};
+	}
+	inst.operand_size = op_size;
+}
 
-			//debug!("Appling option {:?}, opsize = {:?}\n", opt, op_size);
-			match *opt {
-				ImmSize(size) => {
-					imm_size = size;
-				}
-				OpSize(size) => {
-					op_size = size;
-				}
-				OpSizeDef => {
-					op_size = def_op_size;
-				}
-				OpSizePostfix => {
-					inst.op_size_postfix = true;
-				}
-				Implicit(r, access) => {
-					inst.accesses.push((r, access));
-				}
-				Prefix(p) => {
-					inst.prefix_whitelist.push(p);
-				}
-				Read => {
-					access = Access::Read;
-				}
-				Write => {
-					access = Access::Write;
-				}
-				SSEOff => {
-					op_size = op_size;
-					regs = Regs::GP;
-				}
-				OpSizeLimit32 => {
-					op_size = if op_size == S64 { S32 } else { op_size };
-				}
-				SSE => {
-					op_size = S128;
-					regs = Regs::SSE;
-				}
-				NoMem => {
-					inst.no_mem = true;
-				}
-				FixRegRex(reg) => {
-					inst.operands.push((Operand::FixRegRex(reg, regs), op_size, access));
-				}
-				FixReg(reg) => {
-					inst.operands.push((Operand::FixReg(reg, regs), op_size, access));
-				}
-				FixImm(imm) => {
-					inst.operands.push((Operand::FixImm(imm, Lit1), Lit1, Access::Read));
-				}
-				Addr => {
-					inst.operands.push((Operand::Addr, op_size, access));
-				}
-				Imm => {
-					inst.operands.push((Operand::Imm(imm_size), op_size, Access::Read));
-				}
-				Disp => {
-					inst.operands.push((Operand::Disp(imm_size), S64, Access::Read));
-				}
-				Rm => {
-					inst.operands.push((Operand::Rm(regs), op_size, access));
-				}
-				Reg => {
-					inst.operands.push((Operand::Reg(regs), op_size, access));
-				}
-				RmOpcode(opcode_ext) => {
-					inst.opcode = Some(opcode_ext);
-					inst.operands.push((Operand::RmOpcode(opcode_ext), op_size, access));
-				}
-				Mem(opcode_ext) => {
-					inst.opcode = opcode_ext;
-					inst.operands.push((Operand::Mem(opcode_ext), op_size, access));
-				}
-				_ => panic!("unhandled {:?}", opt)
-			};
-		}
-		inst.operand_size = op_size;
+fn do_op(full_code: &[u8], name: &str, options: &[OpOption], def_op_size: Size, vex: bool, ops: &mut Vec<Inst>) {
+	let mut prefix_len = 0;
+	while ALL_PREFIXES.contains(&full_code[prefix_len]) {
+		prefix_len += 1;
+	}
+	let code_prefixes = &full_code[0..prefix_len];
+	let code = &full_code[prefix_len..];
+
+	let mut inst = Inst {
+		prefix_bytes: code_prefixes.to_vec(),
+		bytes: code.to_vec(),
+		opcode: None,
+		prefix_whitelist: vec![],
+		operands: Vec::new(),
+		decoded_operands: Vec::new(),
+		op_size_postfix: false,
+		accesses: Vec::new(),
+		operand_size: SOpSize,
+		no_mem: false,
+		desc: "".to_string(),
+		name: name.to_string(),
+		len: 0,
+		vex: vex,
 	};
 
-	let do_op = |full_code: &[u8], name: &str, options: &[OpOption], def_op_size: Size, ops: &mut Vec<Inst>| {
-		let mut prefix_len = 0;
-		while ALL_PREFIXES.contains(&full_code[prefix_len]) {
-			prefix_len += 1;
-		}
-		let code_prefixes = &full_code[0..prefix_len];
-		let code = &full_code[prefix_len..];
-
-		let mut inst = Inst {
-			prefix_bytes: code_prefixes.to_vec(),
-			bytes: code.to_vec(),
-			opcode: None,
-			prefix_whitelist: vec![],
-			operands: Vec::new(),
-			decoded_operands: Vec::new(),
-			op_size_postfix: false,
-			accesses: Vec::new(),
-			operand_size: SOpSize,
-			no_mem: false,
-			desc: "".to_string(),
-			name: name.to_string(),
-			len: 0,
-		};
+	opts(options, &mut inst, def_op_size);
 
-		opts(options, &mut inst, def_op_size);
-
-		ops.push(inst);
-	};
+	ops.push(inst);
+}
 
+pub fn list_insts(ops: &mut Vec<Inst>, verify: bool) {
 	macro_rules! op {
 		($code:expr, $name:expr, $opts:expr) => ({
-			do_op(&$code, $name, &$opts, SOpSize, ops);
+			do_op(&$code, $name, &$opts, SOpSize, false, ops);
 		})
 	}
 
@@ -228,8 +238,8 @@ pub fn list_insts(ops: &mut Vec<Inst>, verify: bool) {
 			let mut c = Vec::new();
 			c.extend(&$code);
 			*c.last_mut().unwrap() += 1;
-			do_op(&$code, $name, &o, S8, ops);
-			do_op(&c[..], $name, &$opts, SOpSize, ops);
+			do_op(&$code, $name, &o, S8, false, ops);
+			do_op(&c[..], $name, &$opts, SOpSize, false, ops);
 		})
 	}
 
@@ -485,4 +495,102 @@ pub fn list_insts(ops: &mut Vec<Inst>, verify: bool) {
 
 	pair!([0xe6], "out", [OpSize(S8), ImmSize(S8), Imm, OpSizeDef, OpSizeLimit32, FixReg(0)]);
 	pair!([0xee], "out", [OpSize(S16), FixReg(2), OpSizeDef, OpSizeLimit32, FixReg(0)]);
-}
\ No newline at end of file
+}
+
+/// Lists the VEX encoded instructions. These use the opcode maps of the legacy `0f`, `0f 38` and `0f 3a` escapes,
+/// with the `66`, `f3` and `f2` prefixes implied by VEX.pp. Vector sizes depend on VEX.L.
+pub fn list_vex_insts(ops: &mut Vec<Inst>) {
+	macro_rules! op {
+		($code:expr, $name:expr, $opts:expr) => ({
+			do_op(&$code, $name, &$opts, SOpSize, true, ops);
+		})
+	}
+
+	op!([0x0f, 0x77], "vzeroupper", []);
+
+	op!([0x0f, 0x10], "vmovups", [AVX, Reg, Rm]);
+	op!([0x0f, 0x11], "vmovups", [AVX, Rm, Reg]);
+	op!([0x66, 0x0f, 0x10], "vmovupd", [AVX, Reg, Rm]);
+	op!([0x66, 0x0f, 0x11], "vmovupd", [AVX, Rm, Reg]);
+
+	op!([0x0f, 0x28], "vmovaps", [AVX, Reg, Rm]);
+	op!([0x0f, 0x29], "vmovaps", [AVX, Rm, Reg]);
+	op!([0x66, 0x0f, 0x28], "vmovapd", [AVX, Reg, Rm]);
+	op!([0x66, 0x0f, 0x29], "vmovapd", [AVX, Rm, Reg]);
+
+	// The register forms of vmovss and vmovsd merge with VEX.vvvv, the memory forms ignore it
+	op!([0xf3, 0x0f, 0x10], "vmovss", [AVX, Reg, Vvvv, OpSize(S32), Rm]);
+	op!([0xf3, 0x0f, 0x11], "vmovss", [AVX, OpSize(S32), Rm, Vvvv, OpSize(S128), Reg]);
+	op!([0xf2, 0x0f, 0x10], "vmovsd", [AVX, Reg, Vvvv, OpSize(S64), Rm]);
+	op!([0xf2, 0x0f, 0x11], "vmovsd", [AVX, OpSize(S64), Rm, Vvvv, OpSize(S128), Reg]);
+
+	op!([0x66, 0x0f, 0x6f], "vmovdqa", [AVX, Reg, Rm]);
+	op!([0x66, 0x0f, 0x7f], "vmovdqa", [AVX, Rm, Reg]);
+	op!([0xf3, 0x0f, 0x6f], "vmovdqu", [AVX, Reg, Rm]);
+	op!([0xf3, 0x0f, 0x7f], "vmovdqu", [AVX, Rm, Reg]);
+
+	op!([0x66, 0x0f, 0x6e], "vmov", [OpSizePostfix, AVX, OpSize(S128), Reg, OpSize(SRexSize), SSEOff, Rm]);
+	op!([0x66, 0x0f, 0x7e], "vmov", [OpSizePostfix, OpSize(SRexSize), Rm, AVX, OpSize(S128), Reg]);
+	op!([0xf3, 0x0f, 0x7e], "vmovq", [AVX, OpSize(S128), Reg, OpSize(S64), Rm]);
+	op!([0x66, 0x0f, 0xd6], "vmovq", [AVX, OpSize(S64), Rm, OpSize(S128), Reg]);
+
+	for &(instr, opcode) in &[("add", 0x58), ("mul", 0x59), ("sub", 0x5c), ("min", 0x5d), ("div", 0x5e), ("max", 0x5f)] {
+		op!([0x0f, opcode], &format!("v{}ps", instr), [AVX, Reg, Vvvv, Rm]);
+		op!([0x66, 0x0f, opcode], &format!("v{}pd", instr), [AVX, Reg, Vvvv, Rm]);
+		op!([0xf3, 0x0f, opcode], &format!("v{}ss", instr), [AVX, OpSize(S128), Reg, Vvvv, OpSize(S32), Rm]);
+		op!([0xf2, 0x0f, opcode], &format!("v{}sd", instr), [AVX, OpSize(S128), Reg, Vvvv, OpSize(S64), Rm]);
+	}
+
+	op!([0x0f, 0x51], "vsqrtps", [AVX, Reg, Rm]);
+	op!([0x66, 0x0f, 0x51], "vsqrtpd", [AVX, Reg, Rm]);
+	op!([0xf3, 0x0f, 0x51], "vsqrtss", [AVX, OpSize(S128), Reg, Vvvv, OpSize(S32), Rm]);
+	op!([0xf2, 0x0f, 0x51], "vsqrtsd", [AVX, OpSize(S128), Reg, Vvvv, OpSize(S64), Rm]);
+
+	for &(instr, opcode) in &[("and", 0x54), ("andn", 0x55), ("or", 0x56), ("xor", 0x57)] {
+		op!([0x0f, opcode], &format!("v{}ps", instr), [AVX, Reg, Vvvv, Rm]);
+		op!([0x66, 0x0f, opcode], &format!("v{}pd", instr), [AVX, Reg, Vvvv, Rm]);
+	}
+
+	op!([0x0f, 0x2e], "vucomiss", [AVX, Read, Reg, OpSize(S32), Rm]);
+	op!([0x66, 0x0f, 0x2e], "vucomisd", [AVX, Read, Reg, OpSize(S64), Rm]);
+
+	op!([0xf3, 0x0f, 0x2c], "vcvttss2si", [OpSize(SRexSize), Reg, AVX, OpSize(S32), Rm]);
+	op!([0xf2, 0x0f, 0x2c], "vcvttsd2si", [OpSize(SRexSize), Reg, AVX, OpSize(S64), Rm]);
+	op!([0xf3, 0x0f, 0x2a], "vcvtsi2ss", [AVX, OpSize(S128), Reg, Vvvv, OpSize(SRexSize), SSEOff, Rm]);
+	op!([0xf2, 0x0f, 0x2a], "vcvtsi2sd", [AVX, OpSize(S128), Reg, Vvvv, OpSize(SRexSize), SSEOff, Rm]);
+	op!([0xf3, 0x0f, 0x5a], "vcvtss2sd", [AVX, OpSize(S128), Reg, Vvvv, OpSize(S32), Rm]);
+	op!([0xf2, 0x0f, 0x5a], "vcvtsd2ss", [AVX, OpSize(S128), Reg, Vvvv, OpSize(S64), Rm]);
+
+	op!([0x0f, 0x50], "vmovmskps", [OpSize(S32), Reg, AVX, Rm]);
+	op!([0x66, 0x0f, 0x50], "vmovmskpd", [OpSize(S32), Reg, AVX, Rm]);
+	op!([0x66, 0x0f, 0xd7], "vpmovmskb", [OpSize(S32), Reg, AVX, Rm]);
+
+	for &(instr, opcode) in &[("pcmpgtb", 0x64), ("pcmpgtw", 0x65), ("pcmpgtd", 0x66),
+			("punpcklqdq", 0x6c), ("punpckhqdq", 0x6d),
+			("pcmpeqb", 0x74), ("pcmpeqw", 0x75), ("pcmpeqd", 0x76),
+			("paddq", 0xd4), ("pmullw", 0xd5), ("pminub", 0xda), ("pand", 0xdb), ("pmaxub", 0xde), ("pandn", 0xdf),
+			("por", 0xeb), ("pxor", 0xef),
+			("psubb", 0xf8), ("psubw", 0xf9), ("psubd", 0xfa), ("psubq", 0xfb),
+			("paddb", 0xfc), ("paddw", 0xfd), ("paddd", 0xfe)] {
+		op!([0x66, 0x0f, opcode], &format!("v{}", instr), [AVX, Reg, Vvvv, Rm]);
+	}
+
+	op!([0x66, 0x0f, 0x70], "vpshufd", [AVX, Reg, Rm, ImmSize(S8), Imm]);
+
+	op!([0x66, 0x0f, 0x38, 0x00], "vpshufb", [AVX, Reg, Vvvv, Rm]);
+	op!([0x66, 0x0f, 0x38, 0x17], "vptest", [AVX, Read, Reg, Rm]);
+	op!([0x66, 0x0f, 0x38, 0x18], "vbroadcastss", [AVX, Reg, OpSize(S32), Rm]);
+	op!([0x66, 0x0f, 0x38, 0x19], "vbroadcastsd", [AVX, Reg, OpSize(S64), Rm]);
+	op!([0x66, 0x0f, 0x38, 0x58], "vpbroadcastd", [AVX, Reg, OpSize(S32), Rm]);
+	op!([0x66, 0x0f, 0x38, 0x59], "vpbroadcastq", [AVX, Reg, OpSize(S64), Rm]);
+	op!([0x66, 0x0f, 0x38, 0x78], "vpbroadcastb", [AVX, Reg, OpSize(S8), Rm]);
+	op!([0x66, 0x0f, 0x38, 0x79], "vpbroadcastw", [AVX, Reg, OpSize(S16), Rm]);
+
+	op!([0x66, 0x0f, 0x3a, 0x00], "vpermq", [AVX, Reg, Rm, ImmSize(S8), Imm]);
+	op!([0x66, 0x0f, 0x3a, 0x06], "vperm2f128", [AVX, Reg, Vvvv, Rm, ImmSize(S8), Imm]);
+	op!([0x66, 0x0f, 0x3a, 0x18], "vinsertf128", [AVX, Reg, Vvvv, OpSize(S128), Rm, ImmSize(S8), Imm]);
+	op!([0x66, 0x0f, 0x3a, 0x19], "vextractf128", [AVX, OpSize(S128), Rm, OpSize(SVexSize), Reg, ImmSize(S8), Imm]);
+	op!([0x66, 0x0f, 0x3a, 0x38], "vinserti128", [AVX, Reg, Vvvv, OpSize(S128), Rm, ImmSize(S8), Imm]);
+	op!([0x66, 0x0f, 0x3a, 0x39], "vextracti128", [AVX, OpSize(S128), Rm, OpSize(SVexSize), Reg, ImmSize(S8), Imm]);
+	op!([0x66, 0x0f, 0x3a, 0x46], "vperm2i128", [AVX, Reg, Vvvv, Rm, ImmSize(S8), Imm]);
+}
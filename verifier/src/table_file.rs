@@ -0,0 +1,537 @@
+//! The effect table file written by `avery-verify gen-table`.
+//!
+//! All integers are little-endian. The file starts with a 28 byte header:
+//!
+//! ```text
+//! 0   magic           8 bytes, "AVRYEFCT"
+//! 8   version         u32, `VERSION`
+//! 12  generator hash  u64, `generator_hash` of the `table::list_insts` description the table was generated from
+//! 20  lists           u32, the number of effect lists
+//! 24  map size        u32, the size of the FST map in bytes
+//! ```
+//!
+//! It's followed by the effect lists, then the FST map from instruction bytes to the index of their effect list,
+//! and a trailing u64 FNV-1a checksum of everything before it.
+//!
+//! An effect list is a u8 count followed by the effects. Each effect is a tag byte and its operands:
+//!
+//! ```text
+//! 1  ClobReg     reg
+//! 2  CheckMem    mem
+//! 3  Move        reg, reg
+//! 4  WriteStack  mem
+//! 5  ReadStack   mem
+//! 6  Store       mem, reg
+//! 7  Load        reg, mem
+//! 8  Push        reg
+//! 9  Pop         reg
+//! 10 CheckAddr
+//! 11 Call        mem
+//! 12 Call32
+//! 13 Jmp32
+//! 14 Jmp8
+//! 15 Ud2
+//! 16 Imm64
+//! 17 Imm32
+//! 18 Imm16
+//! 19 Imm8
+//! ```
+//!
+//! Registers are a byte. A memory operand is a kind byte, 0 for RIP-relative and 1, 2 or 3 for a base register with
+//! no displacement, an 8-bit displacement or a 32-bit displacement, followed by the base register byte, which is 0 for RIP.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use byteorder::{ByteOrder, LittleEndian};
+use fst;
+use effect::{Access, DecodedOperand, Disp, Effect, Inst, InstFormat, Mem, Operand, Regs, RT, Size};
+use table;
+
+pub const MAGIC: &'static [u8; 8] = b"AVRYEFCT";
+
+/// Incremented whenever the layout or the effect encoding changes
+pub const VERSION: u32 = 1;
+
+const HEADER_SIZE: usize = 28;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TableError {
+	BadMagic,
+	UnsupportedVersion(u32),
+	/// The table was generated from a different instruction description
+	Stale {
+		expected: u64,
+		found: u64,
+	},
+	Truncated,
+	BadChecksum,
+	InvalidEffect(u8),
+	/// An instruction has more effects than fit in the count byte of an effect list
+	TooManyEffects(usize),
+	/// An instruction refers to an effect list which doesn't exist
+	InvalidList(u64),
+	Map(String),
+}
+
+impl fmt::Display for TableError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			TableError::BadMagic => write!(f, "not an effect table"),
+			TableError::UnsupportedVersion(v) => write!(f, "unsupported effect table version {}, expected {}", v, VERSION),
+			TableError::Stale { expected, found } => write!(f, "effect table was generated from a different instruction description (hash {:016x}, expected {:016x}), rerun avery-verify gen-table", found, expected),
+			TableError::Truncated => write!(f, "effect table is truncated"),
+			TableError::BadChecksum => write!(f, "effect table checksum mismatch"),
+			TableError::InvalidEffect(tag) => write!(f, "invalid effect tag {}", tag),
+			TableError::TooManyEffects(n) => write!(f, "an instruction has {} effects, at most 255 are supported", n),
+			TableError::InvalidList(i) => write!(f, "instruction refers to effect list {} which doesn't exist", i),
+			TableError::Map(ref e) => write!(f, "invalid instruction map: {}", e),
+		}
+	}
+}
+
+fn fnv1a(data: &[u8]) -> u64 {
+	data.iter().fold(0xcbf29ce484222325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+fn push_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+	push_u64(out, bytes.len() as u64);
+	out.extend_from_slice(bytes);
+}
+
+fn push_option(out: &mut Vec<u8>, v: Option<usize>) {
+	match v {
+		Some(v) => {
+			out.push(1);
+			push_u64(out, v as u64);
+		}
+		None => out.push(0),
+	}
+}
+
+fn push_size(out: &mut Vec<u8>, size: Size) {
+	out.push(match size {
+		Size::Lit1 => 0,
+		Size::S8 => 1,
+		Size::S16 => 2,
+		Size::S32 => 3,
+		Size::S64 => 4,
+		Size::S128 => 5,
+		Size::SRexSize => 6,
+		Size::SVexSize => 7,
+		Size::SImmSize => 8,
+		Size::SOpSize => 9,
+	});
+}
+
+fn push_regs(out: &mut Vec<u8>, regs: Regs) {
+	out.push(match regs {
+		Regs::GP => 0,
+		Regs::SSE => 1,
+	});
+}
+
+fn push_access(out: &mut Vec<u8>, access: Access) {
+	out.push(match access {
+		Access::Read => 0,
+		Access::Write => 1,
+	});
+}
+
+fn push_operand(out: &mut Vec<u8>, operand: &Operand) {
+	match *operand {
+		Operand::Imm(size) => {
+			out.push(0);
+			push_size(out, size);
+		}
+		Operand::FixImm(imm, size) => {
+			out.push(1);
+			push_u64(out, imm as u64);
+			push_size(out, size);
+		}
+		Operand::Disp(size) => {
+			out.push(2);
+			push_size(out, size);
+		}
+		Operand::FixReg(r, regs) => {
+			out.extend_from_slice(&[3, r as u8]);
+			push_regs(out, regs);
+		}
+		Operand::FixRegRex(r, regs) => {
+			out.extend_from_slice(&[4, r as u8]);
+			push_regs(out, regs);
+		}
+		Operand::Addr => out.push(5),
+		Operand::Rm(regs) => {
+			out.push(6);
+			push_regs(out, regs);
+		}
+		Operand::Reg(regs) => {
+			out.push(7);
+			push_regs(out, regs);
+		}
+		Operand::RmOpcode(op) => out.extend_from_slice(&[8, op as u8]),
+		Operand::Mem(r) => {
+			out.push(9);
+			push_option(out, r);
+		}
+		Operand::Vvvv(regs) => {
+			out.push(10);
+			push_regs(out, regs);
+		}
+	}
+}
+
+fn push_decoded_operand(out: &mut Vec<u8>, operand: &DecodedOperand) {
+	match *operand {
+		DecodedOperand::Direct(rt) => {
+			let (tag, r) = match rt {
+				RT::GP(r) => (0, r),
+				RT::SSE(r) => (1, r),
+				RT::CR(r) => (2, r),
+			};
+			out.extend_from_slice(&[0, tag, r as u8]);
+		}
+		DecodedOperand::Indirect(ref access) => {
+			out.push(1);
+			push_option(out, access.base);
+			push_option(out, access.index);
+			push_u64(out, access.scale as u64);
+			push_u64(out, access.offset as u64);
+			out.push(access.offset_wide as u8);
+		}
+		DecodedOperand::Imm(imm, size) => {
+			out.push(2);
+			push_u64(out, imm as u64);
+			push_size(out, size);
+		}
+	}
+}
+
+/// Serializes every field of an instruction description. Each field is written in declaration order.
+/// Registers are a byte and other integers a u64. Byte strings and lists are prefixed by their length and enums by a tag byte.
+fn push_inst(out: &mut Vec<u8>, op: &Inst) {
+	push_bytes(out, &op.prefix_bytes);
+	push_bytes(out, &op.bytes);
+	push_option(out, op.opcode);
+
+	push_u64(out, op.accesses.len() as u64);
+	for &(i, access) in &op.accesses {
+		push_u64(out, i as u64);
+		push_access(out, access);
+	}
+
+	push_u64(out, op.operands.len() as u64);
+	for &(ref operand, size, access) in &op.operands {
+		push_operand(out, operand);
+		push_size(out, size);
+		push_access(out, access);
+	}
+
+	push_u64(out, op.decoded_operands.len() as u64);
+	for &(ref operand, size) in &op.decoded_operands {
+		push_decoded_operand(out, operand);
+		push_size(out, size);
+	}
+
+	out.push(op.op_size_postfix as u8);
+	push_bytes(out, op.name.as_bytes());
+	out.push(op.no_mem as u8);
+	push_bytes(out, &op.prefix_whitelist);
+	push_bytes(out, op.desc.as_bytes());
+	push_size(out, op.operand_size);
+	push_u64(out, op.len as u64);
+	out.push(op.vex as u8);
+}
+
+/// Hashes an instruction description, so tables generated from another description are detected
+pub fn generator_hash(ops: &[Inst]) -> u64 {
+	let mut out = Vec::new();
+
+	push_u64(&mut out, ops.len() as u64);
+	for op in ops {
+		push_inst(&mut out, op);
+	}
+
+	fnv1a(&out)
+}
+
+/// The hash of the description `avery-verify gen-table` currently generates tables from
+pub fn current_hash() -> u64 {
+	let mut ops = Vec::new();
+	table::list_insts(&mut ops, true);
+	generator_hash(&ops)
+}
+
+fn push_u32(out: &mut Vec<u8>, v: u32) {
+	let mut buf = [0; 4];
+	LittleEndian::write_u32(&mut buf, v);
+	out.extend_from_slice(&buf);
+}
+
+fn push_u64(out: &mut Vec<u8>, v: u64) {
+	let mut buf = [0; 8];
+	LittleEndian::write_u64(&mut buf, v);
+	out.extend_from_slice(&buf);
+}
+
+fn push_mem(out: &mut Vec<u8>, mem: Mem) {
+	match mem {
+		Mem::Rip => out.extend_from_slice(&[0, 0]),
+		Mem::Mem(r, Disp::None) => out.extend_from_slice(&[1, r as u8]),
+		Mem::Mem(r, Disp::Imm8) => out.extend_from_slice(&[2, r as u8]),
+		Mem::Mem(r, Disp::Imm32) => out.extend_from_slice(&[3, r as u8]),
+	}
+}
+
+fn push_effects(out: &mut Vec<u8>, effects: &[Effect]) -> Result<(), TableError> {
+	let effects: Vec<_> = effects.iter().filter(|&&e| e != Effect::None).collect();
+
+	if effects.len() > u8::max_value() as usize {
+		return Err(TableError::TooManyEffects(effects.len()));
+	}
+
+	out.push(effects.len() as u8);
+
+	for &&effect in &effects {
+		match effect {
+			Effect::None => unreachable!(),
+			Effect::ClobReg(r) => out.extend_from_slice(&[1, r as u8]),
+			Effect::CheckMem(mem) => {
+				out.push(2);
+				push_mem(out, mem);
+			}
+			Effect::Move(a, b) => out.extend_from_slice(&[3, a as u8, b as u8]),
+			Effect::WriteStack(mem) => {
+				out.push(4);
+				push_mem(out, mem);
+			}
+			Effect::ReadStack(mem) => {
+				out.push(5);
+				push_mem(out, mem);
+			}
+			Effect::Store(mem, r) => {
+				out.push(6);
+				push_mem(out, mem);
+				out.push(r as u8);
+			}
+			Effect::Load(r, mem) => {
+				out.extend_from_slice(&[7, r as u8]);
+				push_mem(out, mem);
+			}
+			Effect::Push(r) => out.extend_from_slice(&[8, r as u8]),
+			Effect::Pop(r) => out.extend_from_slice(&[9, r as u8]),
+			Effect::CheckAddr => out.push(10),
+			Effect::Call(mem) => {
+				out.push(11);
+				push_mem(out, mem);
+			}
+			Effect::Call32 => out.push(12),
+			Effect::Jmp32 => out.push(13),
+			Effect::Jmp8 => out.push(14),
+			Effect::Ud2 => out.push(15),
+			Effect::Imm64 => out.push(16),
+			Effect::Imm32 => out.push(17),
+			Effect::Imm16 => out.push(18),
+			Effect::Imm8 => out.push(19),
+		}
+	}
+
+	Ok(())
+}
+
+/// Reads from the front of a byte slice
+struct Reader<'s>(&'s [u8]);
+
+impl<'s> Reader<'s> {
+	fn take(&mut self, n: usize) -> Result<&'s [u8], TableError> {
+		if self.0.len() < n {
+			return Err(TableError::Truncated);
+		}
+		let (first, rest) = self.0.split_at(n);
+		self.0 = rest;
+		Ok(first)
+	}
+
+	fn byte(&mut self) -> Result<u8, TableError> {
+		self.take(1).map(|b| b[0])
+	}
+
+	fn reg(&mut self) -> Result<usize, TableError> {
+		self.byte().map(|r| r as usize)
+	}
+
+	fn mem(&mut self) -> Result<Mem, TableError> {
+		let kind = self.byte()?;
+		let r = self.reg()?;
+		Ok(match kind {
+			0 => Mem::Rip,
+			1 => Mem::Mem(r, Disp::None),
+			2 => Mem::Mem(r, Disp::Imm8),
+			3 => Mem::Mem(r, Disp::Imm32),
+			_ => return Err(TableError::InvalidEffect(kind)),
+		})
+	}
+
+	fn effects(&mut self) -> Result<Vec<Effect>, TableError> {
+		let count = self.byte()?;
+
+		(0..count).map(|_| {
+			let tag = self.byte()?;
+			Ok(match tag {
+				1 => Effect::ClobReg(self.reg()?),
+				2 => Effect::CheckMem(self.mem()?),
+				3 => {
+					let a = self.reg()?;
+					Effect::Move(a, self.reg()?)
+				}
+				4 => Effect::WriteStack(self.mem()?),
+				5 => Effect::ReadStack(self.mem()?),
+				6 => {
+					let mem = self.mem()?;
+					Effect::Store(mem, self.reg()?)
+				}
+				7 => {
+					let r = self.reg()?;
+					Effect::Load(r, self.mem()?)
+				}
+				8 => Effect::Push(self.reg()?),
+				9 => Effect::Pop(self.reg()?),
+				10 => Effect::CheckAddr,
+				11 => Effect::Call(self.mem()?),
+				12 => Effect::Call32,
+				13 => Effect::Jmp32,
+				14 => Effect::Jmp8,
+				15 => Effect::Ud2,
+				16 => Effect::Imm64,
+				17 => Effect::Imm32,
+				18 => Effect::Imm16,
+				19 => Effect::Imm8,
+				_ => return Err(TableError::InvalidEffect(tag)),
+			})
+		}).collect()
+	}
+}
+
+/// Writes the effect table for `cases`, which must be sorted by their bytes, generated from the description with `hash`
+pub fn write(cases: &[(Vec<u8>, Vec<Effect>, InstFormat)], hash: u64) -> Result<Vec<u8>, TableError> {
+	// Identical effect lists are stored once, keyed by their encoding
+	let mut lists = BTreeMap::new();
+	let mut encoded = Vec::new();
+	let mut build = fst::MapBuilder::memory();
+
+	for case in cases {
+		let mut list = Vec::new();
+		push_effects(&mut list, &case.1)?;
+
+		let next = lists.len() as u64;
+		let index = *lists.entry(list.clone()).or_insert_with(|| {
+			encoded.extend_from_slice(&list);
+			next
+		});
+
+		build.insert(&case.0, index).map_err(|e| TableError::Map(e.to_string()))?;
+	}
+
+	let map = build.into_inner().map_err(|e| TableError::Map(e.to_string()))?;
+
+	let mut out = Vec::new();
+	out.extend_from_slice(MAGIC);
+	push_u32(&mut out, VERSION);
+	push_u64(&mut out, hash);
+	push_u32(&mut out, lists.len() as u32);
+	push_u32(&mut out, map.len() as u32);
+	out.extend_from_slice(&encoded);
+	out.extend_from_slice(&map);
+	let checksum = fnv1a(&out);
+	push_u64(&mut out, checksum);
+
+	Ok(out)
+}
+
+/// A validated effect table
+pub struct EffectTable {
+	pub hash: u64,
+	lists: Vec<Vec<Effect>>,
+	map: fst::Map,
+}
+
+impl EffectTable {
+	/// Loads and validates a table. It must have been generated from the description with `hash`,
+	/// and every instruction must refer to a valid effect list.
+	pub fn load(data: &[u8], hash: u64) -> Result<EffectTable, TableError> {
+		if !data.starts_with(MAGIC) {
+			return Err(TableError::BadMagic);
+		}
+
+		if data.len() < HEADER_SIZE + 8 {
+			return Err(TableError::Truncated);
+		}
+
+		let version = LittleEndian::read_u32(&data[8..12]);
+
+		if version != VERSION {
+			return Err(TableError::UnsupportedVersion(version));
+		}
+
+		let (body, checksum) = data.split_at(data.len() - 8);
+
+		if fnv1a(body) != LittleEndian::read_u64(checksum) {
+			return Err(TableError::BadChecksum);
+		}
+
+		let found = LittleEndian::read_u64(&body[12..20]);
+
+		if found != hash {
+			return Err(TableError::Stale {
+				expected: hash,
+				found: found,
+			});
+		}
+
+		let count = LittleEndian::read_u32(&body[20..24]);
+		let map_size = LittleEndian::read_u32(&body[24..28]) as usize;
+
+		let mut reader = Reader(&body[HEADER_SIZE..]);
+
+		let lists = (0..count).map(|_| reader.effects()).collect::<Result<Vec<_>, _>>()?;
+
+		if reader.0.len() != map_size {
+			return Err(TableError::Truncated);
+		}
+
+		let map = fst::Map::from_bytes(reader.0.to_vec()).map_err(|e| TableError::Map(e.to_string()))?;
+
+		{
+			use fst::Streamer;
+
+			let mut stream = map.stream();
+
+			while let Some((_, index)) = stream.next() {
+				if index >= lists.len() as u64 {
+					return Err(TableError::InvalidList(index));
+				}
+			}
+		}
+
+		Ok(EffectTable {
+			hash: hash,
+			lists: lists,
+			map: map,
+		})
+	}
+
+	/// The effects of the instruction with the opcode `bytes`, including its prefixes
+	pub fn get(&self, bytes: &[u8]) -> Option<&[Effect]> {
+		self.map.get(bytes).map(|index| &self.lists[index as usize][..])
+	}
+
+	/// The effects of the instruction at the start of `data`, using its longest opcode in the table
+	pub fn find(&self, data: &[u8]) -> Option<&[Effect]> {
+		(1..(data.len() + 1)).rev().filter_map(|len| self.get(&data[..len])).next()
+	}
+
+	/// The number of instructions in the table
+	pub fn len(&self) -> usize {
+		self.map.len()
+	}
+}
@@ -0,0 +1,35 @@
+extern crate verifier;
+
+use verifier::archive;
+
+const ARCHIVE: &'static [u8] = include_bytes!("elf/members.a");
+
+fn names(members: &[archive::Member]) -> Vec<String> {
+	members.iter().map(|m| m.name.to_string()).collect()
+}
+
+#[test]
+fn objects() {
+	assert!(archive::is_archive(ARCHIVE));
+	assert_eq!(names(&archive::members(ARCHIVE).unwrap()), ["calls.o", "notes.txt", "split.o"]);
+
+	let (objects, skipped) = archive::objects(ARCHIVE).unwrap();
+	assert_eq!(names(&objects), ["calls.o", "split.o"]);
+	assert_eq!(names(&skipped), ["notes.txt"]);
+	assert_eq!(objects[0].data, &include_bytes!("elf/calls.o")[..]);
+}
+
+#[test]
+fn truncated() {
+	// The last member ends past the end of the archive
+	assert_eq!(archive::members(&ARCHIVE[..(ARCHIVE.len() - 1)]).unwrap_err(), "truncated archive member");
+
+	// The archive ends in the header of the first member
+	assert_eq!(archive::members(&ARCHIVE[..(archive::MAGIC.len() + 30)]).unwrap_err(), "truncated archive member header");
+
+	// The largest size the header can hold, which overflows when added to the member's offset on 32-bit targets
+	let mut data = ARCHIVE.to_vec();
+	let size = archive::MAGIC.len() + 48;
+	data[size..(size + 10)].copy_from_slice(b"9999999999");
+	assert_eq!(archive::members(&data).unwrap_err(), "truncated archive member");
+}
@@ -0,0 +1,29 @@
+extern crate elfloader;
+extern crate verifier;
+
+use verifier::image::{self, Selection};
+use verifier::policy::Policy;
+use verifier::x86_decoder::DecoderError;
+
+#[test]
+fn direct_calls() {
+	let data = include_bytes!("elf/call_targets.o").to_vec();
+	let bin = elfloader::Image::new(&data).unwrap();
+	let layout = image::layout(&bin).unwrap();
+	let report = image::verify(&bin, &[], &Policy::default(), 1, &Selection::default()).unwrap();
+
+	let function = |name: &str| report.functions.iter().find(|f| f.name == name).unwrap();
+
+	assert!(function("start").passed());
+
+	let f = function("f").address;
+	let middle = function("middle").failure.clone().unwrap();
+	assert_eq!(middle.error, DecoderError::InvalidCallTarget);
+	assert_eq!(middle.target, Some(f + 2));
+
+	// .Lcode is 8 bytes into .data
+	let data = bin.sections.iter().position(|s| bin.section_name(s) == Ok(".data")).unwrap();
+	let unnamed = function("unnamed").failure.clone().unwrap();
+	assert_eq!(unnamed.error, DecoderError::InvalidCallTarget);
+	assert_eq!(unnamed.target, Some(layout.unwrap().addrs[data] + 8));
+}
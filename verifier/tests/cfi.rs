@@ -0,0 +1,132 @@
+extern crate verifier;
+
+use verifier::cfi::{self, CallTable};
+use verifier::x86_decoder::{self, Context, DecoderError};
+
+const BASE: u64 = 0x1000;
+const TABLE: u64 = 0x2000;
+
+/// A function at `BASE` which masks edi with `and`, adds the table at `table` and calls it,
+/// with the call table of `size` bytes at `TABLE`
+fn verify(and: &[u8], table: u64, size: u64) -> Result<(), DecoderError> {
+	let mut data = and.to_vec();
+	// lea rax, [rip + table]
+	let disp = table as i64 - (BASE as i64 + data.len() as i64 + 7);
+	data.extend_from_slice(&[0x48, 0x8D, 0x05, disp as u8, (disp >> 8) as u8, (disp >> 16) as u8, (disp >> 24) as u8]);
+	// add rdi, rax; call rdi; ret
+	data.extend_from_slice(&[0x48, 0x01, 0xC7, 0xFF, 0xD7, 0xC3]);
+
+	let ctx = Context {
+		calls: Some(CallTable {
+			start: TABLE,
+			size: size,
+		}),
+		..Context::default()
+	};
+
+	x86_decoder::decode(&data, BASE, &ctx).map(|_| ()).map_err(|failure| failure.error)
+}
+
+#[test]
+fn recognize() {
+	// and edi, 8
+	verify(&[0x83, 0xE7, 0x08], TABLE, 16).unwrap();
+	// and edi, 0
+	verify(&[0x83, 0xE7, 0x00], TABLE, 16).unwrap();
+	// and edi, 0x100
+	verify(&[0x81, 0xE7, 0x00, 0x01, 0x00, 0x00], TABLE, 0x108).unwrap();
+}
+
+#[test]
+fn invalid_mask() {
+	// and edi, 4 isn't aligned to a slot
+	assert_eq!(verify(&[0x83, 0xE7, 0x04], TABLE, 16), Err(DecoderError::InvalidIndirectCall));
+
+	// and edi, 8 selects a slot past the end of the table
+	assert_eq!(verify(&[0x83, 0xE7, 0x08], TABLE, 8), Err(DecoderError::InvalidIndirectCall));
+
+	// and edi, 0x18 reaches past the end of the table
+	assert_eq!(verify(&[0x83, 0xE7, 0x18], TABLE, 16), Err(DecoderError::InvalidIndirectCall));
+
+	// and edi, -8 doesn't clear the upper bits, so the call isn't masked
+	assert_eq!(verify(&[0x83, 0xE7, 0xF8], TABLE, 16), Err(DecoderError::UncheckedIndirectCall));
+
+	// and rdi, 8 doesn't match the 32-bit form
+	assert_eq!(verify(&[0x48, 0x83, 0xE7, 0x08], TABLE, 16), Err(DecoderError::UncheckedIndirectCall));
+}
+
+#[test]
+fn invalid_table() {
+	// The lea points into the table rather than at its start
+	assert_eq!(verify(&[0x83, 0xE7, 0x08], TABLE + 8, 16), Err(DecoderError::InvalidIndirectCall));
+
+	// The lea points somewhere else
+	assert_eq!(verify(&[0x83, 0xE7, 0x08], 0x3000, 16), Err(DecoderError::InvalidIndirectCall));
+}
+
+#[test]
+fn unmasked() {
+	let ctx = Context::default();
+	let error = |data: &[u8]| x86_decoder::decode(data, BASE, &ctx).unwrap_err().error;
+
+	// call rdi; ret
+	assert_eq!(error(&[0xFF, 0xD7, 0xC3]), DecoderError::UncheckedIndirectCall);
+	// call r15; ret
+	assert_eq!(error(&[0x41, 0xFF, 0xD7, 0xC3]), DecoderError::UncheckedIndirectCall);
+	// call qword ptr [rsp]; ret
+	assert_eq!(error(&[0xFF, 0x14, 0x24, 0xC3]), DecoderError::UncheckedIndirectCall);
+	// jmp rax
+	assert_eq!(error(&[0xFF, 0xE0]), DecoderError::UncheckedIndirectJump);
+}
+
+#[test]
+fn no_table() {
+	// and edi, 8; lea rax, [rip]; add rdi, rax; call rdi; ret
+	let data = [0x83, 0xE7, 0x08, 0x48, 0x8D, 0x05, 0x00, 0x00, 0x00, 0x00, 0x48, 0x01, 0xC7, 0xFF, 0xD7, 0xC3];
+	let error = x86_decoder::decode(&data, BASE, &Context::default()).unwrap_err().error;
+	assert_eq!(error, DecoderError::InvalidIndirectCall);
+}
+
+/// A slot jumping to `target` from the slot at `address`
+fn jump(address: u64, target: u64) -> Vec<u8> {
+	let relative = (target as i64 - (address as i64 + 5)) as i32;
+	vec![0xE9, relative as u8, (relative >> 8) as u8, (relative >> 16) as u8, (relative >> 24) as u8, 0xCC, 0xCC, 0xCC]
+}
+
+#[test]
+fn check_table() {
+	let entries = [0x1000, 0x1010];
+
+	let mut data = vec![0xCC; 8];
+	data.extend_from_slice(&jump(TABLE + 8, 0x1010));
+	data.extend_from_slice(&jump(TABLE + 16, 0x1000));
+	let table = cfi::check_table(TABLE, &data, &entries).unwrap();
+	assert_eq!((table.start, table.size), (TABLE, 24));
+
+	// Sizes which aren't whole slots
+	assert!(cfi::check_table(TABLE, &[], &entries).is_err());
+	assert!(cfi::check_table(TABLE, &data[..12], &entries).is_err());
+
+	// A jump to an address which isn't an entry point
+	assert!(cfi::check_table(TABLE, &jump(TABLE, 0x1004), &entries).is_err());
+}
+
+#[test]
+fn malformed_slot() {
+	let entries = [0x1000];
+
+	// call rel32 instead of jmp rel32
+	let mut slot = jump(TABLE, 0x1000);
+	slot[0] = 0xE8;
+	assert!(cfi::check_table(TABLE, &slot, &entries).is_err());
+
+	// Padding after the jump which isn't int3
+	let mut slot = jump(TABLE, 0x1000);
+	slot[7] = 0x90;
+	assert!(cfi::check_table(TABLE, &slot, &entries).is_err());
+
+	// A slot which only partly traps
+	let mut slot = vec![0xCC; 8];
+	slot[3] = 0x00;
+	assert!(cfi::check_table(TABLE, &slot, &entries).is_err());
+}
@@ -0,0 +1,177 @@
+extern crate verifier;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::process::{Command, Output};
+use verifier::{disasm, table_file};
+
+/// Runs the avery-verify binary, which cargo builds next to the test executables
+fn avery_verify(args: &[&str]) -> Output {
+	let mut path = env::current_exe().unwrap();
+	path.pop();
+	if path.ends_with("deps") {
+		path.pop();
+	}
+
+	let mut command = Command::new(path.join("avery-verify"));
+	command.current_dir(env!("CARGO_MANIFEST_DIR"));
+	command.args(args).output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+	String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+	String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+/// Checks that `lines` appear in `output` in order
+fn assert_lines(output: &str, lines: &[&str]) {
+	let mut rest = output.lines();
+
+	for line in lines {
+		assert!(rest.any(|l| l.trim_right() == *line), "missing `{}` in:\n{}", line, output);
+	}
+}
+
+#[test]
+fn listing() {
+	let output = avery_verify(&["disasm", "tests/elf/calls.o"]);
+	assert!(output.status.success());
+
+	// The indirect call sequence is a single line with the operations of the whole sequence
+	assert_lines(&stdout(&output), &[
+		"caller (0x1):",
+		".L1:",
+		"  00000001  83e708                   and edi, 8                               ; stack 0x0; rdi = ?, rax = ?; masked r15",
+		"  00000004  488d0506000000           lea rax, [rip + 6]",
+		"  00000010  c3                       ret                                      ; stack 0x0; masked r15",
+		"ok   caller",
+	]);
+}
+
+#[test]
+fn listing_failure() {
+	let output = avery_verify(&["disasm", "tests/elf/call_targets.o"]);
+
+	// Decoding stops at the call, so it only has its bytes
+	assert_lines(&stdout(&output), &[
+		"middle (0x9):",
+		"  00000009  e8f4ffffff",
+		"    ^ error: call target is not the start of a function or an allowed import, target 0x2",
+		"FAIL middle",
+	]);
+}
+
+#[test]
+fn diagnostic() {
+	let output = avery_verify(&["check", "tests/elf/call_targets.o"]);
+
+	assert_lines(&stdout(&output), &[
+		"FAIL middle (0x9)",
+		"error: call target is not the start of a function or an allowed import",
+		"  --> middle+0x0 at 0x9",
+		"   = target 0x2",
+		"  --> 00000009  e8f4ffffff               call 2",
+		"      0000000e  c3                       ret",
+	]);
+}
+
+#[test]
+fn exit_codes() {
+	// Every function passes
+	let output = avery_verify(&["check", "tests/elf/calls.o"]);
+	assert_eq!(output.status.code(), Some(0));
+	assert_lines(&stdout(&output), &[
+		"Checking tests/elf/calls.o",
+		"ok   f (0x0)",
+		"ok   caller (0x1)",
+		"2 function(s), 0 failed",
+	]);
+
+	// Functions are rejected
+	let output = avery_verify(&["check", "tests/elf/call_targets.o"]);
+	assert_eq!(output.status.code(), Some(1));
+	assert_lines(&stdout(&output), &["4 function(s), 2 failed"]);
+
+	// The input isn't an ELF file
+	let output = avery_verify(&["check", "tests/elf/notes.txt"]);
+	assert_eq!(output.status.code(), Some(2));
+	assert!(stderr(&output).starts_with("error: tests/elf/notes.txt: "));
+
+	let output = avery_verify(&["check", "tests/elf/missing.o"]);
+	assert_eq!(output.status.code(), Some(2));
+
+	let output = avery_verify(&["frobnicate"]);
+	assert_eq!(output.status.code(), Some(2));
+	assert_lines(&stderr(&output), &["error: unknown command `frobnicate`"]);
+}
+
+#[cfg(not(feature = "capstone"))]
+#[test]
+fn without_capstone() {
+	let output = avery_verify(&["gen-table"]);
+	assert_eq!(output.status.code(), Some(2));
+	assert_lines(&stderr(&output), &["error: `gen-table` needs avery-verify to be built with the capstone feature"]);
+}
+
+#[test]
+fn archive() {
+	let output = avery_verify(&["check", "tests/elf/members.a"]);
+	assert_eq!(output.status.code(), Some(1));
+
+	assert_lines(&stderr(&output), &["note: skipping tests/elf/members.a(notes.txt), which is not an ELF object"]);
+	assert_lines(&stdout(&output), &[
+		"Checking tests/elf/members.a(calls.o)",
+		"Checking tests/elf/members.a(split.o)",
+		"ok   tests/elf/members.a(calls.o)",
+		"FAIL tests/elf/members.a(split.o)",
+	]);
+}
+
+#[test]
+fn reduce_cold_parts() {
+	let mut dir = env::current_exe().unwrap();
+	dir.set_file_name("reduce-cold-parts");
+	let _ = fs::remove_dir_all(&dir);
+
+	let output = avery_verify(&["check", "--reduce", dir.to_str().unwrap(), "tests/elf/cold.o"]);
+	assert_eq!(output.status.code(), Some(1));
+
+	// k is rejected in k.cold, which is reduced on its own
+	assert_lines(&stdout(&output), &[
+		"reduced k.cold from 4 to 2 bytes: 8b07 (memory access without the sandbox segment override)",
+		"reduced m from 13 to 2 bytes: 8b07 (memory access without the sandbox segment override)",
+	]);
+
+	assert!(dir.join("cold.o-k.cold-18.s").exists());
+	assert!(dir.join("cold.o-m-b.s").exists());
+}
+
+#[test]
+fn effect_table() {
+	let cases = disasm::cases(true);
+
+	let mut path = env::current_exe().unwrap();
+	path.set_file_name("effect-table.table");
+	File::create(&path).unwrap().write_all(&table_file::write(&cases, table_file::current_hash()).unwrap()).unwrap();
+
+	let output = avery_verify(&["check", "--effects", path.to_str().unwrap(), "tests/elf/call_targets.o"]);
+	assert_eq!(output.status.code(), Some(1));
+	assert_lines(&stdout(&output), &[
+		"FAIL middle (0x9)",
+		"   = bytes e8f4ffffff",
+		"   = effects [Call32]",
+	]);
+
+	// A table generated from another instruction description is rejected before verifying anything
+	path.set_file_name("effect-table-stale.table");
+	File::create(&path).unwrap().write_all(&table_file::write(&cases, 1).unwrap()).unwrap();
+
+	let output = avery_verify(&["check", "--effects", path.to_str().unwrap(), "tests/elf/calls.o"]);
+	assert_eq!(output.status.code(), Some(2));
+	assert!(stdout(&output).is_empty());
+	assert!(stderr(&output).contains("effect table was generated from a different instruction description"));
+}
@@ -0,0 +1,65 @@
+extern crate verifier;
+
+use verifier::x86_decoder::{self, Context, DecoderError, Reg};
+use verifier::policy::{DataStack, Policy};
+
+/// sub r12, 16; or gs:[r12], 0
+const PROLOGUE: &'static [u8] = &[0x49, 0x83, 0xEC, 0x10, 0x65, 0x41, 0x83, 0x0C, 0x24, 0x00];
+
+/// add r12, 16; ret
+const EPILOGUE: &'static [u8] = &[0x49, 0x83, 0xC4, 0x10, 0xC3];
+
+/// mov rax, gs:[r12 + 8]
+const LOAD: &'static [u8] = &[0x65, 0x49, 0x8B, 0x44, 0x24, 0x08];
+
+fn verify(parts: &[&[u8]]) -> Result<(), DecoderError> {
+	let ctx = Context {
+		policy: Policy {
+			data_stack: Some(DataStack {
+				reg: Reg(12),
+				guard: 0x1000,
+			}),
+			..Policy::default()
+		},
+		..Context::default()
+	};
+
+	x86_decoder::decode(&parts.concat(), 0, &ctx).map(|_| ()).map_err(|failure| failure.error)
+}
+
+fn rejects(parts: &[&[u8]], expected: DecoderError) {
+	match verify(parts) {
+		Err(ref error) if format!("{:?}", error) == format!("{:?}", expected) => (),
+		result => panic!("expected {:?}, got {:?}", expected, result),
+	}
+}
+
+#[test]
+fn frame_accesses() {
+	verify(&[PROLOGUE, LOAD, EPILOGUE]).unwrap();
+	verify(&[&[0xC3]]).unwrap();
+
+	// mov rax, gs:[r12 + 16]
+	rejects(&[PROLOGUE, &[0x65, 0x49, 0x8B, 0x44, 0x24, 0x10], EPILOGUE], DecoderError::DataStackAccessOutOfFrame);
+	rejects(&[LOAD, &[0xC3]], DecoderError::UnprobedDataStackAccess);
+}
+
+#[test]
+fn prologue_placement() {
+	rejects(&[&[0x90], PROLOGUE, EPILOGUE], DecoderError::MisplacedDataStackPrologue);
+	// jmp to the prologue
+	rejects(&[PROLOGUE, &[0xEB, 0xF4]], DecoderError::MisplacedDataStackPrologue);
+	// sub r12, 0x2000; or gs:[r12], 0; add r12, 0x2000; ret
+	rejects(&[&[0x49, 0x81, 0xEC, 0x00, 0x20, 0x00, 0x00], &PROLOGUE[4..], &[0x49, 0x81, 0xC4, 0x00, 0x20, 0x00, 0x00, 0xC3]], DecoderError::DataFrameTooLarge);
+}
+
+#[test]
+fn register_adjustments() {
+	rejects(&[PROLOGUE, &[0xC3]], DecoderError::DataStackIsNotRestored);
+	// add r12, 8; ret
+	rejects(&[PROLOGUE, &[0x49, 0x83, 0xC4, 0x08, 0xC3]], DecoderError::DataStackIsNotRestored);
+	// sub r12, 16 without the probe
+	rejects(&[&PROLOGUE[..4], LOAD, EPILOGUE], DecoderError::DataStackClobbered);
+	// push r12; pop r12; ret
+	rejects(&[&[0x41, 0x54, 0x41, 0x5C, 0xC3]], DecoderError::DataStackClobbered);
+}
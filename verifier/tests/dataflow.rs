@@ -0,0 +1,94 @@
+extern crate verifier;
+
+use verifier::x86_decoder::{self, Context, DecoderError};
+
+/// push rax; and rax, r15; mov [rsp], rax
+const SPILL: &'static [u8] = &[0x50, 0x4C, 0x21, 0xF8, 0x48, 0x89, 0x04, 0x24];
+
+/// pop rax; ret
+const EPILOGUE: &'static [u8] = &[0x58, 0xC3];
+
+fn verify(parts: &[&[u8]]) -> Result<(), DecoderError> {
+	x86_decoder::decode(&parts.concat(), 0, &Context::default()).map(|_| ()).map_err(|failure| failure.error)
+}
+
+#[test]
+fn sized_reads() {
+	// mov rcx, [rsp]
+	verify(&[SPILL, &[0x48, 0x8B, 0x0C, 0x24], EPILOGUE]).unwrap();
+
+	// mov ecx, [rsp]
+	assert_eq!(verify(&[SPILL, &[0x8B, 0x0C, 0x24], EPILOGUE]), Err(DecoderError::PartialSlotAccess));
+	// and ecx, [rsp]
+	assert_eq!(verify(&[SPILL, &[0x23, 0x0C, 0x24], EPILOGUE]), Err(DecoderError::PartialSlotAccess));
+	// cmp [rsp], ecx
+	assert_eq!(verify(&[SPILL, &[0x39, 0x0C, 0x24], EPILOGUE]), Err(DecoderError::PartialSlotAccess));
+	// movzx ecx, byte ptr [rsp + 1]
+	assert_eq!(verify(&[SPILL, &[0x0F, 0xB6, 0x4C, 0x24, 0x01], EPILOGUE]), Err(DecoderError::PartialSlotAccess));
+
+	// The slot doesn't hold a masked value: push rax; mov ecx, [rsp]
+	verify(&[&[0x50, 0x8B, 0x0C, 0x24], EPILOGUE]).unwrap();
+}
+
+#[test]
+fn vex_reads() {
+	// vmovd xmm0, dword ptr [rsp]
+	assert_eq!(verify(&[SPILL, &[0xC5, 0xF9, 0x6E, 0x04, 0x24], EPILOGUE]), Err(DecoderError::PartialSlotAccess));
+	// vmovd xmm0, dword ptr [rsp + 4]
+	assert_eq!(verify(&[SPILL, &[0xC5, 0xF9, 0x6E, 0x44, 0x24, 0x04], EPILOGUE]), Err(DecoderError::PartialSlotAccess));
+	// vmovdqu xmm0, xmmword ptr [rsp - 4]
+	assert_eq!(verify(&[SPILL, &[0xC5, 0xFA, 0x6F, 0x44, 0x24, 0xFC], EPILOGUE]), Err(DecoderError::PartialSlotAccess));
+
+	// Reads covering whole slots
+	// vmovq xmm0, qword ptr [rsp]
+	verify(&[SPILL, &[0xC5, 0xFA, 0x7E, 0x04, 0x24], EPILOGUE]).unwrap();
+	// vmovdqu xmm0, xmmword ptr [rsp]
+	verify(&[SPILL, &[0xC5, 0xFA, 0x6F, 0x04, 0x24], EPILOGUE]).unwrap();
+	// vpaddd xmm0, xmm1, xmmword ptr [rsp]
+	verify(&[SPILL, &[0xC5, 0xF1, 0xFE, 0x04, 0x24], EPILOGUE]).unwrap();
+
+	// The slot doesn't hold a masked value: push rax; vmovd xmm0, dword ptr [rsp]
+	verify(&[&[0x50, 0xC5, 0xF9, 0x6E, 0x04, 0x24], EPILOGUE]).unwrap();
+}
+
+/// mov ecx, gs:[rax]; ret
+const ACCESS: &'static [u8] = &[0x65, 0x8B, 0x08, 0xC3];
+
+#[test]
+fn unmasked_base() {
+	assert_eq!(verify(&[ACCESS]), Err(DecoderError::UnmaskedAccess));
+
+	// and rax, r15
+	verify(&[&[0x4C, 0x21, 0xF8], ACCESS]).unwrap();
+
+	// and rcx, r15 masks the wrong register
+	assert_eq!(verify(&[&[0x4C, 0x21, 0xF9], ACCESS]), Err(DecoderError::UnmaskedAccess));
+
+	// and rax, r15; mov rax, rcx
+	assert_eq!(verify(&[&[0x4C, 0x21, 0xF8, 0x48, 0x89, 0xC8], ACCESS]), Err(DecoderError::UnmaskedAccess));
+}
+
+#[test]
+fn join() {
+	// test edi, edi; je 1f; and rax, r15; jmp 2f; 1: and rax, r15; 2:
+	verify(&[&[0x85, 0xFF, 0x74, 0x05, 0x4C, 0x21, 0xF8, 0xEB, 0x03, 0x4C, 0x21, 0xF8], ACCESS]).unwrap();
+
+	// test edi, edi; je 1f; and rax, r15; 1:
+	assert_eq!(verify(&[&[0x85, 0xFF, 0x74, 0x03, 0x4C, 0x21, 0xF8], ACCESS]), Err(DecoderError::UnmaskedAccess));
+}
+
+#[test]
+fn spills() {
+	// sub rsp, 8; and rax, r15; mov [rsp], rax
+	let spill: &[u8] = &[0x48, 0x83, 0xEC, 0x08, 0x4C, 0x21, 0xF8, 0x48, 0x89, 0x04, 0x24];
+	// mov rcx, [rsp]; mov edx, gs:[rcx]; add rsp, 8; ret
+	let reload: &[u8] = &[0x48, 0x8B, 0x0C, 0x24, 0x65, 0x8B, 0x11, 0x48, 0x83, 0xC4, 0x08, 0xC3];
+
+	verify(&[spill, reload]).unwrap();
+
+	// A call may overwrite the slot: call 0x1000 + .
+	assert_eq!(verify(&[spill, &[0xE8, 0x00, 0x10, 0x00, 0x00], reload]), Err(DecoderError::UnmaskedAccess));
+
+	// The slot is overwritten: mov [rsp], rdi
+	assert_eq!(verify(&[spill, &[0x48, 0x89, 0x3C, 0x24], reload]), Err(DecoderError::UnmaskedAccess));
+}
@@ -0,0 +1,167 @@
+// Compares the decoders with Capstone, which is only linked with the capstone feature
+#![cfg(feature = "capstone")]
+
+extern crate verifier;
+extern crate quickcheck;
+
+use quickcheck::{QuickCheck, TestResult};
+use verifier::{decoder, reduce, table};
+use verifier::disasm::{self, Cases};
+use verifier::x86_decoder::{self, Cursor, DecoderError, FunctionState, Inst, Operation};
+use verifier::policy::Policy;
+
+/// Filler for displacements and immediates, like gen_table uses
+const FILLER: u8 = 0x1D;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Outcome {
+	Length(usize),
+	/// Decoded, but rejected by the verifier's rules. The length isn't known.
+	Disallowed,
+	Invalid,
+}
+
+fn capstone(bytes: &[u8]) -> Outcome {
+	match decoder::capstone_simple(bytes, 0) {
+		Some((_, len)) => Outcome::Length(len),
+		None => Outcome::Invalid,
+	}
+}
+
+type Decoded = Result<(Inst, usize, Vec<Operation>), DecoderError>;
+
+/// The decoder generated by gen_decoder, with the generated `match` or the table driven opcode decoder
+fn generated(bytes: &[u8], table: bool) -> Decoded {
+	let mut c = Cursor {
+		data: bytes,
+		offset: 0,
+	};
+
+	let policy = Policy::default();
+	let mut state = FunctionState::new(&policy);
+
+	x86_decoder::inst_with_decoder(&mut c, &mut state, &policy, table).map(|(inst, len, _)| (inst, len, state.ops))
+}
+
+fn outcome(decoded: &Decoded) -> Outcome {
+	match *decoded {
+		Ok((_, len, _)) => Outcome::Length(len),
+		Err(DecoderError::UnknownInstruction) |
+		Err(DecoderError::InvalidPrefixes) |
+		Err(DecoderError::InstructionTooLong) |
+		Err(DecoderError::OutofBounds) => Outcome::Invalid,
+		Err(_) => Outcome::Disallowed,
+	}
+}
+
+/// The decoder matching byte strings against the cases from `disasm::gen_all`
+fn effect_table(bytes: &[u8], cases: &Cases) -> Outcome {
+	match cases.iter().find(|case| bytes.starts_with(&case.0[..])) {
+		Some(case) => {
+			let mut c = decoder::Cursor {
+				data: bytes,
+				offset: case.2.bytes.len(),
+			};
+			Outcome::Length(disasm::parse(&mut c, 0, &case.2).len)
+		}
+		None => Outcome::Invalid,
+	}
+}
+
+/// Checks that the decoders agree on `bytes`, which must be long enough for any instruction.
+/// Both opcode decoders of the generated decoder must give the same instruction, operations and errors.
+fn agree(bytes: &[u8], cases: &Cases) -> Result<(), String> {
+	let decoded = generated(bytes, false);
+	let table = generated(bytes, true);
+
+	if decoded != table {
+		return Err(format!("generated: {:?}, table driven: {:?}", decoded, table));
+	}
+
+	let cs = capstone(bytes);
+	let new = outcome(&decoded);
+
+	// The effect table has no VEX encoded instructions
+	let vex = bytes.iter().skip_while(|&&b| b == table::P_SEG_GS).next().map(|&b| b == 0xC4 || b == 0xC5).unwrap_or(false);
+	let old = if vex { None } else { Some(effect_table(bytes, cases)) };
+
+	let ok = match (new, cs) {
+		(Outcome::Length(n), Outcome::Length(m)) => n == m && old.map(|old| old == new).unwrap_or(true),
+		(Outcome::Length(_), _) => false,
+		(Outcome::Disallowed, cs) => cs != Outcome::Invalid,
+		(Outcome::Invalid, _) => old.map(|old| old == Outcome::Invalid).unwrap_or(true),
+	};
+
+	if ok {
+		Ok(())
+	} else {
+		Err(format!("generated: {:?}, effect table: {:?}, capstone: {:?}", new, old, cs))
+	}
+}
+
+/// Pads `bytes` so no decoder runs out of input
+fn padded(bytes: &[u8]) -> Vec<u8> {
+	let mut xs = bytes.to_vec();
+	while xs.len() < 16 {
+		xs.push(FILLER);
+	}
+	xs
+}
+
+fn reproducer(bytes: &[u8], cases: &Cases) -> String {
+	let fails = |bytes: &[u8]| agree(&padded(bytes), cases).is_err();
+	let mut bytes = reduce::minimize(bytes.to_vec(), &fails);
+
+	// Clear bytes where the input keeps failing, so the remaining immediates and displacements stand out
+	for i in 0..bytes.len() {
+		let mut simpler = bytes.clone();
+		simpler[i] = 0;
+		if bytes[i] != 0 && fails(&simpler) {
+			bytes = simpler;
+		}
+	}
+
+	let error = agree(&padded(&bytes), cases).err().unwrap();
+	format!("{} ({})", table::bytes(&bytes), error)
+}
+
+#[test]
+fn generated_cases() {
+	let cases = disasm::cases(false);
+
+	let failures: Vec<_> = cases.iter().map(|case| {
+		let mut bytes = case.0.clone();
+		for e in &case.1 {
+			for _ in 0..e.trailing_bytes() {
+				bytes.push(FILLER);
+			}
+		}
+		bytes
+	}).filter(|bytes| agree(&padded(bytes), &cases).is_err()).collect();
+
+	for bytes in failures.iter().take(20) {
+		println!("mismatch on {}", reproducer(bytes, &cases));
+	}
+
+	assert!(failures.is_empty(), "{} of {} cases mismatched", failures.len(), cases.len());
+}
+
+#[test]
+fn random_bytes() {
+	fn prop(bytes: Vec<u8>) -> TestResult {
+		thread_local!(static CASES: Cases = disasm::cases(false));
+
+		if bytes.is_empty() {
+			return TestResult::discard();
+		}
+
+		CASES.with(|cases| {
+			match agree(&padded(&bytes), cases) {
+				Ok(()) => TestResult::passed(),
+				Err(_) => TestResult::error(&format!("mismatch on {}", reproducer(&bytes, cases))[..]),
+			}
+		})
+	}
+
+	QuickCheck::new().tests(10000).quickcheck(prop as fn(Vec<u8>) -> TestResult);
+}
@@ -0,0 +1,38 @@
+# Direct calls to the start of a function, the middle of a function and an address without a symbol
+	.intel_syntax noprefix
+	.text
+
+	.globl f
+	.type f, @function
+f:
+	xor eax, eax
+	ret
+	.size f, . - f
+
+	.globl start
+	.type start, @function
+start:
+	call f
+	ret
+	.size start, . - start
+
+	# The ret of f
+	.globl middle
+	.type middle, @function
+middle:
+	call f + 2
+	ret
+	.size middle, . - middle
+
+	.globl unnamed
+	.type unnamed, @function
+unnamed:
+	call .Lcode
+	ret
+	.size unnamed, . - unnamed
+
+	# Code in a data section which only a local label refers to
+	.data
+	.quad 0
+.Lcode:
+	ret
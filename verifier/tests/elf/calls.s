@@ -0,0 +1,27 @@
+# An indirect call through the call table
+	.intel_syntax noprefix
+	.text
+
+	.globl f
+	.type f, @function
+f:
+	ret
+	.size f, . - f
+
+	.globl caller
+	.type caller, @function
+caller:
+	and edi, 8
+	lea rax, [rip + table]
+	add rdi, rax
+	call rdi
+	ret
+	.size caller, . - caller
+
+	# A slot trapping and a slot jumping to f
+	.section .avery.calls, "ax", @progbits
+table:
+	.byte 0xCC, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC
+	.byte 0xE9
+	.long f - . - 4
+	.byte 0xCC, 0xCC, 0xCC
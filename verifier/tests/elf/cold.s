@@ -0,0 +1,39 @@
+# Functions with cold parts which are rejected in the cold part and in the function itself
+	.intel_syntax noprefix
+	.text
+
+	.globl k
+	.type k, @function
+k:
+	push rbx
+	test eax, eax
+	jz k.cold
+	pop rbx
+	ret
+	.size k, . - k
+
+	.globl m
+	.type m, @function
+m:
+	push rbx
+	mov eax, dword ptr [rdi]
+	test eax, eax
+	jz m.cold
+	pop rbx
+	ret
+	.size m, . - m
+
+	.section .text.unlikely, "ax", @progbits
+
+	.type k.cold, @function
+k.cold:
+	mov eax, dword ptr [rdi]
+	pop rbx
+	ret
+	.size k.cold, . - k.cold
+
+	.type m.cold, @function
+m.cold:
+	pop rbx
+	ret
+	.size m.cold, . - m.cold
@@ -0,0 +1,28 @@
+# Functions reading and writing undefined data symbols and calling an undefined function
+	.intel_syntax noprefix
+	.text
+
+	.globl f
+	.type f, @function
+f:
+	mov eax, dword ptr [rip + counter]
+	mov eax, dword ptr [rip + config + 8]
+	call external
+	ret
+	.size f, . - f
+
+	# Undefined symbols may be read-only
+	.globl h
+	.type h, @function
+h:
+	mov dword ptr [rip + counter], eax
+	ret
+	.size h, . - h
+
+	# Past the region of the last undefined symbol
+	.globl g
+	.type g, @function
+g:
+	mov eax, dword ptr [rip + external + 0x10000]
+	ret
+	.size g, . - g
@@ -0,0 +1,24 @@
+# A function calling an undefined symbol, a data object and a function symbol in a data section
+	.intel_syntax noprefix
+	.text
+
+	.globl f
+	.type f, @function
+f:
+	call external
+	ret
+	.size f, . - f
+
+	.data
+
+	.globl table
+	.type table, @object
+table:
+	.quad 0
+	.size table, . - table
+
+	.globl code_in_data
+	.type code_in_data, @function
+code_in_data:
+	ret
+	.size code_in_data, . - code_in_data
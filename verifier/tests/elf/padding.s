@@ -0,0 +1,32 @@
+# Functions separated by valid and invalid padding
+	.intel_syntax noprefix
+	.text
+
+	.globl first
+	.type first, @function
+first:
+	ret
+	.size first, . - first
+
+	# Valid padding
+	int3
+	nop
+	.byte 0x66, 0x90
+	.byte 0x0F, 0x1F, 0x44, 0x00, 0x00
+	.byte 0x66, 0x2E, 0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00
+
+	.globl second
+	.type second, @function
+second:
+	ret
+	.size second, . - second
+
+	# Code outside of any function
+	mov rax, [rax]
+	ret
+
+	.globl third
+	.type third, @function
+third:
+	ret
+	.size third, . - third
@@ -0,0 +1,17 @@
+# Relocations applied to .text from two relocation sections. The test points .rela.data at .text.
+	.intel_syntax noprefix
+	.text
+
+	.globl f
+	.type f, @function
+f:
+	mov rax, qword ptr [rip + x]
+	mov rcx, qword ptr [rip]
+	ret
+	.size f, . - f
+
+	# The relocation at offset 10 applies to the displacement of the second mov
+	.data
+	.skip 10
+	.long y - . - 4
+	.skip 1
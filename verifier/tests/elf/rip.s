@@ -0,0 +1,58 @@
+# RIP-relative accesses to the sections of an object
+	.intel_syntax noprefix
+	.text
+
+	.globl store_data
+	.type store_data, @function
+store_data:
+	mov dword ptr [rip + data + 4], eax
+	ret
+	.size store_data, . - store_data
+
+	.globl store_rodata
+	.type store_rodata, @function
+store_rodata:
+	mov dword ptr [rip + rodata], eax
+	ret
+	.size store_rodata, . - store_rodata
+
+	.globl store_text
+	.type store_text, @function
+store_text:
+	mov byte ptr [rip + store_data], al
+	ret
+	.size store_text, . - store_text
+
+	# The last 4 bytes of .rodata
+	.globl load_end
+	.type load_end, @function
+load_end:
+	mov eax, dword ptr [rip + rodata + 4]
+	ret
+	.size load_end, . - load_end
+
+	# 8 bytes past the end of .rodata, the last section
+	.globl load_past_end
+	.type load_past_end, @function
+load_past_end:
+	mov rax, qword ptr [rip + rodata + 4]
+	ret
+	.size load_past_end, . - load_past_end
+
+	# 8 bytes straddling the end of .data and the start of .rodata
+	.globl store_past_end
+	.type store_past_end, @function
+store_past_end:
+	mov qword ptr [rip + data + 4], rax
+	ret
+	.size store_past_end, . - store_past_end
+
+	.section .rodata
+	.balign 8
+rodata:
+	.quad 1
+
+	.data
+	.balign 8
+data:
+	.quad 2
@@ -0,0 +1,15 @@
+# Code and read-only data, linked into executables with the segments_*.ld scripts
+	.intel_syntax noprefix
+	.text
+
+	.globl f
+	.type f, @function
+f:
+	ret
+	.size f, . - f
+
+	int3
+	nop
+
+	.section .rodata, "a", @progbits
+	.quad 0x1122334455667788
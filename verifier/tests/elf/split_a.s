@@ -0,0 +1,56 @@
+# Cold parts, labels and padding, linked with split_b.s into split.o
+	.intel_syntax noprefix
+	.text
+
+	.globl f
+	.type f, @function
+f:
+	push rbx
+	test eax, eax
+	jz f.cold
+	# A label inside f, which isn't a function of its own
+	.type f.inner, @function
+f.inner:
+	pop rbx
+	ret
+	.size f, . - f
+
+	# Valid padding
+	.byte 0x0F, 0x1F, 0x80, 0x00, 0x00, 0x00, 0x00
+	int3
+
+	# A symbol without a size, which extends to h
+	.type z, @function
+z:
+	ret
+
+	.type h, @function
+h:
+	push rbx
+	jmp h.cold
+	.size h, . - h
+
+	# Invalid padding, int3 doesn't take prefixes
+	.byte 0x66, 0xCC
+
+	.section .text.unlikely, "ax", @progbits
+
+	.type f.cold, @function
+f.cold:
+	pop rbx
+	ret
+	.size f.cold, . - f.cold
+
+	# g doesn't exist
+	.globl g.cold
+	.type g.cold, @function
+g.cold:
+	ret
+	.size g.cold, . - g.cold
+
+	# There are two functions named h
+	.type h.cold, @function
+h.cold:
+	pop rbx
+	ret
+	.size h.cold, . - h.cold
@@ -0,0 +1,8 @@
+# A second local function named h, linked with split_a.s into split.o
+	.intel_syntax noprefix
+	.text
+
+	.type h, @function
+h:
+	ret
+	.size h, . - h
@@ -0,0 +1,53 @@
+# Switches with jump tables in read-only and in writable data
+	.intel_syntax noprefix
+	.text
+
+	.globl f
+	.type f, @function
+f:
+	cmp rdi, 2
+	ja .Lf_default
+	lea rax, [rip + .Lf_table]
+	movsxd rcx, dword ptr [rax + 4 * rdi]
+	add rcx, rax
+	jmp rcx
+.Lf_0:
+	mov eax, 1
+	ret
+.Lf_1:
+	mov eax, 2
+	ret
+.Lf_default:
+	xor eax, eax
+	ret
+	.size f, . - f
+
+	.globl g
+	.type g, @function
+g:
+	cmp rdi, 1
+	ja .Lg_default
+	lea rax, [rip + .Lg_table]
+	movsxd rcx, dword ptr [rax + 4 * rdi]
+	add rcx, rax
+	jmp rcx
+.Lg_0:
+	mov eax, 1
+	ret
+.Lg_default:
+	xor eax, eax
+	ret
+	.size g, . - g
+
+	.section .rodata, "a", @progbits
+	.p2align 2
+.Lf_table:
+	.long .Lf_0 - .Lf_table
+	.long .Lf_1 - .Lf_table
+	.long .Lf_default - .Lf_table
+
+	.data
+	.p2align 2
+.Lg_table:
+	.long .Lg_0 - .Lg_table
+	.long .Lg_default - .Lg_table
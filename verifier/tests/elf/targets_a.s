@@ -0,0 +1,16 @@
+# A local function `f` with a jump target table. Linked with targets_b.s into targets.o by
+#   ld -r targets_a.o targets_b.o -o targets.o
+	.intel_syntax noprefix
+	.section .text.a, "ax"
+
+	.type f, @function
+f:
+	ret
+	.size f, . - f
+
+	.section .avery.targets, ""
+	.long 7
+	.ascii ".text.a"
+	.quad 0
+	.long 1
+	.long 0, 0
@@ -0,0 +1,19 @@
+# Another local function `f` with a jump target at the nop, see targets_a.s
+	.intel_syntax noprefix
+	.section .text.b, "ax"
+
+	.type f, @function
+f:
+	push rbx
+	nop
+	pop rbx
+	ret
+	.size f, . - f
+
+	.section .avery.targets, ""
+	.long 7
+	.ascii ".text.b"
+	.quad 0
+	.long 2
+	.long 0, 0
+	.long 1, 8
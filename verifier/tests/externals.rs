@@ -0,0 +1,75 @@
+extern crate byteorder;
+extern crate elfloader;
+extern crate verifier;
+
+use byteorder::{ByteOrder, LittleEndian};
+use verifier::relocate;
+use verifier::image::{self, Selection};
+use verifier::policy::Policy;
+use verifier::x86_decoder::DecoderError;
+
+#[test]
+fn data_references() {
+	let data = include_bytes!("elf/externals.o").to_vec();
+	let bin = elfloader::Image::new(&data).unwrap();
+	let report = image::verify(&bin, &[], &Policy::default(), 1, &Selection::default()).unwrap();
+
+	assert_eq!(report.externals, vec!["counter", "config", "external"]);
+
+	let f = report.functions.iter().find(|f| f.name == "f").unwrap();
+	assert!(f.passed(), "{:?}", f.failure);
+
+	let g = report.functions.iter().find(|f| f.name == "g").unwrap();
+	assert_eq!(g.failure.as_ref().map(|f| f.error.clone()), Some(DecoderError::RipAccessOutOfBounds));
+
+	let h = report.functions.iter().find(|f| f.name == "h").unwrap();
+	assert_eq!(h.failure.as_ref().map(|f| f.error.clone()), Some(DecoderError::RipWriteToReadOnly));
+}
+
+/// `data` with the `info` field of the section header at `index` set to `info`
+fn set_info(data: &[u8], index: usize, info: u32) -> Vec<u8> {
+	let mut data = data.to_vec();
+	let headers = LittleEndian::read_u64(&data[0x28..0x30]) as usize;
+	let offset = headers + index * 64 + 44;
+	LittleEndian::write_u32(&mut data[offset..(offset + 4)], info);
+	data
+}
+
+fn section(bin: &elfloader::Image, name: &str) -> usize {
+	bin.sections.iter().position(|s| bin.section_name(s) == Ok(name)).unwrap()
+}
+
+#[test]
+fn relocation_sections() {
+	let data = include_bytes!("elf/relocs.o").to_vec();
+	let (text, rela) = {
+		let bin = elfloader::Image::new(&data).unwrap();
+		(section(&bin, ".text"), section(&bin, ".rela.data"))
+	};
+
+	// Both relocation sections apply to .text
+	let data = set_info(&data, rela, text as u32);
+	let bin = elfloader::Image::new(&data).unwrap();
+	let layout = relocate::layout(&bin).unwrap();
+
+	let x = layout.externals.iter().find(|e| e.name == "x").unwrap().address;
+	let y = layout.externals.iter().find(|e| e.name == "y").unwrap().address;
+
+	let code = &layout.data[&text];
+	assert_eq!(LittleEndian::read_i32(&code[3..7]) as i64, x as i64 - 7);
+	assert_eq!(LittleEndian::read_i32(&code[10..14]) as i64, y as i64 - 14);
+}
+
+#[test]
+fn relocation_offset() {
+	let mut data = include_bytes!("elf/relocs.o").to_vec();
+	let offset = {
+		let bin = elfloader::Image::new(&data).unwrap();
+		bin.sections[section(&bin, ".rela.text")].offset as usize
+	};
+
+	// An offset which overflows when the size is added
+	LittleEndian::write_u64(&mut data[offset..(offset + 8)], !0 - 1);
+	let bin = elfloader::Image::new(&data).unwrap();
+	assert_eq!(relocate::layout(&bin).unwrap_err(), "relocation offset out of bounds");
+}
@@ -0,0 +1,27 @@
+extern crate elfloader;
+extern crate verifier;
+
+use verifier::image::{self, Selection};
+use verifier::policy::Policy;
+
+#[test]
+fn imports() {
+	let data = include_bytes!("elf/imports.o").to_vec();
+	let bin = elfloader::Image::new(&data).unwrap();
+	let layout = image::layout(&bin).unwrap();
+
+	let f = image::functions(&bin, layout.as_ref())[0].address;
+	let external = layout.as_ref().unwrap().externals[0].address;
+	assert_eq!(layout.as_ref().unwrap().externals[0].name, "external");
+
+	assert_eq!(image::entries(&bin, layout.as_ref(), &[]), Ok(vec![f, external]));
+	assert_eq!(image::entries(&bin, layout.as_ref(), &["f", "external", "missing"]), Ok(vec![f, external]));
+
+	// Data objects and function symbols outside of executable sections
+	assert!(image::entries(&bin, layout.as_ref(), &["table"]).is_err());
+	assert!(image::entries(&bin, layout.as_ref(), &["code_in_data"]).is_err());
+
+	let report = image::verify(&bin, &["external"], &Policy::default(), 1, &Selection::default()).unwrap();
+	assert!(report.passed());
+	assert!(image::verify(&bin, &["table"], &Policy::default(), 1, &Selection::default()).is_err());
+}
@@ -0,0 +1,119 @@
+extern crate elfloader;
+extern crate verifier;
+
+use verifier::image::{self, Selection};
+use verifier::policy::Policy;
+use verifier::x86_decoder::{self, Context, DecoderError};
+
+const BASE: u64 = 0x1000;
+const TABLE: u64 = 0x2000;
+
+/// cmp rdi, 2
+const CMP: &'static [u8] = &[0x48, 0x83, 0xFF, 0x02];
+
+/// movsxd rcx, dword ptr [rax + 4 * rdi]
+const LOAD: &'static [u8] = &[0x48, 0x63, 0x0C, 0xB8];
+
+/// How a table entry targets the function
+#[derive(Copy, Clone)]
+enum Entry {
+	Case(usize),
+	/// The second byte of a case
+	Inside(usize),
+	Offset(i64),
+}
+
+/// A function at `BASE` which jumps through a table at `TABLE` with `entries`, using `cmp` and an optional `mov`
+/// to bound the index in `load`. There are three cases which undo `prologue` and return, the last one is the default.
+fn verify(prologue: &[u8], cmp: &[u8], mov: &[u8], load: &[u8], entries: &[Entry]) -> Result<(), DecoderError> {
+	// mov eax, 0xC3C3C3C3; ret, or pop rbx; ret
+	let epilogue: &[u8] = if prologue.is_empty() { &[0xB8, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3] } else { &[0x5B, 0xC3] };
+
+	let lea = prologue.len() + cmp.len() + 2 + mov.len();
+	let cases = lea + 7 + load.len() + 3 + 2;
+	let case = |i: usize| cases + i * epilogue.len();
+
+	let mut data = prologue.to_vec();
+	data.extend_from_slice(cmp);
+	// ja default
+	data.extend_from_slice(&[0x77, (case(2) - (lea - mov.len())) as u8]);
+	data.extend_from_slice(mov);
+	// lea rax, [rip + table]
+	let disp = TABLE as i64 - (BASE as i64 + lea as i64 + 7);
+	data.extend_from_slice(&[0x48, 0x8D, 0x05, disp as u8, (disp >> 8) as u8, (disp >> 16) as u8, (disp >> 24) as u8]);
+	data.extend_from_slice(load);
+	// add rcx, rax; jmp rcx
+	data.extend_from_slice(&[0x48, 0x01, 0xC1, 0xFF, 0xE1]);
+	for _ in 0..3 {
+		data.extend_from_slice(epilogue);
+	}
+
+	let mut table = Vec::new();
+	for &entry in entries {
+		let offset = match entry {
+			Entry::Case(i) => case(i) as i64,
+			Entry::Inside(i) => case(i) as i64 + 1,
+			Entry::Offset(offset) => offset,
+		};
+		let relative = (BASE as i64 + offset - TABLE as i64) as i32;
+		table.extend_from_slice(&[relative as u8, (relative >> 8) as u8, (relative >> 16) as u8, (relative >> 24) as u8]);
+	}
+
+	let ctx = Context {
+		rodata: vec![(TABLE, table)],
+		..Context::default()
+	};
+
+	x86_decoder::decode(&data, BASE, &ctx).map(|_| ()).map_err(|failure| failure.error)
+}
+
+const CASES: &'static [Entry] = &[Entry::Case(0), Entry::Case(1), Entry::Case(2)];
+
+#[test]
+fn accepted() {
+	verify(&[], CMP, &[], LOAD, CASES).unwrap();
+
+	// A 32-bit compare with the index zero extended by mov edi, edi
+	verify(&[], &[0x83, 0xFF, 0x02], &[0x89, 0xFF], LOAD, CASES).unwrap();
+
+	// r12 as the index: cmp r12, 2; movsxd rcx, dword ptr [rax + 4 * r12]
+	verify(&[], &[0x49, 0x83, 0xFC, 0x02], &[], &[0x4A, 0x63, 0x0C, 0xA0], CASES).unwrap();
+
+	// With a push before the dispatch
+	verify(&[0x53], CMP, &[], LOAD, CASES).unwrap();
+}
+
+#[test]
+fn rejected() {
+	// A 32-bit compare leaves the upper bits of the index unknown, so this is an unchecked indirect jump
+	assert!(verify(&[], &[0x83, 0xFF, 0x02], &[], LOAD, CASES).is_err());
+
+	// rsp can't be an index without REX.X, so there's no index
+	assert!(verify(&[], &[0x48, 0x83, 0xFC, 0x02], &[], &[0x48, 0x63, 0x0C, 0xA0], CASES).is_err());
+
+	// The bound covers an entry past the end of the table
+	assert_eq!(verify(&[], CMP, &[], LOAD, &CASES[..2]), Err(DecoderError::InvalidJumpTable));
+
+	// An entry in the middle of a mov, where the immediate decodes as a ret
+	assert_eq!(verify(&[], CMP, &[], LOAD, &[Entry::Case(0), Entry::Case(1), Entry::Inside(1)]), Err(DecoderError::MisalignedJumpTarget));
+
+	// An entry outside of the function
+	assert_eq!(verify(&[], CMP, &[], LOAD, &[Entry::Case(0), Entry::Offset(0x100), Entry::Case(2)]), Err(DecoderError::JumpOutsideOfFunction));
+
+	// An entry at the start of the function, where the push hasn't happened yet
+	assert_eq!(verify(&[0x53], CMP, &[], LOAD, &[Entry::Case(0), Entry::Offset(0), Entry::Case(2)]), Err(DecoderError::UnbalancedStackJump));
+}
+
+#[test]
+fn writable_table() {
+	// f has its table in .rodata and g in .data
+	let data = include_bytes!("elf/switch.o").to_vec();
+	let bin = elfloader::Image::new(&data).unwrap();
+	let report = image::verify(&bin, &[], &Policy::default(), 1, &Selection::default()).unwrap();
+
+	let f = report.functions.iter().find(|f| f.name == "f").unwrap();
+	assert!(f.passed());
+
+	let g = report.functions.iter().find(|f| f.name == "g").unwrap();
+	assert_eq!(g.failure.as_ref().unwrap().error, DecoderError::InvalidJumpTable);
+}
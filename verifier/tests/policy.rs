@@ -0,0 +1,78 @@
+extern crate verifier;
+
+use verifier::policy::{self, DataStack, Policy, LOCK, OP_SIZE, REP};
+use verifier::x86_decoder::{Reg, P_SEG_FS};
+
+fn error(text: &str) -> String {
+	policy::parse(text).err().unwrap()
+}
+
+#[test]
+fn parse() {
+	let policy = policy::parse("
+		# A comment
+		segment = fs
+		mask = r14 # trailing comment
+		prefixes = lock opsize
+		forbid = int3
+		forbid = ud2 int3
+		max-frame = 4096
+		data-stack = r12
+		data-stack-guard = 8192
+	").unwrap();
+
+	assert_eq!(policy.segment, P_SEG_FS);
+	assert_eq!(policy.mask_reg, Reg(14));
+	assert_eq!(policy.prefixes, LOCK | OP_SIZE);
+	assert_eq!(policy.max_frame, Some(4096));
+	assert_eq!(policy.data_stack, Some(DataStack { reg: Reg(12), guard: 8192 }));
+
+	let mut names: Vec<_> = policy.forbidden.iter().map(|f| &f.name[..]).collect();
+	names.dedup();
+	assert_eq!(names, vec!["int3", "ud2"]);
+
+	// Left out keys keep their defaults
+	let default = Policy::default();
+	let policy = policy::parse("max-frame = 64\n\n").unwrap();
+	assert_eq!((policy.segment, policy.mask_reg, policy.prefixes), (default.segment, default.mask_reg, default.prefixes));
+	assert_eq!(policy.data_stack, None);
+	assert!(policy.forbidden.is_empty());
+
+	assert_eq!(policy::parse("data-stack = r12").unwrap().data_stack.unwrap().guard, policy::DATA_STACK_GUARD);
+}
+
+#[test]
+fn errors() {
+	assert_eq!(error("segment = gs\nfoo = 1"), "line 2: unknown key `foo`");
+	assert_eq!(error("\n# comment\nsegment gs"), "line 3: expected `key = value`, found `segment gs`");
+	assert_eq!(error("segment = ds"), "line 1: `ds` is not fs or gs, which are the only segments with a base");
+	assert_eq!(error("mask = rsp"), "line 1: the stack pointer can't be the mask register");
+	assert_eq!(error("mask = xmm0"), "line 1: `xmm0` is not a general purpose register");
+	assert_eq!(error("prefixes = lock cs"), "line 1: unknown prefix `cs`");
+	assert_eq!(error("forbid = int3 frobnicate"), "line 1: unknown instruction `frobnicate`");
+	assert_eq!(error("max-frame = big"), "line 1: invalid frame size `big`");
+	assert_eq!(error("data-stack-guard = -1"), "line 1: invalid guard page size `-1`");
+
+	// Errors found after reading every line
+	assert_eq!(error("max-frame = 64\ndata-stack-guard = 4096"), "line 2: `data-stack-guard` is given without `data-stack`");
+	assert_eq!(error("data-stack = r15\nmax-frame = 64"), "line 1: the data stack register can't be the mask register");
+	assert_eq!(error("data-stack = r12\n\nmask = r12"), "line 3: the data stack register can't be the mask register");
+}
+
+#[test]
+fn forbids() {
+	let policy = policy::parse("forbid = int3 not pause").unwrap();
+
+	// int3
+	assert_eq!(policy.forbids(None, 0, &[0xCC]).map(|f| &f.name[..]), Some("int3"));
+	assert!(policy.forbids(None, 0, &[0xC3]).is_none());
+
+	// not rax is F7 /2, neg rax is F7 /3
+	assert_eq!(policy.forbids(None, 0, &[0xF7, 0xD0]).map(|f| &f.name[..]), Some("not"));
+	assert!(policy.forbids(None, 0, &[0xF7, 0xD8]).is_none());
+
+	// pause is F3 90, nop is 90
+	assert_eq!(policy.forbids(None, REP, &[0x90]).map(|f| &f.name[..]), Some("pause"));
+	assert!(policy.forbids(None, 0, &[0x90]).is_none());
+	assert!(policy.forbids(Some(1), REP, &[0x90]).is_none());
+}
@@ -0,0 +1,61 @@
+extern crate verifier;
+
+use verifier::reduce;
+use verifier::x86_decoder::{Context, DecoderError};
+
+/// push rbx; nop; mov rax, [rax]; pop rbx; ret
+const REJECTED: &'static [u8] = &[0x53, 0x90, 0x48, 0x8B, 0x00, 0x5B, 0xC3];
+
+#[test]
+fn minimize() {
+	let items: Vec<u32> = (0..20).collect();
+
+	assert_eq!(reduce::minimize(items.clone(), |x| x.contains(&3) && x.contains(&17)), vec![3, 17]);
+	assert_eq!(reduce::minimize(items.clone(), |x| x.contains(&5)), vec![5]);
+	assert_eq!(reduce::minimize(items.clone(), |x| x.len() >= 3).len(), 3);
+
+	// Nothing can be removed
+	assert_eq!(reduce::minimize(items.clone(), |x| x.len() == 20), items);
+	assert_eq!(reduce::minimize(vec![1], |_| true), vec![1]);
+}
+
+#[test]
+fn minimize_is_one_minimal() {
+	// Fails if it has two even numbers
+	let fails = |x: &[u32]| x.iter().filter(|&&i| i % 2 == 0).count() >= 2;
+	let result = reduce::minimize((1..30).collect(), &fails);
+
+	assert!(fails(&result));
+
+	for i in 0..result.len() {
+		let mut smaller = result.clone();
+		smaller.remove(i);
+		assert!(!fails(&smaller));
+	}
+}
+
+#[test]
+fn reduce_function() {
+	let ctx = Context::default();
+
+	let reduced = reduce::reduce(REJECTED, 0x1000, &ctx, None).unwrap();
+	assert_eq!(reduced.error, DecoderError::NonSegmentedMemAccess);
+	assert_eq!(reduced.insts, vec![vec![0x48, 0x8B, 0x00]]);
+
+	assert!(reduce::reduce(&[0x53, 0x5B, 0xC3], 0x1000, &ctx, None).is_none());
+}
+
+#[test]
+fn reduce_with_targets() {
+	let ctx = Context::default();
+
+	// The nop is a jump target after the push
+	let reduced = reduce::reduce(REJECTED, 0x1000, &ctx, Some(&[(0, 0), (1, 8)])).unwrap();
+	assert_eq!(reduced.error, DecoderError::NonSegmentedMemAccess);
+	assert_eq!(reduced.insts, vec![vec![0x48, 0x8B, 0x00]]);
+
+	// A wrong stack offset in the table is the reason the function is rejected, so the push and the target are kept
+	let reduced = reduce::reduce(&[0x53, 0x90, 0x5B, 0xC3], 0x1000, &ctx, Some(&[(0, 0), (1, 0)])).unwrap();
+	assert_eq!(reduced.error, DecoderError::UnbalancedStackJump);
+	assert_eq!(reduced.insts, vec![vec![0x53], vec![0x90]]);
+}
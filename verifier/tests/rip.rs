@@ -0,0 +1,27 @@
+extern crate elfloader;
+extern crate verifier;
+
+use verifier::image::{self, Selection};
+use verifier::policy::Policy;
+use verifier::x86_decoder::DecoderError;
+
+#[test]
+fn sections() {
+	let data = include_bytes!("elf/rip.o").to_vec();
+	let bin = elfloader::Image::new(&data).unwrap();
+	let report = image::verify(&bin, &[], &Policy::default(), 1, &Selection::default()).unwrap();
+
+	let error = |name: &str| {
+		let f = report.functions.iter().find(|f| f.name == name).unwrap();
+		f.failure.as_ref().map(|f| f.error.clone())
+	};
+
+	assert_eq!(error("store_data"), None);
+	assert_eq!(error("load_end"), None);
+
+	assert_eq!(error("store_rodata"), Some(DecoderError::RipWriteToReadOnly));
+	assert_eq!(error("store_text"), Some(DecoderError::RipWriteToReadOnly));
+
+	assert_eq!(error("load_past_end"), Some(DecoderError::RipAccessOutOfBounds));
+	assert_eq!(error("store_past_end"), Some(DecoderError::RipAccessOutOfBounds));
+}
@@ -0,0 +1,77 @@
+extern crate elfloader;
+extern crate verifier;
+
+use verifier::image::{self, Function, Report, Selection};
+use verifier::policy::Policy;
+use verifier::x86_decoder::{self, Context};
+
+/// The result of `check_segment` for each loaded segment of `data`, along with whether it's executable
+fn segments(data: &[u8], change: &Fn(&mut Report)) -> Vec<(bool, Result<(), &'static str>)> {
+	let bin = elfloader::Image::new(data).unwrap();
+	let mut report = image::verify(&bin, &[], &Policy::default(), 1, &Selection::default()).unwrap();
+	change(&mut report);
+
+	let mut results = Vec::new();
+
+	bin.load(|header, data| {
+		results.push((header.flags.0 & elfloader::elf::PF_X.0 != 0, image::check_segment(&bin, &report, header, data)));
+		Ok(())
+	}).unwrap();
+
+	results
+}
+
+#[test]
+fn code() {
+	let data = include_bytes!("elf/segments_code").to_vec();
+
+	assert_eq!(segments(&data, &|_| ()), vec![(true, Ok(())), (false, Ok(()))]);
+
+	// A rejected function anywhere in the image
+	let failure = x86_decoder::decode(&[0x48, 0x8B, 0x00, 0xC3], 0x1000, &Context::default()).unwrap_err();
+	let reject = |report: &mut Report| {
+		report.functions.push(Function {
+			name: "g".to_string(),
+			address: 0x1000,
+			size: 4,
+			failure: Some(failure.clone()),
+			frame: None,
+		});
+	};
+	assert_eq!(segments(&data, &reject)[0], (true, Err("executable segment holds code which failed verification")));
+}
+
+#[test]
+fn unverified_bytes() {
+	let rodata = include_bytes!("elf/segments_rodata").to_vec();
+	assert_eq!(segments(&rodata, &|_| ()), vec![(true, Err("executable segment has bytes which are not verified code or padding"))]);
+
+	let bss = include_bytes!("elf/segments_bss").to_vec();
+	assert_eq!(segments(&bss, &|_| ()), vec![(true, Err("executable segment has bytes which are not verified code or padding"))]);
+
+	let rwx = include_bytes!("elf/segments_rwx").to_vec();
+	assert_eq!(segments(&rwx, &|_| ()), vec![(true, Err("executable segment is writable"))]);
+}
+
+#[test]
+fn moved_section() {
+	// The section header points at valid code elsewhere in the file, while the segment loads other bytes
+	let mut data = include_bytes!("elf/segments_code").to_vec();
+	let (index, offset) = {
+		let bin = elfloader::Image::new(&data).unwrap();
+		bin.sections.iter().enumerate().find(|&(_, s)| bin.section_name(s) == Ok(".text")).map(|(i, s)| (i, s.offset as usize)).unwrap()
+	};
+	let unused = offset + 0x800;
+	let moved = data[offset..(offset + 3)].to_vec();
+	data[unused..(unused + 3)].copy_from_slice(&moved);
+
+	// mov rax, [rax]
+	data[offset..(offset + 3)].copy_from_slice(&[0x48, 0x8B, 0x00]);
+
+	let shoff = data[0x28] as usize | (data[0x29] as usize) << 8;
+	let field = shoff + index * 64 + 24;
+	data[field] = unused as u8;
+	data[field + 1] = (unused >> 8) as u8;
+
+	assert_eq!(segments(&data, &|_| ())[0], (true, Err("executable section is not at its address in the executable segment")));
+}
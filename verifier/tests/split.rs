@@ -0,0 +1,137 @@
+extern crate elfloader;
+extern crate verifier;
+
+use verifier::image::{self, Code, Selection};
+use verifier::policy::Policy;
+use verifier::x86_decoder::{self, Context, DecoderError, Parts};
+
+/// push rbx; jmp f.cold
+const PARENT: &'static [u8] = &[0x53, 0xE9, 0xFA, 0x0F, 0x00, 0x00];
+
+/// pop rbx; ret
+const COLD: &'static [u8] = &[0x5B, 0xC3];
+
+fn parts() -> Parts {
+	Parts {
+		starts: vec![(0, 0x1000), (PARENT.len(), 0x2000)],
+		len: PARENT.len() + COLD.len(),
+	}
+}
+
+#[test]
+fn addresses() {
+	let parts = parts();
+
+	assert_eq!(parts.address(1), 0x1001);
+	assert_eq!(parts.address(7), 0x2001);
+	assert_eq!(parts.offset(0x2001), Some(7));
+	assert_eq!(parts.offset(0x1006), None);
+	assert_eq!(parts.end(3), PARENT.len());
+}
+
+#[test]
+fn cold_parts() {
+	let ctx = Context::default();
+	let data = [PARENT, COLD].concat();
+
+	x86_decoder::decode_parts(&data, &parts(), &ctx).unwrap();
+
+	let failure = x86_decoder::decode(PARENT, 0x1000, &ctx).unwrap_err();
+	assert_eq!((failure.error, failure.target), (DecoderError::JumpOutsideOfFunction, Some(0x2000)));
+
+	// Falling through the end of a part
+	let data = [&[0x53, 0x90][..], COLD].concat();
+	let parts = Parts {
+		starts: vec![(0, 0x1000), (2, 0x2000)],
+		len: data.len(),
+	};
+	assert_eq!(x86_decoder::decode_parts(&data, &parts, &ctx).unwrap_err().error, DecoderError::OutofBounds);
+}
+
+fn names<'s>(functions: &[Code<'s>]) -> Vec<&'s str> {
+	let mut names: Vec<_> = functions.iter().map(|f| f.name).collect();
+	names.sort();
+	names
+}
+
+#[test]
+fn symbols() {
+	let data = include_bytes!("elf/split.o").to_vec();
+	let bin = elfloader::Image::new(&data).unwrap();
+	let layout = image::layout(&bin).unwrap();
+	let functions = image::functions(&bin, layout.as_ref());
+
+	// f.inner is a label inside f and the cold parts of g and h have no single parent
+	assert_eq!(names(&functions), vec!["f", "g.cold", "h", "h", "h.cold", "z"]);
+
+	let f = functions.iter().find(|f| f.name == "f").unwrap();
+	assert_eq!(f.data.len(), 11);
+	assert_eq!(names(&f.cold), vec!["f.cold"]);
+	assert_eq!(f.cold[0].section, ".text.unlikely");
+
+	// z extends to h
+	let z = functions.iter().find(|f| f.name == "z").unwrap();
+	assert_eq!((z.data, z.address), (&[0xC3][..], f.address + 0x13));
+
+	assert!(functions.iter().filter(|f| f.name != "f").all(|f| f.cold.is_empty()));
+
+	let ctx = image::context(&bin, layout.as_ref(), &[], &Policy::default()).unwrap();
+	assert!(image::verify_function(f, &ctx).failure.is_none());
+}
+
+#[test]
+fn padding() {
+	let data = include_bytes!("elf/split.o").to_vec();
+	let bin = elfloader::Image::new(&data).unwrap();
+	let layout = image::layout(&bin).unwrap();
+	let text = layout.as_ref().unwrap().addrs[1];
+
+	// The nop and int3 after f are valid, `66 CC` after the first h isn't
+	let failures = image::padding(&bin, layout.as_ref());
+	assert_eq!(failures.len(), 1);
+	assert_eq!((&failures[0].name[..], failures[0].address, failures[0].size), (".text+0x1a", text + 0x1a, 2));
+	assert_eq!(failures[0].failure.as_ref().unwrap().error, DecoderError::InvalidPadding);
+
+	let data = include_bytes!("elf/padding.o").to_vec();
+	let bin = elfloader::Image::new(&data).unwrap();
+	let layout = image::layout(&bin).unwrap();
+
+	let failures = image::padding(&bin, layout.as_ref());
+	assert_eq!(failures.len(), 1);
+	assert_eq!((&failures[0].name[..], failures[0].size), (".text+0x15", 4));
+}
+
+#[test]
+fn call_table_padding() {
+	// The slots of the call table aren't covered by symbols, but are checked as a call table instead
+	let data = include_bytes!("elf/calls.o").to_vec();
+	let bin = elfloader::Image::new(&data).unwrap();
+	let layout = image::layout(&bin).unwrap();
+
+	assert!(image::padding(&bin, layout.as_ref()).is_empty());
+
+	let report = image::verify(&bin, &[], &Policy::default(), 1, &Selection::default()).unwrap();
+	assert_eq!(report.functions.len(), 2);
+	assert!(report.passed());
+}
+
+#[test]
+fn unreached() {
+	let ctx = Context::default();
+
+	// ret; int3; nop; nop [rax]
+	x86_decoder::decode(&[0xC3, 0xCC, 0x90, 0x0F, 0x1F, 0x00], 0x1000, &ctx).unwrap();
+
+	// ret; int3; mov rax, [rax]
+	let failure = x86_decoder::decode(&[0xC3, 0xCC, 0x48, 0x8B, 0x00], 0x1000, &ctx).unwrap_err();
+	assert_eq!((failure.error, failure.offset, failure.address), (DecoderError::InvalidPadding, 2, 0x1002));
+
+	// Padding at the end of the parent doesn't continue into the cold part
+	let data = [&[0x53, 0xE9, 0xFB, 0x0F, 0x00, 0x00, 0x0F, 0x1F][..], &[0x00, 0x5B, 0xC3]].concat();
+	let parts = Parts {
+		starts: vec![(0, 0x1000), (8, 0x2000)],
+		len: data.len(),
+	};
+	let failure = x86_decoder::decode_parts(&data, &parts, &ctx).unwrap_err();
+	assert_eq!((failure.error, failure.offset), (DecoderError::InvalidPadding, 6));
+}
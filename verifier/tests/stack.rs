@@ -0,0 +1,135 @@
+extern crate verifier;
+
+use verifier::image::{self, Code, Function};
+use verifier::stack::{self, Unbounded};
+use verifier::x86_decoder::{CallSite, Context, DecoderError, Frame};
+
+/// A verified function at `address` using `depth` bytes, with calls given by their stack offset and callee
+fn function(name: &str, address: u64, depth: u32, calls: &[(u32, Option<u64>)]) -> Function {
+	Function {
+		name: name.to_string(),
+		address: address,
+		size: 0x10,
+		failure: None,
+		frame: Some(Frame {
+			depth: depth,
+			calls: calls.iter().enumerate().map(|(i, &(stack_offset, callee))| {
+				CallSite {
+					offset: i,
+					address: address + i as u64,
+					stack_offset: stack_offset,
+					callee: callee,
+				}
+			}).collect(),
+		}),
+	}
+}
+
+fn verify(name: &'static str, address: u64, data: &'static [u8]) -> Function {
+	let code = Code {
+		name: name,
+		section: ".text",
+		address: address,
+		data: data,
+		cold: Vec::new(),
+	};
+	image::verify_function(&code, &Context::default())
+}
+
+#[test]
+fn straight_line() {
+	// push rbx; call g; pop rbx; ret
+	let f = verify("f", 0x1000, &[0x53, 0xE8, 0xFA, 0x0F, 0x00, 0x00, 0x5B, 0xC3]);
+	// sub rsp, 0x18; add rsp, 0x18; ret
+	let g = verify("g", 0x2000, &[0x48, 0x83, 0xEC, 0x18, 0x48, 0x83, 0xC4, 0x18, 0xC3]);
+
+	let frame = f.frame.as_ref().unwrap();
+	assert_eq!(frame.depth, 8);
+	assert_eq!(frame.calls.len(), 1);
+	assert_eq!((frame.calls[0].address, frame.calls[0].stack_offset, frame.calls[0].callee), (0x1001, 8, Some(0x2000)));
+	assert_eq!(g.frame.as_ref().unwrap().depth, 0x18);
+
+	// f pushes rbx and the return address before g's frame
+	assert_eq!(stack::worst_case(&[f, g]), vec![Ok(0x28), Ok(0x18)]);
+}
+
+#[test]
+fn negative_immediates() {
+	// add rsp, -8; sub rsp, -8; ret
+	let f = verify("f", 0x1000, &[0x48, 0x83, 0xC4, 0xF8, 0x48, 0x83, 0xEC, 0xF8, 0xC3]);
+	assert_eq!(f.frame.as_ref().unwrap().depth, 8);
+
+	// add rsp, -0x100; sub rsp, -0x100; ret
+	let g = verify("g", 0x2000, &[0x48, 0x81, 0xC4, 0x00, 0xFF, 0xFF, 0xFF, 0x48, 0x81, 0xEC, 0x00, 0xFF, 0xFF, 0xFF, 0xC3]);
+	assert_eq!(g.frame.as_ref().unwrap().depth, 0x100);
+
+	// sub rsp, -8 pops the return address
+	let h = verify("h", 0x3000, &[0x48, 0x83, 0xEC, 0xF8, 0xC3]);
+	assert_eq!(h.failure.unwrap().error, DecoderError::StackOverflow);
+}
+
+#[test]
+fn deepest_call() {
+	let functions = [
+		function("a", 0x10, 0x40, &[(0x8, Some(0x20)), (0x30, Some(0x30))]),
+		function("b", 0x20, 0x100, &[]),
+		function("c", 0x30, 0x10, &[(0, Some(0x20))]),
+	];
+
+	assert_eq!(stack::worst_case(&functions), vec![Ok(0x140), Ok(0x100), Ok(0x108)]);
+}
+
+#[test]
+fn recursion() {
+	let functions = [
+		function("a", 0x10, 8, &[(8, Some(0x20))]),
+		function("b", 0x20, 8, &[(8, Some(0x10))]),
+		function("c", 0x30, 8, &[(8, Some(0x10))]),
+		function("d", 0x40, 8, &[(8, Some(0x40))]),
+	];
+
+	let cycle = Unbounded::Recursion(vec!["a".to_string(), "b".to_string()]);
+	let results = stack::worst_case(&functions);
+
+	assert_eq!(results[..3], [Err(cycle.clone()), Err(cycle.clone()), Err(cycle)]);
+	assert_eq!(results[3], Err(Unbounded::Recursion(vec!["d".to_string()])));
+	assert_eq!(results[0].as_ref().unwrap_err().to_string(), "recursion through a -> b");
+}
+
+#[test]
+fn unbounded_calls() {
+	let mut unverified = function("u", 0x40, 0, &[]);
+	unverified.frame = None;
+
+	let functions = [
+		function("indirect", 0x10, 8, &[(0, Some(0x30)), (8, None)]),
+		function("unknown", 0x20, 8, &[(8, Some(0x1000))]),
+		function("leaf", 0x30, 8, &[]),
+		unverified,
+		function("caller", 0x50, 8, &[(8, Some(0x30)), (8, Some(0x40))]),
+	];
+
+	assert_eq!(stack::worst_case(&functions), vec![
+		Err(Unbounded::IndirectCall(0x11)),
+		Err(Unbounded::UnknownCallee(0x1000)),
+		Ok(8),
+		Err(Unbounded::Unverified("u".to_string())),
+		Err(Unbounded::Unverified("u".to_string())),
+	]);
+}
+
+#[test]
+fn entry_points() {
+	let mut unverified = function("u", 0x40, 0, &[]);
+	unverified.frame = None;
+
+	let functions = [
+		function("a", 0x10, 8, &[(8, Some(0x20)), (8, None)]),
+		function("b", 0x20, 8, &[(8, Some(0x20))]),
+		function("c", 0x30, 8, &[(8, Some(0x30))]),
+		unverified,
+	];
+
+	// Only a calls b, and c only calls itself
+	assert_eq!(stack::entry_points(&functions), vec![true, false, true, true]);
+}
@@ -0,0 +1,63 @@
+extern crate elfloader;
+extern crate verifier;
+
+use verifier::image::{self, Code, Selection};
+use verifier::policy::Policy;
+use verifier::stats::{self, Stats};
+use verifier::x86_decoder::Context;
+
+fn stats(data: &[u8]) -> Stats {
+	let code = Code {
+		name: "f",
+		section: ".text",
+		address: 0,
+		data: data,
+		cold: Vec::new(),
+	};
+	let ctx = Context::default();
+	let mut stats = Stats::default();
+	stats.add(&code, &image::verify_function(&code, &ctx), &ctx);
+	stats
+}
+
+#[test]
+fn histograms() {
+	// push rbx; mov rax, [rsp + 8]; add rax, 1; pop rbx; ret
+	let stats = stats(&[0x53, 0x48, 0x8B, 0x44, 0x24, 0x08, 0x48, 0x83, 0xC0, 0x01, 0x5B, 0xC3]);
+
+	assert_eq!((stats.functions, stats.failed, stats.bytes, stats.insts), (1, 0, 12, 5));
+	assert_eq!(stats.kinds.get("Push"), 1);
+	assert_eq!(stats.kinds.get("Pop"), 1);
+	assert_eq!(stats.kinds.get("Ret"), 1);
+	assert_eq!(stats.opcodes.get("8B"), 1);
+	assert_eq!(stats.opcodes.get("83 /0"), 1);
+	assert_eq!(stats.prefixes.get("REX.W"), 2);
+	assert_eq!(stats.addressing.get("stack"), 1);
+	assert_eq!(stats.addressing.get("register"), 1);
+	assert_eq!(stats.sizes.get(&0), Some(&1));
+}
+
+#[test]
+fn errors() {
+	// mov rax, [rax]; ret
+	let mut total = stats(&[0x48, 0x8B, 0x00, 0xC3]);
+	total.merge(&stats(&[0x90; 40]));
+
+	assert_eq!((total.functions, total.failed, total.insts), (2, 2, 42));
+	assert_eq!(total.errors.get("NonSegmentedMemAccess"), 1);
+	assert_eq!(total.errors.get("OutofBounds"), 1);
+	assert_eq!(total.kinds.get("rejected"), 1);
+	assert_eq!(total.sizes.get(&32), Some(&1));
+	assert!(total.report().contains("32-63 bytes"));
+}
+
+#[test]
+fn padding() {
+	let data = include_bytes!("elf/padding.o").to_vec();
+	let bin = elfloader::Image::new(&data).unwrap();
+	let stats = stats::collect(&bin, &[], &Policy::default(), 1, &Selection::default()).unwrap();
+
+	assert_eq!((stats.functions, stats.failed, stats.padding), (3, 0, 1));
+	assert_eq!(stats.errors.get("InvalidPadding"), 1);
+	assert!(stats.summary().contains("1 invalid padding range(s)"));
+}
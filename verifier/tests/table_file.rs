@@ -0,0 +1,76 @@
+extern crate verifier;
+
+use verifier::{disasm, table};
+use verifier::effect::{Access, Effect, Operand, Size};
+use verifier::table_file::{self, EffectTable, TableError};
+
+#[test]
+fn round_trip() {
+	let cases = disasm::cases(true);
+	let hash = table_file::current_hash();
+	let data = table_file::write(&cases, hash).unwrap();
+	let table = EffectTable::load(&data, hash).unwrap();
+
+	assert_eq!(table.len(), cases.len());
+
+	for case in &cases {
+		let effects: Vec<_> = case.1.iter().cloned().filter(|&e| e != Effect::None).collect();
+		assert_eq!(table.get(&case.0), Some(&effects[..]), "effects of {}", table::bytes(&case.0));
+	}
+}
+
+#[test]
+fn rejects_invalid_tables() {
+	let cases = disasm::cases(true);
+	let data = table_file::write(&cases, 1).unwrap();
+
+	assert_eq!(EffectTable::load(&data, 2).err(), Some(TableError::Stale { expected: 2, found: 1 }));
+	assert_eq!(EffectTable::load(&data[1..], 1).err(), Some(TableError::BadMagic));
+	assert_eq!(EffectTable::load(&data[..40], 1).err(), Some(TableError::BadChecksum));
+
+	let mut version = data.clone();
+	version[8] += 1;
+	assert_eq!(EffectTable::load(&version, 1).err(), Some(TableError::UnsupportedVersion(table_file::VERSION + 1)));
+
+	let mut corrupt = data.clone();
+	corrupt[30] ^= 1;
+	assert_eq!(EffectTable::load(&corrupt, 1).err(), Some(TableError::BadChecksum));
+}
+
+#[test]
+fn rejects_too_many_effects() {
+	let mut cases = disasm::cases(true);
+	cases.truncate(1);
+	cases[0].1 = vec![Effect::Imm8; 256];
+
+	assert_eq!(table_file::write(&cases, 1).err(), Some(TableError::TooManyEffects(256)));
+
+	cases[0].1.pop();
+	assert!(table_file::write(&cases, 1).is_ok());
+}
+
+#[test]
+fn generator_hash() {
+	let mut ops = Vec::new();
+	table::list_insts(&mut ops, true);
+
+	let hash = table_file::generator_hash(&ops);
+	assert_eq!(table_file::generator_hash(&ops.clone()), hash);
+
+	// Every field is hashed
+	let mut changed = ops.clone();
+	changed[0].operand_size = Size::S128;
+	assert!(table_file::generator_hash(&changed) != hash);
+
+	let mut changed = ops.clone();
+	changed[0].operands.push((Operand::Imm(Size::S8), Size::S8, Access::Read));
+	assert!(table_file::generator_hash(&changed) != hash);
+
+	let mut changed = ops.clone();
+	changed[0].vex = !changed[0].vex;
+	assert!(table_file::generator_hash(&changed) != hash);
+
+	let mut changed = ops.clone();
+	changed.pop();
+	assert!(table_file::generator_hash(&changed) != hash);
+}
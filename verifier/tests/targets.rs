@@ -0,0 +1,27 @@
+extern crate elfloader;
+extern crate verifier;
+
+use verifier::image::{self, Selection};
+use verifier::policy::Policy;
+
+#[test]
+fn keyed_by_address() {
+	// Two local functions named `f` in .text.a and .text.b
+	let data = include_bytes!("elf/targets.o").to_vec();
+	let bin = elfloader::Image::new(&data).unwrap();
+	let layout = image::layout(&bin).unwrap();
+
+	let functions = image::functions(&bin, layout.as_ref());
+	let a = functions.iter().find(|c| c.section == ".text.a").unwrap();
+	let b = functions.iter().find(|c| c.section == ".text.b").unwrap();
+	assert_eq!((a.name, b.name), ("f", "f"));
+
+	let tables = image::targets(&bin, layout.as_ref()).unwrap().unwrap();
+	assert_eq!(tables.len(), 2);
+	assert_eq!(tables[&a.address], vec![(0, 0)]);
+	assert_eq!(tables[&b.address], vec![(0, 0), (1, 8)]);
+
+	let report = image::verify(&bin, &[], &Policy::default(), 1, &Selection::default()).unwrap();
+	assert_eq!(report.functions.len(), 2);
+	assert!(report.passed());
+}
@@ -0,0 +1,34 @@
+extern crate verifier;
+
+use verifier::image::{self, Code};
+use verifier::x86_decoder::Context;
+
+/// Functions which alternately pass and fail in different ways, so results out of order are noticed
+fn functions() -> Vec<Code<'static>> {
+	// ret, push rbx; pop rbx; ret, jmp rax and sub rsp, 0x18; ret
+	let bodies: [&'static [u8]; 4] = [&[0xC3], &[0x53, 0x5B, 0xC3], &[0xFF, 0xE0], &[0x48, 0x83, 0xEC, 0x18, 0xC3]];
+
+	(0..23).map(|i| {
+		Code {
+			name: "f",
+			section: ".text",
+			address: 0x1000 + i as u64 * 0x10,
+			data: bodies[i % bodies.len()],
+			cold: Vec::new(),
+		}
+	}).collect()
+}
+
+#[test]
+fn input_order() {
+	let functions = functions();
+	let ctx = Context::default();
+	let serial = image::verify_functions(&functions, &ctx, 1);
+
+	assert_eq!(serial.iter().map(|f| f.address).collect::<Vec<_>>(), functions.iter().map(|f| f.address).collect::<Vec<_>>());
+
+	for &threads in &[2, 3, 4, 8, 23, 64] {
+		let results = image::verify_functions(&functions, &ctx, threads);
+		assert_eq!(format!("{:?}", results), format!("{:?}", serial), "{} threads", threads);
+	}
+}